@@ -0,0 +1,341 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use data::tables::nav_status::{NavStatus, NavStatusValue};
+use data::tables::{MMSIType, Ships};
+use linesonmaps::algo::stop_cluster::{DbScanConf, StopOrLs, cluster_to_traj_with_stop_object};
+use linesonmaps::types::measure;
+use linesonmaps::types::pointm::PointM;
+use rayon::prelude::*;
+
+type Interval = (DateTime<Utc>, DateTime<Utc>);
+
+/// Temporal agreement between detected stops and labelled stops, as durations such that scores can be summed over vessels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Scores {
+    /// Total duration of the detected stops
+    pub detected: TimeDelta,
+    /// Total duration of the labelled stops
+    pub labelled: TimeDelta,
+    /// Duration that is both detected and labelled as a stop
+    pub overlap: TimeDelta,
+}
+
+impl Scores {
+    /// Fraction of the detected duration that is labelled, [`None`] if nothing was detected
+    pub fn precision(&self) -> Option<f64> {
+        ratio(self.overlap, self.detected)
+    }
+
+    /// Fraction of the labelled duration that is detected, [`None`] if nothing is labelled
+    pub fn recall(&self) -> Option<f64> {
+        ratio(self.overlap, self.labelled)
+    }
+
+    /// Harmonic mean of precision and recall, 0 if either is missing
+    pub fn f1(&self) -> f64 {
+        ratio(self.overlap * 2, self.detected + self.labelled).unwrap_or(0.)
+    }
+
+    /// Intersection over union of the detected and labelled durations, [`None`] if both are empty
+    pub fn iou(&self) -> Option<f64> {
+        ratio(self.overlap, self.detected + self.labelled - self.overlap)
+    }
+}
+
+impl std::ops::Add for Scores {
+    type Output = Scores;
+
+    fn add(self, rhs: Scores) -> Scores {
+        Scores {
+            detected: self.detected + rhs.detected,
+            labelled: self.labelled + rhs.labelled,
+            overlap: self.overlap + rhs.overlap,
+        }
+    }
+}
+
+impl std::iter::Sum for Scores {
+    fn sum<I: Iterator<Item = Scores>>(iter: I) -> Scores {
+        iter.fold(Scores::default(), |acc, s| acc + s)
+    }
+}
+
+fn ratio(a: TimeDelta, b: TimeDelta) -> Option<f64> {
+    (b > TimeDelta::zero()).then(|| a.as_seconds_f64() / b.as_seconds_f64())
+}
+
+/// Scores of a stop detection per vessel and overall, the overall scores are over the summed durations of every vessel
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Evaluation {
+    pub per_vessel: Vec<(MMSIType, Scores)>,
+    pub overall: Scores,
+}
+
+/// Sorted, disjoint intervals covering the same time as `intervals`
+fn union(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.sort_unstable();
+    let mut res: Vec<Interval> = vec![];
+    for (start, end) in intervals {
+        match res.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => res.push((start, end)),
+        }
+    }
+    res
+}
+
+fn duration(intervals: &[Interval]) -> TimeDelta {
+    intervals.iter().map(|(start, end)| *end - *start).sum()
+}
+
+/// Duration covered by both lists of sorted, disjoint intervals
+fn overlap(a: &[Interval], b: &[Interval]) -> TimeDelta {
+    let (mut i, mut j, mut res) = (0, 0, TimeDelta::zero());
+    while i < a.len() && j < b.len() {
+        let (start, end) = (a[i].0.max(b[j].0), a[i].1.min(b[j].1));
+        if start < end {
+            res += end - start;
+        }
+        match a[i].1 < b[j].1 {
+            true => i += 1,
+            false => j += 1,
+        }
+    }
+    res
+}
+
+/// Compares detected stop intervals with labelled stop intervals
+pub fn score(detected: Vec<Interval>, labelled: Vec<Interval>) -> Scores {
+    let (detected, labelled) = (union(detected), union(labelled));
+    Scores {
+        detected: duration(&detected),
+        labelled: duration(&labelled),
+        overlap: overlap(&detected, &labelled),
+    }
+}
+
+/// The intervals in which `mmsi` reported to be at anchor or moored, clipped to `period`
+pub fn labelled_stops(nav_status: &NavStatus, mmsi: MMSIType, period: Interval) -> Vec<Interval> {
+    (0..nav_status.mmsi.len())
+        .filter(|&i| nav_status.mmsi[i] == mmsi)
+        .filter(|&i| {
+            matches!(
+                nav_status.nav_status[i],
+                NavStatusValue::Anchored | NavStatusValue::Moord
+            )
+        })
+        .map(|i| {
+            (
+                nav_status.time_begin[i].max(period.0),
+                nav_status.time_end[i].min(period.1),
+            )
+        })
+        .filter(|(start, end)| start < end)
+        .collect()
+}
+
+/// Runs the stop detection of `conf` over the trajectory of `mmsi`, along with the period covered by the trajectory.
+///
+/// The SOG of every position is interpolated from the reports of the ship, positions without reports around them are never part of a stop.
+pub fn detect_stops<Dist>(
+    ships: &Ships,
+    mmsi: MMSIType,
+    conf: &mut DbScanConf<Dist, 4326>,
+) -> Option<(Interval, Vec<Interval>)>
+where
+    Dist: Fn(&PointM<4326>, &PointM<4326>) -> f64 + Send + Sync,
+{
+    let ls = ships.trajectories.search_by_key(mmsi).ok()?;
+    let period = (
+        measure::to_datetime(ls.0.first()?.m)?,
+        measure::to_datetime(ls.0.last()?.m)?,
+    );
+
+    let points = ls
+        .points()
+        .map(|p| {
+            let sog = p.time().and_then(|t| ships.sog.interpolate(mmsi, t).ok());
+            (p, sog.unwrap_or(f32::INFINITY))
+        })
+        .collect::<Vec<_>>();
+    let stops = cluster_to_traj_with_stop_object(conf.run(&points))
+        .0
+        .into_iter()
+        .filter_map(|s| match s {
+            StopOrLs::Stop { tz_tange, .. } => Some(tz_tange),
+            StopOrLs::LS(_) => None,
+        })
+        .collect();
+
+    Some((period, stops))
+}
+
+/// Scores the stop detection of `conf` against the moored and at anchor intervals of the [`NavStatus`] of every ship with a trajectory.
+///
+/// The status is a noisy label, e.g. ships often forget to switch it, so the scores are best used to compare parameters rather than as absolute numbers.
+/// Labels are clipped to the period covered by the trajectory of the ship. Relies on the `b_tree_index` of the [`Sog`](data::tables::sog::Sog) table being populated.
+pub fn evaluate<Dist>(ships: &Ships, conf: &mut DbScanConf<Dist, 4326>) -> Evaluation
+where
+    Dist: Fn(&PointM<4326>, &PointM<4326>) -> f64 + Send + Sync,
+{
+    let per_vessel = ships
+        .trajectories
+        .mmsi
+        .iter()
+        .filter_map(|&mmsi| {
+            let (period, detected) = detect_stops(ships, mmsi, conf)?;
+            let labelled = labelled_stops(&ships.nav_status, mmsi, period);
+            Some((mmsi, score(detected, labelled)))
+        })
+        .collect::<Vec<_>>();
+
+    Evaluation {
+        overall: per_vessel.iter().map(|(_, s)| *s).sum(),
+        per_vessel,
+    }
+}
+
+/// Evaluates every parameter set of `params` in parallel, see [`evaluate`], `conf` builds the configuration of a parameter set.
+///
+/// Results are in the order of `params`.
+pub fn sweep<P, Dist>(
+    ships: &Ships,
+    params: impl IntoIterator<Item = P>,
+    conf: impl Fn(&P) -> DbScanConf<Dist, 4326> + Sync,
+) -> Vec<(P, Evaluation)>
+where
+    P: Send,
+    Dist: Fn(&PointM<4326>, &PointM<4326>) -> f64 + Send + Sync,
+{
+    params
+        .into_iter()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|p| {
+            let evaluation = evaluate(ships, &mut conf(&p));
+            (p, evaluation)
+        })
+        .collect()
+}
+
+/// The result of a [`sweep`] with the highest overall F1 score, the first of equally good ones
+pub fn best<P>(results: &[(P, Evaluation)]) -> Option<&(P, Evaluation)> {
+    results
+        .iter()
+        .rev()
+        .max_by(|(_, a), (_, b)| a.overall.f1().total_cmp(&b.overall.f1()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::tables::{
+        cog::Cog, dimensions::Dimensions, gps_position::GPSPosition, rot::Rot,
+        ship_draught::Draught, sog::Sog, trajectories::Trajectories,
+    };
+    use geo::{Distance, Geodesic};
+    use linesonmaps::types::linestringm::LineStringM;
+
+    fn t(minutes: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_secs(minutes * 60).unwrap()
+    }
+
+    /// Moored for an hour, sailing north for an hour, and waiting for an hour without reporting a status
+    fn ships() -> Ships {
+        let mut sog = Sog::new();
+        let mut coords = vec![];
+        for minute in 0..180 {
+            let (lat, speed) = match minute {
+                0..60 => (56., 0.),
+                60..120 => (56. + (minute - 60) as f64 * 0.003, 10.),
+                _ => (56.18, 0.),
+            };
+            coords.push((10., lat, measure::from_datetime(t(minute))).into());
+            sog.mmsi.push(1);
+            sog.time.push(t(minute));
+            sog.sog.push(speed);
+            sog.b_tree_index.insert((1, t(minute)), sog.mmsi.len() - 1);
+        }
+
+        let mut nav_status = NavStatus::new();
+        for (mmsi, tb, te, status) in [
+            (1, 0, 59, NavStatusValue::Moord),
+            (1, 59, 179, NavStatusValue::UnderWayUsingEngine),
+            // another ship, without a trajectory
+            (2, 0, 179, NavStatusValue::Anchored),
+        ] {
+            nav_status.mmsi.push(mmsi);
+            nav_status.time_begin.push(t(tb));
+            nav_status.time_end.push(t(te));
+            nav_status.nav_status.push(status);
+        }
+
+        Ships {
+            nav_status,
+            ship_draught: Draught::new(),
+            cog: Cog::new(),
+            sog,
+            rot: Rot::new(),
+            gps_position: GPSPosition::new(),
+            dimensions: Dimensions::new(),
+            trajectories: Trajectories {
+                mmsi: vec![1],
+                trajectory: vec![LineStringM::new(coords).unwrap()],
+            },
+        }
+    }
+
+    fn conf(speed_thres: f32) -> DbScanConf<impl Fn(&PointM, &PointM) -> f64 + Send + Sync, 4326> {
+        DbScanConf::builder()
+            .dist(|a: &PointM<4326>, b: &PointM<4326>| Geodesic.distance(*a, *b))
+            .max_time_thres(TimeDelta::minutes(30))
+            .min_cluster_size(5.try_into().unwrap())
+            .speed_thres(speed_thres)
+            .dist_thres(100.)
+            .build()
+    }
+
+    #[test]
+    fn interval_scores() {
+        let scores = score(vec![(t(0), t(30)), (t(20), t(60))], vec![(t(30), t(90))]);
+        assert_eq!(
+            scores,
+            Scores {
+                detected: TimeDelta::minutes(60),
+                labelled: TimeDelta::minutes(60),
+                overlap: TimeDelta::minutes(30),
+            }
+        );
+        assert_eq!(scores.precision(), Some(0.5));
+        assert_eq!(scores.recall(), Some(0.5));
+        assert_eq!(scores.f1(), 0.5);
+        assert_eq!(scores.iou(), Some(1. / 3.));
+
+        let empty = score(vec![], vec![]);
+        assert_eq!(
+            (empty.precision(), empty.f1(), empty.iou()),
+            (None, 0., None)
+        );
+    }
+
+    #[test]
+    fn evaluate_against_nav_status() {
+        let ships = ships();
+        let evaluation = evaluate(&ships, &mut conf(1.5));
+        assert_eq!(evaluation.per_vessel.len(), 1);
+        let scores = evaluation.overall;
+        assert_eq!(evaluation.per_vessel[0], (1, scores));
+        // both stops are found, but only the first is labelled
+        assert_eq!(scores.labelled, TimeDelta::minutes(59));
+        assert_eq!(scores.recall(), Some(1.));
+        assert!(
+            (scores.precision().unwrap() - 0.5).abs() < 0.05,
+            "{scores:?}"
+        );
+
+        // no ship is that slow
+        let results = sweep(&ships, [0., 1.5], |speed_thres| conf(*speed_thres));
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].1.overall.detected, TimeDelta::zero());
+        assert_eq!(best(&results).unwrap().0, 1.5);
+    }
+}
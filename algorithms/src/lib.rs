@@ -1,3 +1,4 @@
+pub mod evaluation;
 pub mod lines;
 
 pub fn add(left: u64, right: u64) -> u64 {
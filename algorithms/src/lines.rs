@@ -1,13 +1,70 @@
 use std::time::Duration;
+
+use data::tables::MMSIType;
+use data::tables::trajectories::Trajectories;
+use geo::{Distance, Geodesic, Haversine};
+use linesonmaps::algo::segmenter::TrajectorySplit;
+use linesonmaps::algo::strategy::{SegmentationStrategy, segment};
+use linesonmaps::types::linestringm::LineStringM;
+use linesonmaps::types::pointm::PointM;
+use rayon::prelude::*;
+
 type EuclidianDist = f32;
+
+/// How the distance and time gap between two subsequent points are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Combine {
+    /// Split if either gap is too large
+    #[default]
+    Or,
+    /// Only split if both gaps are too large, e.g. to keep ships that are stationary during an AIS outage in one piece
+    And,
+}
+
+/// The metric used for the distance gap and the length of sub-trajectories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Metric {
+    /// Distance on the ellipsoid, see [`Geodesic`]
+    #[default]
+    Geodesic,
+    /// Distance on a sphere, faster but off by up to 0.5%, see [`Haversine`]
+    Haversine,
+}
+
+impl Metric {
+    fn distance(&self, from: PointM, to: PointM) -> f64 {
+        match self {
+            Metric::Geodesic => Geodesic.distance(from, to),
+            Metric::Haversine => Haversine.distance(from, to),
+        }
+    }
+}
+
+/// Separates trajectories at gaps in space and/or time, e.g. where the AIS signal of a ship was lost.
+///
+/// Splits that are too short to be useful can be dropped, see [`SeperateConfBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct SeperateConf {
     pub distance: EuclidianDist, // meter
     pub time: Duration,
+    pub combine: Combine,
+    pub metric: Metric,
+    /// Sub-trajectories shorter than this are dropped (meter)
+    pub min_length: EuclidianDist,
+    /// Sub-trajectories lasting less than this are dropped
+    pub min_duration: Duration,
+    /// Whether splits of a single point are dropped
+    pub drop_points: bool,
 }
 
 pub struct SeperateConfBuilder {
     distance: Option<EuclidianDist>,
     time: Option<Duration>,
+    combine: Option<Combine>,
+    metric: Option<Metric>,
+    min_length: Option<EuclidianDist>,
+    min_duration: Option<Duration>,
+    drop_points: Option<bool>,
 }
 
 impl SeperateConfBuilder {
@@ -15,22 +72,201 @@ impl SeperateConfBuilder {
         SeperateConfBuilder {
             distance: None,
             time: None,
+            combine: None,
+            metric: None,
+            min_length: None,
+            min_duration: None,
+            drop_points: None,
         }
     }
 
-    pub fn distance(&mut self, distance: EuclidianDist) {
-        self.distance = Some(distance)
+    pub fn distance(&mut self, distance: EuclidianDist) -> &mut Self {
+        self.distance = Some(distance);
+        self
     }
 
-    pub fn time(&mut self, time: Duration) {
-        self.time = Some(time)
+    pub fn time(&mut self, time: Duration) -> &mut Self {
+        self.time = Some(time);
+        self
+    }
+
+    pub fn combine(&mut self, combine: Combine) -> &mut Self {
+        self.combine = Some(combine);
+        self
+    }
+
+    pub fn metric(&mut self, metric: Metric) -> &mut Self {
+        self.metric = Some(metric);
+        self
+    }
+
+    pub fn min_length(&mut self, min_length: EuclidianDist) -> &mut Self {
+        self.min_length = Some(min_length);
+        self
+    }
+
+    pub fn min_duration(&mut self, min_duration: Duration) -> &mut Self {
+        self.min_duration = Some(min_duration);
+        self
+    }
+
+    pub fn drop_points(&mut self, drop_points: bool) -> &mut Self {
+        self.drop_points = Some(drop_points);
+        self
     }
 
     pub fn build(&self) -> SeperateConf {
         SeperateConf {
             distance: self.distance.unwrap_or(1000.0),
             time: self.time.unwrap_or(Duration::from_secs(60)), // Should be set to whatever we find to be the best value.
+            combine: self.combine.unwrap_or_default(),
+            metric: self.metric.unwrap_or_default(),
+            min_length: self.min_length.unwrap_or(0.0),
+            min_duration: self.min_duration.unwrap_or(Duration::ZERO),
+            drop_points: self.drop_points.unwrap_or(false),
         }
     }
 }
 
+impl Default for SeperateConfBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for SeperateConf {
+    fn default() -> Self {
+        SeperateConfBuilder::new().build()
+    }
+}
+
+/// Splits where the gap between two subsequent points is too large, the minimum length, duration and point filters are not applied.
+impl SegmentationStrategy<4326> for SeperateConf {
+    fn split_before(&mut self, ls: &LineStringM, i: usize) -> bool {
+        let (from, to) = (PointM::from(ls.0[i - 1]), PointM::from(ls.0[i]));
+        let distance = self.metric.distance(from, to) >= f64::from(self.distance);
+        let time = to.coord.m - from.coord.m >= self.time.as_secs_f64();
+        match self.combine {
+            Combine::Or => distance || time,
+            Combine::And => distance && time,
+        }
+    }
+}
+
+impl SeperateConf {
+    /// Separates `ls` at its gaps and drops the splits that are too short
+    pub fn separate(&self, ls: LineStringM) -> Vec<TrajectorySplit<4326>> {
+        segment(ls, &mut { *self })
+            .into_iter()
+            .filter(|split| self.keep(split))
+            .collect()
+    }
+
+    /// Separates every trajectory of the table in parallel, see [`SeperateConf::separate`]
+    pub fn separate_all(
+        &self,
+        trajectories: &Trajectories,
+    ) -> Vec<(MMSIType, Vec<TrajectorySplit<4326>>)> {
+        trajectories
+            .mmsi
+            .par_iter()
+            .zip(trajectories.trajectory.par_iter())
+            .map(|(mmsi, ls)| (*mmsi, self.separate(ls.clone())))
+            .collect()
+    }
+
+    fn keep(&self, split: &TrajectorySplit<4326>) -> bool {
+        match split {
+            TrajectorySplit::Point(_) => !self.drop_points,
+            TrajectorySplit::SubTrajectory(ls) => {
+                let (first, last) = (ls.0[0], ls.0[ls.0.len() - 1]);
+                let length: f64 = ls.lines().map(|l| self.metric.distance(l.from, l.to)).sum();
+                length >= f64::from(self.min_length)
+                    && last.m - first.m >= self.min_duration.as_secs_f64()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lengths(splits: &[TrajectorySplit<4326>]) -> Vec<usize> {
+        splits
+            .iter()
+            .map(|s| match s {
+                TrajectorySplit::Point(_) => 1,
+                TrajectorySplit::SubTrajectory(ls) => ls.0.len(),
+            })
+            .collect()
+    }
+
+    /// Northwards at about 11 m/s, with a 2 minute gap without moving, a jump of 5.5 km and a stray point
+    fn trajectory() -> LineStringM {
+        let lat = |i: u32| 56. + f64::from(i) * 0.0001;
+        let first = (0..10).map(|i| (10., lat(i), f64::from(i)));
+        let gap = (10..20).map(|i| {
+            (
+                10.,
+                lat(9) + f64::from(i - 10) * 0.0001,
+                120. + f64::from(i),
+            )
+        });
+        let jump = (20..25).map(|i| (10., lat(i) + 0.05, 120. + f64::from(i)));
+        let stray = std::iter::once((10., lat(25) + 0.05, 1000.));
+        LineStringM::new(
+            first
+                .chain(gap)
+                .chain(jump)
+                .chain(stray)
+                .map(Into::into)
+                .collect(),
+        )
+        .expect("measures are increasing")
+    }
+
+    #[test]
+    fn combine_gaps() {
+        let or = SeperateConfBuilder::new().build();
+        assert_eq!(lengths(&or.separate(trajectory())), vec![10, 10, 5, 1]);
+
+        // none of the gaps is both, e.g. the jump happens within a second
+        let and = SeperateConfBuilder::new().combine(Combine::And).build();
+        assert_eq!(lengths(&and.separate(trajectory())), vec![26]);
+
+        let haversine = SeperateConfBuilder::new().metric(Metric::Haversine).build();
+        assert_eq!(haversine.separate(trajectory()), or.separate(trajectory()));
+    }
+
+    #[test]
+    fn drop_short_splits() {
+        let conf = SeperateConfBuilder::new()
+            .min_duration(Duration::from_secs(5))
+            .min_length(50.)
+            .build();
+        assert_eq!(lengths(&conf.separate(trajectory())), vec![10, 10, 1]);
+
+        let conf = SeperateConfBuilder::new()
+            .min_length(500.)
+            .drop_points(true)
+            .build();
+        assert!(conf.separate(trajectory()).is_empty());
+    }
+
+    #[test]
+    fn separate_table() {
+        let trajectories = Trajectories {
+            mmsi: vec![1, 2],
+            trajectory: vec![
+                trajectory(),
+                LineStringM::new(vec![]).expect("empty is valid"),
+            ],
+        };
+        let conf = SeperateConf::default();
+        assert_eq!(
+            conf.separate_all(&trajectories),
+            vec![(1, conf.separate(trajectory())), (2, vec![])]
+        );
+    }
+}
@@ -4,12 +4,13 @@ use crate::tables::*;
 use chrono::TimeDelta;
 use itertools::Itertools;
 use linesonmaps::algo::segmenter::TrajectorySplit;
+use linesonmaps::types::ewkb::{FromEwkb, ToEwkb};
 use linesonmaps::types::linestringm::LineStringM;
+use linesonmaps::types::measure;
 use postgres::types::Type;
 use postgres::{Client, Config, NoTls, Statement, Transaction};
 use std::collections::HashSet;
 use std::io::Write;
-use wkb::reader::read_wkb;
 
 pub struct DbConn {
     pub conn: Client,
@@ -107,7 +108,7 @@ pub fn insert_sub_traj_inteval(
                     format!(
                         "{0}\t{1}\t{2}\n",
                         g.0,
-                        tstz.timestamp_millis() as f64 / 1000_f64,
+                        measure::from_datetime(tstz),
                         i.as_seconds_f64()
                     )
                 })
@@ -176,7 +177,7 @@ fn insert_split_traj<const CRS: u64>(
             format!(
                 "{0}\t{2}\t{3}\t{1}",
                 v.0,
-                hex::encode_upper(v.1.to_wkb()),
+                hex::encode_upper(v.1.to_ewkb()),
                 v.2,
                 v.3.as_seconds_f64(),
             )
@@ -191,7 +192,7 @@ fn insert_split_traj<const CRS: u64>(
     );
 
     let insert = "insert into program_data.trajectory_splits (mmsi, dist_thres, time_thres, sub_traj)
-        select mmsi, dist_thres, make_interval(secs=>time_thres_s) as time_thres, st_geomfromwkb(decode(bytev,'hex'),4326) as sub_traj from temp_split
+        select mmsi, dist_thres, make_interval(secs=>time_thres_s) as time_thres, st_geomfromewkb(decode(bytev,'hex')) as sub_traj from temp_split
             on conflict do nothing";
 
     let b = t
@@ -318,13 +319,14 @@ fn fetch_cog(
     cog_table.time.reserve(size);
     cog_table.cog.reserve(size);
 
-    for row in result {
+    for (i, row) in result.iter().enumerate() {
         let mmsi: i32 = row.get("mmsi");
         let time: DateTime<Utc> = row.get("timestamp");
         let cog: f32 = row.get("cog");
         cog_table.mmsi.push(mmsi);
         cog_table.time.push(time);
         cog_table.cog.push(cog);
+        cog_table.b_tree_index.insert((mmsi, time), i);
     }
 
     Ok(cog_table)
@@ -392,13 +394,14 @@ fn fetch_rot(
     rot_table.time.reserve(size);
     rot_table.rot.reserve(size);
 
-    for row in result {
+    for (i, row) in result.iter().enumerate() {
         let mmsi: i32 = row.get("mmsi");
         let time: DateTime<Utc> = row.get("timestamp");
         let rot: f32 = row.get("rot");
         rot_table.mmsi.push(mmsi);
         rot_table.time.push(time);
         rot_table.rot.push(rot);
+        rot_table.b_tree_index.insert((mmsi, time), i);
     }
 
     Ok(rot_table)
@@ -413,12 +416,12 @@ fn fetch_trajectories(
 
     let result = conn
         .query(
-            "SELECT mmsi, ST_AsBinary(ST_FilterByM(traj, $1, $2, true), 'NDR') as traj
+            "SELECT mmsi, ST_AsEWKB(ST_FilterByM(traj, $1, $2, true), 'NDR') as traj
 FROM PROGRAM_DATA.trajectories
 WHERE ST_IsEmpty(ST_FilterByM(traj, $1, $2)) = false;",
             &[
-                &(time_begin.timestamp() as f64),
-                &(time_end.timestamp() as f64),
+                &measure::from_datetime(time_begin),
+                &measure::from_datetime(time_end),
             ],
         )
         .map_err(|e| DatabaseError::QueryError {
@@ -435,8 +438,7 @@ WHERE ST_IsEmpty(ST_FilterByM(traj, $1, $2)) = false;",
         let mmsi: i32 = row.get("mmsi");
         let traj: Vec<u8> = row.get("traj");
 
-        let wkb_data = read_wkb(traj.as_slice())?;
-        let lsm = LineStringM::try_from(wkb_data)?;
+        let lsm = LineStringM::from_ewkb(traj.as_slice())?;
 
         trajectories_table.mmsi.push(mmsi);
         trajectories_table.trajectory.push(lsm);
@@ -538,7 +540,7 @@ impl<const CHUNK_SIZE: u32> TrajectoryIter<CHUNK_SIZE> {
             .conn
             .prepare_typed(
                 "
-                SELECT MMSI, st_asbinary(TRAJ,'NDR') as traj FROM
+                SELECT MMSI, st_asewkb(TRAJ,'NDR') as traj FROM
                     PROGRAM_DATA.TRAJECTORIES 
                         ORDER BY MMSI
                         LIMIT $1 
@@ -578,9 +580,7 @@ impl<const CHUNK_SIZE: u32> Iterator for TrajectoryIter<CHUNK_SIZE> {
                     .map(|r| {
                         Ok::<(i32, LineStringM<4326>), DatabaseError>((
                             r.get::<'_, _, i32>("mmsi"),
-                            LineStringM::try_from(read_wkb(
-                                r.get::<'_, _, Vec<u8>>("traj").as_slice(),
-                            )?)?,
+                            LineStringM::from_ewkb(r.get::<'_, _, Vec<u8>>("traj").as_slice())?,
                         ))
                     })
                     .collect::<Result<Vec<_>, _>>()
@@ -1,11 +1,12 @@
 use super::*;
 
-type CogType = f32;
+pub type CogType = f32;
 
 pub struct Cog {
     pub mmsi: Vec<MMSIType>,
     pub time: Vec<TimeType>,
     pub cog: Vec<CogType>,
+    pub b_tree_index: std::collections::BTreeMap<(MMSIType, TimeType), usize>,
 }
 
 impl Cog {
@@ -14,6 +15,7 @@ impl Cog {
             mmsi: Vec::new(),
             time: Vec::new(),
             cog: Vec::new(),
+            b_tree_index: std::collections::BTreeMap::new(),
         }
     }
 }
@@ -35,4 +37,59 @@ impl Cog {
 
         Ok(self.cog[index])
     }
+
+    /// Interpolates the COG at `time` from the closest reports before and after it, turning the shortest way around the compass.
+    ///
+    /// Relies on `b_tree_index` being populated.
+    pub fn interpolate(&self, mmsi: MMSIType, time: TimeType) -> Result<CogType, TableError> {
+        let before = self
+            .b_tree_index
+            .range(..=(mmsi, time))
+            .next_back()
+            .filter(|((m, _), _)| *m == mmsi);
+        let after = self
+            .b_tree_index
+            .range((mmsi, time)..)
+            .next()
+            .filter(|((m, _), _)| *m == mmsi);
+
+        match (before, after) {
+            (Some(((_, tb), ib)), Some(((_, ta), ia))) => {
+                let ratio = interpolation_ratio(*tb, *ta, time);
+                let (from, to) = (self.cog[*ib], self.cog[*ia]);
+                let turn = (to - from + 540.).rem_euclid(360.) - 180.;
+                Ok((from + turn * ratio).rem_euclid(360.))
+            }
+            _ => Err(TableError::MissingKey),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolate_across_north() {
+        let mut cog = Cog::new();
+        let t0 = DateTime::from_timestamp_secs(0).unwrap();
+        let t1 = DateTime::from_timestamp_secs(10).unwrap();
+        for (i, (t, c)) in [(t0, 350.), (t1, 10.)].into_iter().enumerate() {
+            cog.mmsi.push(1);
+            cog.time.push(t);
+            cog.cog.push(c);
+            cog.b_tree_index.insert((1, t), i);
+        }
+
+        let halfway = cog
+            .interpolate(1, DateTime::from_timestamp_secs(5).unwrap())
+            .unwrap();
+        assert!(halfway.abs() < 1e-3 || (halfway - 360.).abs() < 1e-3);
+        let quarter = cog
+            .interpolate(1, DateTime::from_timestamp_secs(8).unwrap())
+            .unwrap();
+        assert!((quarter - 6.).abs() < 1e-3);
+        assert!(cog.interpolate(1, DateTime::from_timestamp_secs(11).unwrap()).is_err());
+        assert!(cog.interpolate(2, t0).is_err());
+    }
 }
@@ -1,5 +1,7 @@
 use super::*;
 
+use std::collections::HashMap;
+
 pub type DimensionType = f64;
 
 pub struct Dimensions {
@@ -37,4 +39,18 @@ impl Dimensions {
 
         Ok((self.width[index], self.length[index]))
     }
+
+    /// The width and length of every ship, [`None`] for ships without dimensions.
+    ///
+    /// Intended for [`StopAreaConf::aggregate`](linesonmaps::algo::stop_area::StopAreaConf::aggregate), which reports the range of ship sizes in every area.
+    pub fn size_of(&self) -> impl Fn(&MMSIType) -> Option<(DimensionType, DimensionType)> + use<> {
+        let sizes = self
+            .mmsi
+            .iter()
+            .zip(self.width.iter().zip(self.length.iter()))
+            .map(|(mmsi, (width, length))| (*mmsi, (*width, *length)))
+            .collect::<HashMap<_, _>>();
+
+        move |mmsi| sizes.get(mmsi).copied()
+    }
 }
@@ -1,5 +1,16 @@
 use crate::errors::*;
 pub use chrono::prelude::*;
+use geo::Geodesic;
+use linesonmaps::algo::kinematics::{
+    Disagreement, Reported, Tolerance, VertexKinematics, compare_reported, vertex_kinematics,
+};
+use linesonmaps::algo::resample::{ResampleConf, resample};
+use linesonmaps::algo::stop_area::StopArea;
+use linesonmaps::algo::stop_cluster::Trajectory;
+use linesonmaps::algo::stop_kind::{StopEvidence, StopKindConf};
+use linesonmaps::types::measure;
+use linesonmaps::types::pointm::PointM;
+use std::num::NonZero;
 
 pub mod cog;
 pub mod dimensions;
@@ -12,7 +23,7 @@ pub mod stop_object;
 pub mod trajectories;
 
 type TimeType = DateTime<Utc>;
-type MMSIType = i32;
+pub type MMSIType = i32;
 
 pub struct Ships {
     pub nav_status: nav_status::NavStatus,
@@ -24,3 +35,102 @@ pub struct Ships {
     pub dimensions: dimensions::Dimensions,
     pub trajectories: trajectories::Trajectories,
 }
+
+/// A resampled position, with the reported SOG and COG interpolated to the same timestamp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResampledPoint {
+    pub point: PointM<4326>,
+    pub sog: Option<sog::SogType>,
+    pub cog: Option<cog::CogType>,
+}
+
+impl Ships {
+    /// Resamples the trajectory of `mmsi` to a fixed time grid, see [`resample`], and attaches the SOG and COG at every sample (if reported around that time).
+    pub fn resample_with_sog_cog(
+        &self,
+        mmsi: MMSIType,
+        conf: &ResampleConf,
+    ) -> Result<Vec<Vec<ResampledPoint>>, TableError> {
+        let ls = self.trajectories.search_by_key(mmsi)?;
+
+        Ok(resample(ls, conf)
+            .0
+            .into_iter()
+            .map(|part| {
+                part.points()
+                    .map(|point| {
+                        let time = point.time();
+                        ResampledPoint {
+                            point,
+                            sog: time.and_then(|t| self.sog.interpolate(mmsi, t).ok()),
+                            cog: time.and_then(|t| self.cog.interpolate(mmsi, t).ok()),
+                        }
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+impl Ships {
+    /// Compares the kinematics implied by the trajectory of `mmsi` (see [`vertex_kinematics`]) with the SOG, COG and ROT the ship reported, interpolated to the timestamps of the vertices.
+    ///
+    /// Vertices without reports around them are compared with nothing. Relies on the `b_tree_index` of the [`sog::Sog`], [`cog::Cog`] and [`rot::Rot`] tables being populated.
+    pub fn kinematic_disagreements(
+        &self,
+        mmsi: MMSIType,
+        window: NonZero<usize>,
+        tolerance: &Tolerance,
+    ) -> Result<Vec<(VertexKinematics, Disagreement)>, TableError> {
+        let ls = self.trajectories.search_by_key(mmsi)?;
+
+        Ok(vertex_kinematics(ls, &Geodesic, window)
+            .into_iter()
+            .map(|derived| {
+                let reported = measure::to_datetime(derived.m)
+                    .map(|t| Reported {
+                        sog: self.sog.interpolate(mmsi, t).ok().map(f64::from),
+                        cog: self.cog.interpolate(mmsi, t).ok().map(f64::from),
+                        rot: self.rot.interpolate(mmsi, t).ok().map(f64::from),
+                    })
+                    .unwrap_or_default();
+                (derived, compare_reported(&derived, &reported, tolerance))
+            })
+            .collect())
+    }
+}
+
+impl Ships {
+    /// Classifies the stops of `mmsi` in `trajectory` and stores the result in the stops, see [`StopKindConf::classify`].
+    ///
+    /// The evidence of every stop is the [`nav_status::NavStatus`] reported during it, the change of the [`ship_draught::Draught`] from its start to its end and the distance to the closest of `areas`.
+    pub fn classify_stops<K>(
+        &self,
+        mmsi: MMSIType,
+        trajectory: &mut Trajectory<4326>,
+        areas: &[StopArea<K>],
+        conf: &StopKindConf,
+    ) {
+        conf.classify_trajectory(trajectory, |polygon, (from, to)| StopEvidence {
+            reported: self
+                .nav_status
+                .dominant(mmsi, from, to)
+                .and_then(|status| status.stop_kind()),
+            draught_change: self.ship_draught.change(mmsi, from, to).map(f64::from),
+            area_distance: areas
+                .iter()
+                .map(|area| area.distance(polygon))
+                .min_by(f64::total_cmp),
+        });
+    }
+}
+
+/// Fraction of the interval `from`..`to` that has passed at `at`
+fn interpolation_ratio(from: TimeType, to: TimeType, at: TimeType) -> f32 {
+    let span = (to - from).as_seconds_f64();
+    if span > 0. {
+        ((at - from).as_seconds_f64() / span) as f32
+    } else {
+        0.
+    }
+}
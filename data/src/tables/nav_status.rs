@@ -1,4 +1,7 @@
 use super::*;
+use chrono::TimeDelta;
+use linesonmaps::algo::stop_kind::StopKind;
+use linesonmaps::types::coordm::CoordM;
 
 pub struct NavStatus {
     pub mmsi: Vec<MMSIType>,
@@ -39,9 +42,56 @@ impl NavStatus {
 
         Ok(self.nav_status[index])
     }
+
+    /// The reported status of `mmsi` at a point of its trajectory, [`None`] where nothing was reported.
+    ///
+    /// Intended for [`StatusChange`](linesonmaps::algo::strategy::StatusChange), which splits the trajectory where the status changes.
+    pub fn status_of(
+        &self,
+        mmsi: MMSIType,
+    ) -> impl Fn(&CoordM<4326>) -> Option<NavStatusValue> + use<> {
+        let intervals = (0..self.mmsi.len())
+            .filter(|&i| self.mmsi[i] == mmsi)
+            .map(|i| (self.time_begin[i], self.time_end[i], self.nav_status[i]))
+            .collect::<Vec<_>>();
+
+        move |coord| {
+            let time = measure::to_datetime(coord.m)?;
+            intervals
+                .iter()
+                .find(|(tb, te, _)| *tb <= time && *te >= time)
+                .map(|(_, _, status)| *status)
+        }
+    }
+
+    /// The status `mmsi` reported for the longest part of `time_from`..`time_to`, [`None`] if no status was reported for at least half of it
+    pub fn dominant(
+        &self,
+        mmsi: MMSIType,
+        time_from: TimeType,
+        time_to: TimeType,
+    ) -> Option<NavStatusValue> {
+        let mut durations: Vec<(NavStatusValue, TimeDelta)> = vec![];
+        for i in (0..self.mmsi.len()).filter(|&i| self.mmsi[i] == mmsi) {
+            let overlap = self.time_end[i].min(time_to) - self.time_begin[i].max(time_from);
+            if overlap < TimeDelta::zero() {
+                continue;
+            }
+            match durations.iter_mut().find(|(s, _)| *s == self.nav_status[i]) {
+                Some((_, d)) => *d += overlap,
+                None => durations.push((self.nav_status[i], overlap)),
+            }
+        }
+
+        durations
+            .into_iter()
+            .max_by_key(|(_, d)| *d)
+            .filter(|(_, d)| *d * 2 >= time_to - time_from)
+            .map(|(status, _)| status)
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum NavStatusValue {
     UnderWayUsingEngine,
     Anchored,
@@ -55,6 +105,18 @@ pub enum NavStatusValue {
     AISSART,
 }
 
+impl NavStatusValue {
+    /// The kind of stop implied by the status, [`None`] for statuses that say nothing about a stop
+    pub fn stop_kind(&self) -> Option<StopKind> {
+        match self {
+            NavStatusValue::Anchored => Some(StopKind::Anchored),
+            NavStatusValue::Moord => Some(StopKind::Moored),
+            NavStatusValue::EngagedInFishingActivity => Some(StopKind::Fishing),
+            _ => None,
+        }
+    }
+}
+
 pub fn nav_status_converter(field: &str) -> NavStatusValue {
     match field {
         "aground" => NavStatusValue::Aground,
@@ -70,3 +132,31 @@ pub fn nav_status_converter(field: &str) -> NavStatusValue {
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dominant_status() {
+        let t = |h: i64| DateTime::from_timestamp_secs(h * 3600).unwrap();
+        let mut nav_status = NavStatus::new();
+        for (tb, te, status) in [
+            (0, 1, NavStatusValue::UnderWayUsingEngine),
+            (1, 4, NavStatusValue::Anchored),
+            (4, 5, NavStatusValue::UnderWayUsingEngine),
+        ] {
+            nav_status.mmsi.push(1);
+            nav_status.time_begin.push(t(tb));
+            nav_status.time_end.push(t(te));
+            nav_status.nav_status.push(status);
+        }
+
+        let anchored = nav_status.dominant(1, t(0), t(5));
+        assert_eq!(anchored, Some(NavStatusValue::Anchored));
+        assert_eq!(anchored.unwrap().stop_kind(), Some(StopKind::Anchored));
+        // reported for less than half of the period
+        assert_eq!(nav_status.dominant(1, t(4), t(10)), None);
+        assert_eq!(nav_status.dominant(2, t(0), t(5)), None);
+    }
+}
@@ -6,6 +6,7 @@ pub struct Rot {
     pub mmsi: Vec<MMSIType>,
     pub time: Vec<TimeType>,
     pub rot: Vec<RotType>,
+    pub b_tree_index: std::collections::BTreeMap<(MMSIType, TimeType), usize>,
 }
 
 impl Rot {
@@ -14,6 +15,7 @@ impl Rot {
             mmsi: Vec::new(),
             time: Vec::new(),
             rot: Vec::new(),
+            b_tree_index: std::collections::BTreeMap::new(),
         }
     }
 }
@@ -35,4 +37,28 @@ impl Rot {
 
         Ok(self.rot[index])
     }
+
+    /// Linearly interpolates the ROT at `time` from the closest reports before and after it.
+    ///
+    /// Relies on `b_tree_index` being populated.
+    pub fn interpolate(&self, mmsi: MMSIType, time: TimeType) -> Result<RotType, TableError> {
+        let before = self
+            .b_tree_index
+            .range(..=(mmsi, time))
+            .next_back()
+            .filter(|((m, _), _)| *m == mmsi);
+        let after = self
+            .b_tree_index
+            .range((mmsi, time)..)
+            .next()
+            .filter(|((m, _), _)| *m == mmsi);
+
+        match (before, after) {
+            (Some(((_, tb), ib)), Some(((_, ta), ia))) => {
+                let ratio = interpolation_ratio(*tb, *ta, time);
+                Ok(self.rot[*ib] + (self.rot[*ia] - self.rot[*ib]) * ratio)
+            }
+            _ => Err(TableError::MissingKey),
+        }
+    }
 }
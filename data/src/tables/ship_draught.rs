@@ -38,6 +38,24 @@ impl Draught {
         Ok(self.draught[index])
     }
 
+    /// The draught of `mmsi` last reported at or before `time`
+    pub fn reported_at(&self, mmsi: MMSIType, time: TimeType) -> Option<DraughtType> {
+        (0..self.mmsi.len())
+            .filter(|&i| self.mmsi[i] == mmsi && self.time_begin[i] <= time)
+            .max_by_key(|&i| self.time_begin[i])
+            .map(|i| self.draught[i])
+    }
+
+    /// Absolute change of the draught of `mmsi` from `time_from` to `time_to`, [`None`] if it was not reported before both
+    pub fn change(
+        &self,
+        mmsi: MMSIType,
+        time_from: TimeType,
+        time_to: TimeType,
+    ) -> Option<DraughtType> {
+        Some((self.reported_at(mmsi, time_to)? - self.reported_at(mmsi, time_from)?).abs())
+    }
+
     pub fn search_range_by_time(&self, mmsi: MMSIType, time_from: TimeType, time_to: TimeType) -> Vec<usize> {
         self.mmsi.iter().zip(self.time_begin.iter().zip(self.time_end.iter())).enumerate().filter(|(_,(m,(tb,te)))| **m == mmsi && time_from <= **te && time_to >= **tb).map(|(i,_)| i).collect()
     }
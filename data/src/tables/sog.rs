@@ -37,4 +37,28 @@ impl Sog {
 
         Ok(self.sog[index])
     }
+
+    /// Linearly interpolates the SOG at `time` from the closest reports before and after it.
+    ///
+    /// Relies on `b_tree_index` being populated.
+    pub fn interpolate(&self, mmsi: MMSIType, time: TimeType) -> Result<SogType, TableError> {
+        let before = self
+            .b_tree_index
+            .range(..=(mmsi, time))
+            .next_back()
+            .filter(|((m, _), _)| *m == mmsi);
+        let after = self
+            .b_tree_index
+            .range((mmsi, time)..)
+            .next()
+            .filter(|((m, _), _)| *m == mmsi);
+
+        match (before, after) {
+            (Some(((_, tb), ib)), Some(((_, ta), ia))) => {
+                let ratio = interpolation_ratio(*tb, *ta, time);
+                Ok(self.sog[*ib] + (self.sog[*ia] - self.sog[*ib]) * ratio)
+            }
+            _ => Err(TableError::MissingKey),
+        }
+    }
 }
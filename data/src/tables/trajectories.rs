@@ -1,6 +1,14 @@
 use super::*;
 
+use linesonmaps::algo::encounter::{Encounter, EncounterConf, detect_encounters};
+use linesonmaps::algo::index::SegmentIndex;
+use linesonmaps::algo::segmenter::{SegmentStats, TrajectorySplit, segment_batch};
+use linesonmaps::algo::strategy::SegmentationStrategy;
 use linesonmaps::types::linestringm::LineStringM;
+use std::num::NonZero;
+
+/// Number of trajectories segmented in parallel at a time, see [`segment_batch`]
+const CHUNK_SIZE: NonZero<usize> = NonZero::new(256).expect("256 is nonzero");
 
 #[derive(Debug)]
 pub struct Trajectories {
@@ -34,3 +42,50 @@ impl Trajectories {
         Ok(&self.trajectory[index])
     }
 }
+
+impl Trajectories {
+    /// Finds every encounter between two ships of the table, see [`detect_encounters`].
+    pub fn encounters(&self, conf: &EncounterConf) -> Vec<(MMSIType, MMSIType, Encounter)> {
+        detect_encounters(&self.trajectory, conf)
+            .into_iter()
+            .map(|(i, j, encounter)| (self.mmsi[i], self.mmsi[j], encounter))
+            .collect()
+    }
+}
+
+impl Trajectories {
+    /// Builds a spatio-temporal index over all trajectories, the trajectory indices it returns are positions in [`Trajectories::mmsi`].
+    pub fn index(&self) -> SegmentIndex<4326> {
+        SegmentIndex::new(&self.trajectory)
+    }
+}
+
+impl Trajectories {
+    /// Segments every trajectory of the table in parallel, see [`segment_batch`].
+    pub fn segment<S>(
+        &self,
+        strategy: &S,
+    ) -> Vec<(MMSIType, Vec<TrajectorySplit<4326>>, SegmentStats)>
+    where
+        S: SegmentationStrategy<4326> + Clone + Sync,
+    {
+        let trajectories = self
+            .mmsi
+            .iter()
+            .copied()
+            .zip(self.trajectory.iter().cloned());
+        segment_batch(trajectories, strategy, CHUNK_SIZE).collect()
+    }
+
+    /// Like [`Trajectories::segment`], but consumes the table, such that the trajectories are not copied.
+    pub fn into_segments<S>(
+        self,
+        strategy: &S,
+    ) -> Vec<(MMSIType, Vec<TrajectorySplit<4326>>, SegmentStats)>
+    where
+        S: SegmentationStrategy<4326> + Clone + Sync,
+    {
+        let trajectories = self.mmsi.into_iter().zip(self.trajectory);
+        segment_batch(trajectories, strategy, CHUNK_SIZE).collect()
+    }
+}
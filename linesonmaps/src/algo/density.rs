@@ -0,0 +1,732 @@
+//! Density based clustering of stops, as alternatives to [`DbScanConf`](crate::algo::stop_cluster::DbScanConf).
+//!
+//! Every algorithm labels points with [`Classification`], so the results can be turned into stops with [`cluster_to_traj_with_stop_object`](crate::algo::stop_cluster::cluster_to_traj_with_stop_object).
+//! Like `DbScanConf`, points are only neighbors if both have a Speed Over Ground (SOG) below `speed_thres` and are less than `max_time_thres` apart,
+//! which also bounds the number of distances that are computed.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::num::NonZero;
+
+use chrono::TimeDelta;
+use rayon::prelude::*;
+use typed_builder::TypedBuilder;
+
+use crate::algo::stop_cluster::Classification;
+use crate::types::measure;
+use crate::types::pointm::PointM;
+
+/// ST-DBSCAN (Birant & Kut, 2007), DBSCAN with separate spatial and temporal thresholds.
+///
+/// Points only join a cluster if their SOG is close to the mean SOG of the cluster, which keeps e.g. drifting apart from anchoring.
+#[derive(TypedBuilder, Debug)]
+pub struct StDbScanConf<Dist, const CRS: u64>
+where
+    Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+{
+    /// Minimum number of 'nearby' points to a [Classification::Core] point
+    pub(crate) min_cluster_size: NonZero<usize>,
+    pub(crate) dist: Dist,
+    /// Maximum distance to a [Classification::Core] point
+    pub(crate) spatial_thres: f64,
+    /// Maximum time interval to a [Classification::Core] point
+    pub(crate) temporal_thres: TimeDelta,
+    /// Maximum Speed Over Ground (SOG) for a point to be clustered
+    pub(crate) speed_thres: f32,
+    /// Maximum difference between the SOG of a point and the mean SOG of the cluster it joins
+    #[builder(default = f64::INFINITY)]
+    pub(crate) sog_thres: f64,
+}
+
+impl<Dist, const CRS: u64> StDbScanConf<Dist, CRS>
+where
+    Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+{
+    pub fn run<'p>(
+        &self,
+        points: &'p [(PointM<CRS>, f32)],
+    ) -> Vec<(&'p PointM<CRS>, Classification)> {
+        use Classification::{Core, Edge, Noise, Unclassified};
+
+        let neighbors = neighborhoods(
+            points,
+            &self.dist,
+            self.speed_thres,
+            self.temporal_thres,
+            self.spatial_thres,
+        );
+        let is_core = |i: usize| neighbors[i].len() >= self.min_cluster_size.get();
+        let similar =
+            |i: usize, j: usize| f64::from(points[i].1 - points[j].1).abs() <= self.sog_thres;
+        // a single point with an outlying SOG does not become a cluster of its own
+        let is_seed = |i: usize| {
+            neighbors[i].iter().filter(|(j, _)| similar(i, *j)).count()
+                >= self.min_cluster_size.get()
+        };
+
+        let mut classes = vec![Unclassified; points.len()];
+        let mut cluster = 0_usize;
+        for i in 0..points.len() {
+            if !matches!(classes[i], Unclassified) {
+                continue;
+            }
+            if !is_seed(i) {
+                classes[i] = Noise;
+                continue;
+            }
+            classes[i] = Core(cluster);
+
+            let (mut sog_sum, mut count) = (f64::from(points[i].1), 1.);
+            let mut stack = neighbors[i].iter().map(|(j, _)| *j).collect::<Vec<_>>();
+            while let Some(j) = stack.pop() {
+                if !matches!(classes[j], Unclassified | Noise) {
+                    continue;
+                }
+                let sog = f64::from(points[j].1);
+                if (sog - sog_sum / count).abs() > self.sog_thres {
+                    // left unclassified, such that it can seed or join a cluster of points with a similar SOG
+                    continue;
+                }
+                sog_sum += sog;
+                count += 1.;
+
+                if is_core(j) {
+                    classes[j] = Core(cluster);
+                    stack.extend(neighbors[j].iter().map(|(k, _)| *k));
+                } else {
+                    classes[j] = Edge(cluster);
+                }
+            }
+            cluster += 1;
+        }
+
+        points.iter().map(|(p, _)| p).zip(classes).collect()
+    }
+}
+
+/// OPTICS (Ankerst et al., 1999), orders the points by reachability such that clusters for any distance threshold can be extracted from one run, see [`Optics::classify`].
+#[derive(TypedBuilder, Debug)]
+pub struct OpticsConf<Dist, const CRS: u64>
+where
+    Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+{
+    /// Minimum number of 'nearby' points to a [Classification::Core] point
+    pub(crate) min_cluster_size: NonZero<usize>,
+    pub(crate) dist: Dist,
+    /// Largest distance threshold that can be extracted, lower values save distance computations
+    #[builder(default = f64::INFINITY)]
+    pub(crate) max_dist: f64,
+    /// Maximum time interval to a [Classification::Core] point
+    pub(crate) max_time_thres: TimeDelta,
+    /// Maximum Speed Over Ground (SOG) for a point to be clustered
+    pub(crate) speed_thres: f32,
+}
+
+/// The cluster ordering of [`OpticsConf::run`]
+#[derive(Debug, Clone)]
+pub struct Optics<'p, const CRS: u64> {
+    points: &'p [(PointM<CRS>, f32)],
+    /// Indices of the points in the order they were processed
+    pub order: Vec<usize>,
+    /// Reachability distance of every point, [`None`] if it is not reachable from a core point
+    pub reachability: Vec<Option<f64>>,
+    /// Core distance of every point, [`None`] if it is not a core point for any distance threshold
+    pub core_distance: Vec<Option<f64>>,
+}
+
+/// Entry of the OPTICS seed list, ordered by reachability and then index
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Seed(f64, usize);
+
+impl Eq for Seed {}
+
+impl PartialOrd for Seed {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Seed {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+impl<Dist, const CRS: u64> OpticsConf<Dist, CRS>
+where
+    Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+{
+    pub fn run<'p>(&self, points: &'p [(PointM<CRS>, f32)]) -> Optics<'p, CRS> {
+        let neighbors = neighborhoods(
+            points,
+            &self.dist,
+            self.speed_thres,
+            self.max_time_thres,
+            self.max_dist,
+        );
+        let core_distance = neighbors
+            .iter()
+            .map(|n| n.get(self.min_cluster_size.get() - 1).map(|(_, d)| *d))
+            .collect::<Vec<_>>();
+
+        let mut reachability = vec![None; points.len()];
+        let mut processed = vec![false; points.len()];
+        let mut order = Vec::with_capacity(points.len());
+        let mut seeds = BinaryHeap::new();
+
+        for start in 0..points.len() {
+            if processed[start] {
+                continue;
+            }
+            seeds.push(Reverse(Seed(f64::INFINITY, start)));
+
+            while let Some(Reverse(Seed(_, i))) = seeds.pop() {
+                // seeds are not removed when their reachability decreases, so stale entries are skipped
+                if processed[i] {
+                    continue;
+                }
+                processed[i] = true;
+                order.push(i);
+
+                let Some(core) = core_distance[i] else {
+                    continue;
+                };
+                for &(j, d) in &neighbors[i] {
+                    let reach = core.max(d);
+                    if !processed[j] && reachability[j].is_none_or(|r| reach < r) {
+                        reachability[j] = Some(reach);
+                        seeds.push(Reverse(Seed(reach, j)));
+                    }
+                }
+            }
+        }
+
+        Optics {
+            points,
+            order,
+            reachability,
+            core_distance,
+        }
+    }
+}
+
+impl<'p, const CRS: u64> Optics<'p, CRS> {
+    /// The clusters DBSCAN finds with distance threshold `dist_thres` (up to the assignment of [`Classification::Edge`] points between clusters).
+    ///
+    /// `dist_thres` should not exceed the `max_dist` of the [`OpticsConf`].
+    pub fn classify(&self, dist_thres: f64) -> Vec<(&'p PointM<CRS>, Classification)> {
+        use Classification::{Core, Edge, Noise};
+
+        let mut classes = vec![Noise; self.points.len()];
+        let (mut cluster, mut next) = (None, 0_usize);
+        for &i in &self.order {
+            let core = self.core_distance[i].is_some_and(|d| d < dist_thres);
+            let reachable = self.reachability[i].is_some_and(|r| r < dist_thres);
+
+            classes[i] = match (reachable, core, cluster) {
+                (true, true, Some(c)) => Core(c),
+                (true, false, Some(c)) => Edge(c),
+                (false, true, _) => {
+                    cluster = Some(next);
+                    next += 1;
+                    Core(next - 1)
+                }
+                _ => Noise,
+            };
+        }
+
+        self.points.iter().map(|(p, _)| p).zip(classes).collect()
+    }
+}
+
+/// HDBSCAN (Campello et al., 2013), finds clusters of varying density without a distance threshold.
+///
+/// Clusters are selected by their stability in the hierarchy of the mutual reachability graph.
+/// Points of a cluster are [`Classification::Core`] if they stay in it until it splits or vanishes, and [`Classification::Edge`] if they fall out before.
+#[derive(TypedBuilder, Debug)]
+pub struct HdbscanConf<Dist, const CRS: u64>
+where
+    Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+{
+    /// Minimum number of points of a cluster
+    pub(crate) min_cluster_size: NonZero<usize>,
+    /// Number of 'nearby' points that define the core distance, defaults to `min_cluster_size`
+    #[builder(default, setter(strip_option))]
+    pub(crate) min_samples: Option<NonZero<usize>>,
+    pub(crate) dist: Dist,
+    /// Maximum time interval between points of a cluster
+    pub(crate) max_time_thres: TimeDelta,
+    /// Maximum Speed Over Ground (SOG) for a point to be clustered
+    pub(crate) speed_thres: f32,
+    /// Whether all points may form a single cluster, otherwise at least two clusters or none are found
+    #[builder(default = true)]
+    pub(crate) allow_single_cluster: bool,
+}
+
+/// Distances are clamped to this many meters, such that duplicate points do not have an infinite density
+const MIN_DIST: f64 = 1e-9;
+
+impl<Dist, const CRS: u64> HdbscanConf<Dist, CRS>
+where
+    Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+{
+    pub fn run<'p>(
+        &self,
+        points: &'p [(PointM<CRS>, f32)],
+    ) -> Vec<(&'p PointM<CRS>, Classification)> {
+        let min_samples = self.min_samples.unwrap_or(self.min_cluster_size).get();
+        let neighbors = neighborhoods(
+            points,
+            &self.dist,
+            self.speed_thres,
+            self.max_time_thres,
+            f64::INFINITY,
+        );
+        let core_distance = neighbors
+            .iter()
+            .map(|n| n.get(min_samples - 1).map_or(f64::INFINITY, |(_, d)| *d))
+            .collect::<Vec<_>>();
+
+        let mut edges = neighbors
+            .iter()
+            .enumerate()
+            .flat_map(|(i, n)| {
+                let core_distance = &core_distance;
+                n.iter()
+                    .filter(move |(j, _)| i < *j)
+                    .map(move |&(j, d)| (d.max(core_distance[i]).max(core_distance[j]), i, j))
+            })
+            .filter(|(w, _, _)| w.is_finite())
+            .collect::<Vec<_>>();
+        edges.par_sort_unstable_by(|a, b| a.0.total_cmp(&b.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+        let classes = Hierarchy::new(points.len(), &edges)
+            .classify(self.min_cluster_size.get(), self.allow_single_cluster);
+        points.iter().map(|(p, _)| p).zip(classes).collect()
+    }
+}
+
+/// Single linkage hierarchy of the minimum spanning tree, nodes below `n` are points, and node `n + k` is the `k`th merge
+struct Hierarchy {
+    n: usize,
+    /// Children, distance and size of every merge
+    merges: Vec<([usize; 2], f64, usize)>,
+}
+
+impl Hierarchy {
+    /// `edges` must be sorted by weight, components that are not connected are joined at an infinite distance
+    fn new(n: usize, edges: &[(f64, usize, usize)]) -> Self {
+        let mut uf = UnionFind::new(n);
+        let mut node = (0..n).collect::<Vec<_>>();
+        let mut hierarchy = Hierarchy { n, merges: vec![] };
+
+        let components = edges
+            .iter()
+            .copied()
+            .chain((1..n).map(|i| (f64::INFINITY, 0, i)));
+        for (w, a, b) in components {
+            let (ra, rb) = (uf.find(a), uf.find(b));
+            if ra == rb {
+                continue;
+            }
+            let children = [node[ra], node[rb]];
+            let size = hierarchy.size(children[0]) + hierarchy.size(children[1]);
+            hierarchy.merges.push((children, w, size));
+            node[uf.union(ra, rb)] = n + hierarchy.merges.len() - 1;
+        }
+        hierarchy
+    }
+
+    fn size(&self, node: usize) -> usize {
+        match node.checked_sub(self.n) {
+            Some(k) => self.merges[k].2,
+            None => 1,
+        }
+    }
+
+    fn leaves(&self, node: usize) -> Vec<usize> {
+        let (mut stack, mut leaves) = (vec![node], vec![]);
+        while let Some(node) = stack.pop() {
+            match node.checked_sub(self.n) {
+                Some(k) => stack.extend(self.merges[k].0),
+                None => leaves.push(node),
+            }
+        }
+        leaves
+    }
+
+    /// Condenses the hierarchy to clusters of at least `min_cluster_size` points, and selects the most stable ones
+    fn classify(&self, min_cluster_size: usize, allow_single_cluster: bool) -> Vec<Classification> {
+        use Classification::{Core, Edge, Noise};
+
+        let Some(root) = (self.n + self.merges.len()).checked_sub(1) else {
+            return vec![];
+        };
+
+        // per cluster
+        let mut parent: Vec<Option<usize>> = vec![None];
+        let mut birth = vec![0_f64];
+        let mut death = vec![0_f64];
+        let mut stability = vec![0_f64];
+        // per point, the cluster it fell out of and when
+        let mut fell_out = vec![(0_usize, 0_f64); self.n];
+
+        let mut stack = vec![(root, 0_usize)];
+        while let Some((node, c)) = stack.pop() {
+            let Some(k) = node.checked_sub(self.n) else {
+                // a cluster of a single point
+                fell_out[node] = (c, birth[c]);
+                death[c] = birth[c];
+                continue;
+            };
+            let ([a, b], w, size) = self.merges[k];
+            let lambda = 1. / w.max(MIN_DIST);
+
+            let falling = match (
+                self.size(a) >= min_cluster_size,
+                self.size(b) >= min_cluster_size,
+            ) {
+                (true, true) => {
+                    death[c] = lambda;
+                    stability[c] += size as f64 * (lambda - birth[c]);
+                    for child in [a, b] {
+                        parent.push(Some(c));
+                        birth.push(lambda);
+                        death.push(lambda);
+                        stability.push(0.);
+                        stack.push((child, parent.len() - 1));
+                    }
+                    vec![]
+                }
+                (true, false) => {
+                    stack.push((a, c));
+                    vec![b]
+                }
+                (false, true) => {
+                    stack.push((b, c));
+                    vec![a]
+                }
+                (false, false) => {
+                    death[c] = lambda;
+                    vec![a, b]
+                }
+            };
+            for node in falling {
+                for p in self.leaves(node) {
+                    fell_out[p] = (c, lambda);
+                }
+                stability[c] += self.size(node) as f64 * (lambda - birth[c]);
+            }
+        }
+
+        // excess of mass, children are created after their parent
+        let mut children = vec![vec![]; parent.len()];
+        for (c, p) in parent.iter().enumerate() {
+            if let Some(p) = p {
+                children[*p].push(c);
+            }
+        }
+        let mut selected = vec![false; parent.len()];
+        let mut best = vec![0_f64; parent.len()];
+        for c in (0..parent.len()).rev() {
+            let of_children = children[c].iter().map(|&x| best[x]).sum::<f64>();
+            let selectable = parent[c].is_some() || allow_single_cluster;
+            if selectable && (children[c].is_empty() || stability[c] >= of_children) {
+                selected[c] = true;
+                best[c] = stability[c];
+                let mut descendants = children[c].clone();
+                while let Some(d) = descendants.pop() {
+                    selected[d] = false;
+                    descendants.extend(&children[d]);
+                }
+            } else {
+                best[c] = of_children;
+            }
+        }
+
+        // clusters are numbered in the order of their first point
+        let mut number = vec![None; parent.len()];
+        let mut next = 0_usize;
+        fell_out
+            .into_iter()
+            .map(|(left, lambda)| {
+                // points that are only joined at an infinite distance are not density connected to anything
+                if lambda == 0. {
+                    return Noise;
+                }
+                let Some(s) =
+                    std::iter::successors(Some(left), |&c| parent[c]).find(|&c| selected[c])
+                else {
+                    return Noise;
+                };
+                let id = *number[s].get_or_insert_with(|| {
+                    next += 1;
+                    next - 1
+                });
+                match left != s || lambda >= death[s] {
+                    true => Core(id),
+                    false => Edge(id),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Disjoint sets over indices, with path halving and union by size
+pub(crate) struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    pub(crate) fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    /// Joins the sets of `a` and `b`, returning the root of the joined set
+    pub(crate) fn union(&mut self, a: usize, b: usize) -> usize {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return a;
+        }
+        let (big, small) = match self.size[a] >= self.size[b] {
+            true => (a, b),
+            false => (b, a),
+        };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+        big
+    }
+}
+
+/// The neighbors of every point (including itself) less than `max_time` and `max_dist` away, as indices and distances sorted by distance.
+///
+/// Points with a SOG of at least `speed_thres` have no neighbors, and are no neighbors.
+fn neighborhoods<const CRS: u64, Dist>(
+    points: &[(PointM<CRS>, f32)],
+    dist: &Dist,
+    speed_thres: f32,
+    max_time: TimeDelta,
+    max_dist: f64,
+) -> Vec<Vec<(usize, f64)>>
+where
+    Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+{
+    let slow = |i: usize| points[i].1 < speed_thres;
+    let m = |i: usize| points[i].0.coord.m;
+
+    let mut by_time = (0..points.len()).filter(|&i| slow(i)).collect::<Vec<_>>();
+    by_time.sort_by(|&a, &b| m(a).total_cmp(&m(b)));
+    let window = max_time.as_seconds_f64();
+
+    (0..points.len())
+        .into_par_iter()
+        .map(|i| {
+            if !slow(i) {
+                return vec![];
+            }
+            let start = by_time.partition_point(|&j| m(j) <= m(i) - window);
+            let mut neighbors = by_time[start..]
+                .iter()
+                .take_while(|&&j| m(j) < m(i) + window)
+                .filter(|&&j| measure::to_timedelta(m(j) - m(i)).abs() < max_time)
+                .map(|&j| (j, dist(&points[i].0, &points[j].0)))
+                .filter(|(_, d)| *d < max_dist)
+                .collect::<Vec<_>>();
+            neighbors.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            neighbors
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::stop_cluster::{StopOrLs, cluster_to_traj_with_stop_object};
+    use geo::{Distance, Euclidean};
+    use pretty_assertions::assert_eq;
+
+    fn dist(a: &PointM<3857>, b: &PointM<3857>) -> f64 {
+        Euclidean.distance(*a, *b)
+    }
+
+    /// A stop, a transit, another stop 1.1 km away and a return to the first stop two hours later
+    fn stops() -> Vec<(PointM<3857>, f32)> {
+        let stop = |x: f64, t: f64| {
+            (0..15).map(move |i| {
+                let jitter = (f64::from(i % 3) * 5., f64::from(i % 5) * 4.);
+                (
+                    PointM::from((x + jitter.0, jitter.1, t + f64::from(i) * 10.)),
+                    0.5,
+                )
+            })
+        };
+        let transit = (1..=10).map(|i| {
+            (
+                PointM::from((f64::from(i) * 100., 0., 140. + f64::from(i) * 10.)),
+                10.,
+            )
+        });
+        stop(0., 0.)
+            .chain(transit)
+            .chain(stop(1100., 250.))
+            .chain(stop(0., 7200.))
+            .collect()
+    }
+
+    fn clusters(classes: &[(&PointM<3857>, Classification)]) -> Vec<Option<usize>> {
+        classes.iter().map(|(_, c)| c.cluster()).collect()
+    }
+
+    fn expected(ids: [Option<usize>; 3]) -> Vec<Option<usize>> {
+        [
+            vec![ids[0]; 15],
+            vec![None; 10],
+            vec![ids[1]; 15],
+            vec![ids[2]; 15],
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn st_dbscan() {
+        let mut points = stops();
+        let conf = StDbScanConf::builder()
+            .min_cluster_size(5.try_into().unwrap())
+            .dist(dist)
+            .spatial_thres(50.)
+            .temporal_thres(TimeDelta::minutes(10))
+            .speed_thres(1.5)
+            .build();
+
+        let classes = conf.run(&points);
+        assert_eq!(clusters(&classes), expected([Some(0), Some(1), Some(2)]));
+        assert!(
+            classes
+                .iter()
+                .all(|(_, c)| !matches!(c, Classification::Edge(_)))
+        );
+
+        let traj = cluster_to_traj_with_stop_object(classes);
+        assert!(matches!(
+            traj.0.as_slice(),
+            [
+                StopOrLs::Stop { .. },
+                StopOrLs::LS(_),
+                StopOrLs::Stop { .. },
+                StopOrLs::Stop { .. }
+            ]
+        ));
+
+        // a slow, but not as slow, point in the second stop
+        points[30].1 = 1.4;
+        let conf = StDbScanConf::builder()
+            .min_cluster_size(5.try_into().unwrap())
+            .dist(dist)
+            .spatial_thres(50.)
+            .temporal_thres(TimeDelta::minutes(10))
+            .speed_thres(1.5)
+            .sog_thres(0.5)
+            .build();
+        assert_eq!(conf.run(&points)[30].1, Classification::Noise);
+    }
+
+    #[test]
+    fn st_dbscan_sog_groups() {
+        // anchored and drifting points at the same place and time
+        let points = (0..20)
+            .map(|i| {
+                let sog = if i % 2 == 0 { 0.2 } else { 1.2 };
+                (
+                    PointM::from((f64::from(i % 4) * 5., 0., f64::from(i) * 10.)),
+                    sog,
+                )
+            })
+            .collect::<Vec<_>>();
+        let conf = StDbScanConf::builder()
+            .min_cluster_size(5.try_into().unwrap())
+            .dist(dist)
+            .spatial_thres(50.)
+            .temporal_thres(TimeDelta::minutes(10))
+            .speed_thres(1.5)
+            .sog_thres(0.5)
+            .build();
+
+        let expected = (0..20).map(|i| Some(i % 2)).collect::<Vec<_>>();
+        assert_eq!(clusters(&conf.run(&points)), expected);
+    }
+
+    #[test]
+    fn optics() {
+        let points = stops();
+        let conf = OpticsConf::builder()
+            .min_cluster_size(5.try_into().unwrap())
+            .dist(dist)
+            .max_time_thres(TimeDelta::minutes(10))
+            .speed_thres(1.5)
+            .build();
+        let optics = conf.run(&points);
+        assert_eq!(optics.order.len(), points.len());
+
+        assert_eq!(
+            clusters(&optics.classify(50.)),
+            expected([Some(0), Some(1), Some(2)])
+        );
+        // both stops are within reach, the return is not within the time threshold
+        assert_eq!(
+            clusters(&optics.classify(2000.)),
+            expected([Some(0), Some(0), Some(1)])
+        );
+        assert!(clusters(&optics.classify(1.)).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn hdbscan() {
+        let points = stops();
+        let conf = HdbscanConf::builder()
+            .min_cluster_size(5.try_into().unwrap())
+            .dist(dist)
+            .max_time_thres(TimeDelta::minutes(10))
+            .speed_thres(1.5)
+            .build();
+
+        let classes = conf.run(&points);
+        assert_eq!(clusters(&classes), expected([Some(0), Some(1), Some(2)]));
+        assert!(
+            classes
+                .iter()
+                .any(|(_, c)| matches!(c, Classification::Core(_)))
+        );
+
+        // a single stop
+        let classes = conf.run(&points[..25]);
+        assert_eq!(
+            clusters(&classes),
+            [vec![Some(0); 15], vec![None; 10]].concat()
+        );
+
+        let conf = HdbscanConf::builder()
+            .min_cluster_size(5.try_into().unwrap())
+            .dist(dist)
+            .max_time_thres(TimeDelta::minutes(10))
+            .speed_thres(1.5)
+            .allow_single_cluster(false)
+            .build();
+        assert!(
+            clusters(&conf.run(&points[..25]))
+                .iter()
+                .all(Option::is_none)
+        );
+    }
+}
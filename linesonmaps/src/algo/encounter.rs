@@ -0,0 +1,438 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::TimeDelta;
+use rayon::prelude::*;
+use typed_builder::TypedBuilder;
+
+use crate::types::consts::METERS_PER_DEGREE;
+use crate::types::linem::interpolation;
+use crate::types::linestringm::LineStringM;
+
+/// Closest point of approach between two ships
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cpa {
+    /// Measure at which the ships are closest
+    pub m: f64,
+    /// Distance in meters between the ships at `m`
+    pub distance: f64,
+}
+
+impl Cpa {
+    /// Time to the closest point of approach in seconds as seen from measure `m`, negative if it has already passed
+    pub fn tcpa(&self, m: f64) -> f64 {
+        self.m - m
+    }
+}
+
+/// A period in which two ships were closer than the threshold of [`EncounterConf`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Encounter {
+    /// Measure at which the ships came within the threshold
+    pub start: f64,
+    /// Measure at which the ships were no longer within the threshold
+    pub end: f64,
+    /// Closest point of approach during the encounter
+    pub cpa: Cpa,
+}
+
+/// Measure range in which both linestrings have a position
+fn overlap(a: &LineStringM<4326>, b: &LineStringM<4326>) -> Option<(f64, f64)> {
+    let start = a.0.first()?.m.max(b.0.first()?.m);
+    let end = a.0.last()?.m.min(b.0.last()?.m);
+    (start <= end).then_some((start, end))
+}
+
+/// Every measure of either linestring within their overlap, i.e. the times between which both ships move linearly
+fn shared_measures(a: &LineStringM<4326>, b: &LineStringM<4326>) -> Vec<f64> {
+    let Some((start, end)) = overlap(a, b) else {
+        return vec![];
+    };
+    let mut ms =
+        a.0.iter()
+            .chain(b.0.iter())
+            .map(|c| c.m)
+            .filter(|m| *m > start && *m < end)
+            .chain([start, end])
+            .collect::<Vec<_>>();
+    ms.sort_by(f64::total_cmp);
+    ms.dedup();
+    ms
+}
+
+/// Position of `b` relative to `a` at measure `m` in meters (east, north), in a local plane around `a`
+fn relative_position(
+    a: &LineStringM<4326>,
+    b: &LineStringM<4326>,
+    m: f64,
+    lat: f64,
+) -> Option<(f64, f64)> {
+    let pa = a.locate_along(m, interpolation::Linear)?.coord;
+    let pb = b.locate_along(m, interpolation::Linear)?.coord;
+    let dlon = (pb.x - pa.x + 540.).rem_euclid(360.) - 180.;
+    Some((
+        dlon * lat.to_radians().cos() * METERS_PER_DEGREE,
+        (pb.y - pa.y) * METERS_PER_DEGREE,
+    ))
+}
+
+/// Distance in meters between the ships at every measure where either of them has a position, restricted to the period where both have one.
+pub fn distance_series(a: &LineStringM<4326>, b: &LineStringM<4326>) -> Vec<(f64, f64)> {
+    shared_measures(a, b)
+        .into_iter()
+        .filter_map(|m| {
+            let pa = a.locate_along(m, interpolation::Linear)?;
+            let pb = b.locate_along(m, interpolation::Linear)?;
+            Some((m, pa.distance_m(&pb)))
+        })
+        .collect()
+}
+
+/// Movement of the ships relative to each other between two subsequent shared measures
+struct Leg {
+    from: f64,
+    to: f64,
+    /// Relative position at `from`
+    r: (f64, f64),
+    /// Change of the relative position from `from` to `to`
+    v: (f64, f64),
+}
+
+impl Leg {
+    /// Fraction of the leg where the ships are closest
+    fn closest(&self) -> f64 {
+        let vv = self.v.0 * self.v.0 + self.v.1 * self.v.1;
+        if vv == 0. {
+            return 0.;
+        }
+        (-(self.r.0 * self.v.0 + self.r.1 * self.v.1) / vv).clamp(0., 1.)
+    }
+
+    /// Fraction of the leg (first and last) where the ships are within `threshold` meters
+    fn within(&self, threshold: f64) -> Option<(f64, f64)> {
+        let (r, v) = (self.r, self.v);
+        let a = v.0 * v.0 + v.1 * v.1;
+        let b = 2. * (r.0 * v.0 + r.1 * v.1);
+        let c = r.0 * r.0 + r.1 * r.1 - threshold * threshold;
+        if a == 0. {
+            return (c <= 0.).then_some((0., 1.));
+        }
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            return None;
+        }
+        let sqrt = discriminant.sqrt();
+        let first = ((-b - sqrt) / (2. * a)).max(0.);
+        let last = ((-b + sqrt) / (2. * a)).min(1.);
+        (first <= last).then_some((first, last))
+    }
+
+    fn at(&self, s: f64) -> f64 {
+        self.from + s * (self.to - self.from)
+    }
+}
+
+fn legs(a: &LineStringM<4326>, b: &LineStringM<4326>) -> Vec<Leg> {
+    shared_measures(a, b)
+        .windows(2)
+        .filter_map(|w| {
+            let lat = a.locate_along(w[0], interpolation::Linear)?.coord.y;
+            let r0 = relative_position(a, b, w[0], lat)?;
+            let r1 = relative_position(a, b, w[1], lat)?;
+            Some(Leg {
+                from: w[0],
+                to: w[1],
+                r: r0,
+                v: (r1.0 - r0.0, r1.1 - r0.1),
+            })
+        })
+        .collect()
+}
+
+/// Geodesic distance between the ships at measure `m`
+fn distance_at(a: &LineStringM<4326>, b: &LineStringM<4326>, m: f64) -> Option<Cpa> {
+    let pa = a.locate_along(m, interpolation::Linear)?;
+    let pb = b.locate_along(m, interpolation::Linear)?;
+    Some(Cpa {
+        m,
+        distance: pa.distance_m(&pb),
+    })
+}
+
+/// Closest point of approach of two ships over the period both trajectories overlap.
+///
+/// Between subsequent positions both ships are assumed to move linearly, such that the closest approach is found exactly, and not only at reported positions.
+/// Returns [`None`] if the trajectories do not overlap in time.
+pub fn closest_approach(a: &LineStringM<4326>, b: &LineStringM<4326>) -> Option<Cpa> {
+    let legs = legs(a, b);
+    let m = match legs.is_empty() {
+        // the trajectories only touch at a single measure
+        true => overlap(a, b)?.0,
+        false => {
+            legs.iter()
+                .map(|leg| {
+                    let s = leg.closest();
+                    let (x, y) = (leg.r.0 + s * leg.v.0, leg.r.1 + s * leg.v.1);
+                    (leg.at(s), x.hypot(y))
+                })
+                .min_by(|x, y| x.1.total_cmp(&y.1))?
+                .0
+        }
+    };
+    distance_at(a, b, m)
+}
+
+/// Predicts the closest point of approach as seen at measure `m`, by extrapolating the course and speed both ships had at `m`.
+///
+/// This is the CPA/TCPA an ARPA radar or ECDIS would have shown at the time, see [`Cpa::tcpa`].
+/// Returns [`None`] if either ship has no position at `m`.
+pub fn predicted_approach(a: &LineStringM<4326>, b: &LineStringM<4326>, m: f64) -> Option<Cpa> {
+    // velocity in m/s of the segment that contains m, the last segment if m is its end
+    let velocity = |ls: &LineStringM<4326>, lat: f64| -> Option<(f64, f64)> {
+        let idx =
+            ls.0.partition_point(|c| c.m <= m)
+                .clamp(1, ls.0.len().checked_sub(1)?);
+        let (from, to) = (ls.0[idx - 1], ls.0[idx]);
+        let dt = to.m - from.m;
+        if dt <= 0. {
+            return Some((0., 0.));
+        }
+        let dlon = (to.x - from.x + 540.).rem_euclid(360.) - 180.;
+        Some((
+            dlon * lat.to_radians().cos() * METERS_PER_DEGREE / dt,
+            (to.y - from.y) * METERS_PER_DEGREE / dt,
+        ))
+    };
+
+    let lat = a.locate_along(m, interpolation::Linear)?.coord.y;
+    let r = relative_position(a, b, m, lat)?;
+    let (va, vb) = (velocity(a, lat)?, velocity(b, lat)?);
+    let leg = Leg {
+        from: m,
+        to: m + 1.,
+        r,
+        v: (vb.0 - va.0, vb.1 - va.1),
+    };
+
+    // unlike the legs of a trajectory, the prediction may lie arbitrarily far in the past or future
+    let vv = leg.v.0 * leg.v.0 + leg.v.1 * leg.v.1;
+    let tcpa = if vv > 0. {
+        -(r.0 * leg.v.0 + r.1 * leg.v.1) / vv
+    } else {
+        0.
+    };
+    Some(Cpa {
+        m: leg.at(tcpa),
+        distance: (r.0 + tcpa * leg.v.0).hypot(r.1 + tcpa * leg.v.1),
+    })
+}
+
+/// Every period in which the two ships were within `threshold` meters of each other.
+pub fn encounters(a: &LineStringM<4326>, b: &LineStringM<4326>, threshold: f64) -> Vec<Encounter> {
+    let mut res: Vec<Encounter> = vec![];
+    let legs = legs(a, b);
+
+    for leg in &legs {
+        let Some((first, last)) = leg.within(threshold) else {
+            continue;
+        };
+        let s = leg.closest().clamp(first, last);
+        let Some(cpa) = distance_at(a, b, leg.at(s)) else {
+            continue;
+        };
+        let (start, end) = (leg.at(first), leg.at(last));
+
+        match res.last_mut() {
+            // continues the encounter of the previous leg
+            Some(prev) if prev.end == start => {
+                prev.end = end;
+                if cpa.distance < prev.cpa.distance {
+                    prev.cpa = cpa;
+                }
+            }
+            _ => res.push(Encounter { start, end, cpa }),
+        }
+    }
+
+    if legs.is_empty() {
+        // the trajectories only touch at a single measure
+        if let Some(cpa) = overlap(a, b).and_then(|(m, _)| distance_at(a, b, m))
+            && cpa.distance <= threshold
+        {
+            res.push(Encounter {
+                start: cpa.m,
+                end: cpa.m,
+                cpa,
+            });
+        }
+    }
+
+    res
+}
+
+/// Configuration of the fleet wide encounter detection, see [`detect_encounters`]
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq)]
+pub struct EncounterConf {
+    /// Ships closer than this many meters are in an encounter
+    pub(crate) threshold: f64,
+    /// Duration of a cell of the spatio-temporal grid
+    #[builder(default = TimeDelta::minutes(10))]
+    pub(crate) time_cell: TimeDelta,
+    /// Size of a cell of the spatio-temporal grid in degrees
+    #[builder(default = 0.05)]
+    pub(crate) space_cell: f64,
+}
+
+type Cell = (i64, i64, i64);
+
+/// Every cell of the grid that a trajectory could be within `threshold` meters of
+fn cells(ls: &LineStringM<4326>, conf: &EncounterConf) -> HashSet<Cell> {
+    let period = conf.time_cell.as_seconds_f64();
+    let dlat = conf.threshold / METERS_PER_DEGREE;
+    let mut res = HashSet::new();
+
+    for line in ls.lines() {
+        let (from, to) = (line.from.coord, line.to.coord);
+        let (min_y, max_y) = (from.y.min(to.y) - dlat, from.y.max(to.y) + dlat);
+        let dlon = dlat / max_y.abs().max(min_y.abs()).min(89.).to_radians().cos();
+        let (min_x, max_x) = (from.x.min(to.x) - dlon, from.x.max(to.x) + dlon);
+
+        let cell = |v: f64, size: f64| (v / size).floor() as i64;
+        for t in cell(from.m, period)..=cell(to.m, period) {
+            for x in cell(min_x, conf.space_cell)..=cell(max_x, conf.space_cell) {
+                for y in cell(min_y, conf.space_cell)..=cell(max_y, conf.space_cell) {
+                    res.insert((t, x, y));
+                }
+            }
+        }
+    }
+
+    res
+}
+
+/// Finds every encounter between any two of `trajectories`, see [`encounters`].
+///
+/// Only pairs of trajectories that share a cell of a spatio-temporal grid are compared, so the cost depends on the traffic density rather than the number of pairs.
+/// Returns the indices of both trajectories (ordered) along with the encounter, sorted by the indices.
+pub fn detect_encounters(
+    trajectories: &[LineStringM<4326>],
+    conf: &EncounterConf,
+) -> Vec<(usize, usize, Encounter)> {
+    let cells = trajectories
+        .par_iter()
+        .map(|ls| cells(ls, conf))
+        .collect::<Vec<_>>();
+
+    let mut grid: HashMap<Cell, Vec<usize>> = HashMap::new();
+    for (i, cells) in cells.iter().enumerate() {
+        for cell in cells {
+            grid.entry(*cell).or_default().push(i);
+        }
+    }
+
+    let mut pairs = grid
+        .values()
+        .flat_map(|idxs| {
+            idxs.iter()
+                .enumerate()
+                .flat_map(move |(n, i)| idxs[n + 1..].iter().map(move |j| (*i, *j)))
+        })
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    pairs.sort_unstable();
+
+    pairs
+        .into_par_iter()
+        .flat_map_iter(|(i, j)| {
+            encounters(&trajectories[i], &trajectories[j], conf.threshold)
+                .into_iter()
+                .map(move |e| (i, j, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::coordm::CoordM;
+
+    fn crossing() -> (LineStringM<4326>, LineStringM<4326>) {
+        // a sails north, b sails east, both pass (10, 56) at t = 60
+        let a: Vec<CoordM<4326>> = [(10., 55.99, 0.), (10., 56.01, 120.)]
+            .map(|f| f.into())
+            .to_vec();
+        let b: Vec<CoordM<4326>> = [(9.99, 56., 0.), (10.01, 56., 120.)]
+            .map(|f| f.into())
+            .to_vec();
+        (LineStringM::new(a).unwrap(), LineStringM::new(b).unwrap())
+    }
+
+    #[test]
+    fn crossing_ships_collide() {
+        let (a, b) = crossing();
+        let cpa = closest_approach(&a, &b).unwrap();
+        assert!((cpa.m - 60.).abs() < 1e-6, "{cpa:?}");
+        assert!(cpa.distance < 1e-6, "{cpa:?}");
+
+        let predicted = predicted_approach(&a, &b, 0.).unwrap();
+        assert!((predicted.tcpa(0.) - 60.).abs() < 1e-6, "{predicted:?}");
+        assert!(predicted.distance < 1e-6, "{predicted:?}");
+
+        let series = distance_series(&a, &b);
+        assert_eq!(series.len(), 2);
+        assert!(series.iter().all(|(_, d)| *d > 1000.));
+    }
+
+    #[test]
+    fn encounter_interval() {
+        let (a, b) = crossing();
+        let found = encounters(&a, &b, 200.);
+        assert_eq!(found.len(), 1);
+        let e = found[0];
+        assert!(e.start < 60. && e.end > 60., "{e:?}");
+        assert!((e.cpa.m - 60.).abs() < 1e-6, "{e:?}");
+
+        assert!(
+            encounters(&a, &b, 200.)
+                .iter()
+                .all(|e| e.cpa.distance <= 200.)
+        );
+    }
+
+    #[test]
+    fn fleet_only_reports_close_pairs() {
+        let (a, b) = crossing();
+        // same path as a, but two hours later
+        let c =
+            LineStringM::new(a.0.iter().map(|c| (c.x, c.y, c.m + 7200.).into()).collect()).unwrap();
+        // far away at the same time
+        let d =
+            LineStringM::new(b.0.iter().map(|c| (c.x + 1., c.y, c.m).into()).collect()).unwrap();
+
+        let conf = EncounterConf::builder().threshold(200.).build();
+        let found = detect_encounters(&[a, b, c, d], &conf);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].0, found[0].1), (0, 1));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let encounter = Encounter {
+            start: 30.,
+            end: 90.5,
+            cpa: Cpa {
+                m: 60.,
+                distance: 12.5,
+            },
+        };
+        let json = serde_json::to_string(&encounter).unwrap();
+        assert_eq!(
+            json,
+            r#"{"start":30.0,"end":90.5,"cpa":{"m":60.0,"distance":12.5}}"#
+        );
+        assert_eq!(serde_json::from_str::<Encounter>(&json).unwrap(), encounter);
+    }
+}
@@ -0,0 +1,298 @@
+//! Estimation of the parameters of [`DbScanConf`] from the data it will cluster, instead of guessing them.
+//!
+//! The distance threshold is the knee of the k-distance curve, i.e. the distance to the k-th nearest neighbor of every point that is slow enough to be clustered, sorted.
+//! Points in stops have close neighbors and make up the flat part of the curve, while the points past the knee are noise (e.g. slow steaming or drifting).
+//! The time threshold is found the same way from the time gaps between the slow points, see [`Estimate::max_time_thres`].
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::num::NonZero;
+
+use chrono::TimeDelta;
+use geo::Coord;
+use rstar::RTree;
+use rstar::primitives::GeomWithData;
+use typed_builder::TypedBuilder;
+
+use crate::algo::footprint::Plane;
+use crate::algo::stop_cluster::{DbScanConf, Neighborhood};
+use crate::types::crs::{Crs, Epsg};
+use crate::types::measure;
+use crate::types::pointm::PointM;
+
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq)]
+pub struct EstimateConf {
+    /// Which neighbor the k-distance is measured to, the suggested `min_cluster_size` is one more since a point is its own neighbor
+    #[builder(default = NonZero::new(4).expect("4 is not zero"))]
+    pub(crate) k: NonZero<usize>,
+    /// Only points slower than this are considered, as in [`DbScanConf`]
+    #[builder(default = 1.5)]
+    pub(crate) speed_thres: f32,
+}
+
+/// Suggested parameters of [`DbScanConf`], along with the curves they were found on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    /// Knee of the k-distance curve in meters (or the unit of the CRS if it is not in degrees)
+    pub dist_thres: f64,
+    pub min_cluster_size: NonZero<usize>,
+    /// Knee of the time gaps between subsequent slow points, times `min_cluster_size`, such that a core point can reach that many subsequent points
+    pub max_time_thres: TimeDelta,
+    pub speed_thres: f32,
+    /// The sorted k-distances
+    pub k_distances: Vec<f64>,
+    /// The sorted time gaps in seconds
+    pub time_gaps: Vec<f64>,
+}
+
+impl Estimate {
+    /// A [`DbScanConf`] with the suggested parameters, `dist` has to measure in meters for degree CRSs.
+    ///
+    /// Neighbors are found with [`Neighborhood::RTree`], since the k-distances do not depend on the order of the points either.
+    pub fn conf<Dist, const CRS: u64>(&self, dist: Dist) -> DbScanConf<Dist, CRS>
+    where
+        Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+    {
+        DbScanConf::builder()
+            .min_cluster_size(self.min_cluster_size)
+            .dist(dist)
+            .dist_thres(self.dist_thres)
+            .speed_thres(self.speed_thres)
+            .max_time_thres(self.max_time_thres)
+            .neighborhood(Neighborhood::RTree)
+            .build()
+    }
+}
+
+/// Index of the knee of an ascending, convex curve (Kneedle), i.e. the point furthest below the line through its first and last point.
+///
+/// [`None`] if the curve has less than 3 points, the last point if it is flat.
+pub fn knee(sorted: &[f64]) -> Option<usize> {
+    let (first, last) = (*sorted.first()?, *sorted.last()?);
+    if sorted.len() < 3 {
+        return None;
+    }
+    if last <= first {
+        return Some(sorted.len() - 1);
+    }
+    let n = (sorted.len() - 1) as f64;
+    (0..sorted.len()).max_by(|a, b| {
+        let below = |i: usize| i as f64 / n - (sorted[i] - first) / (last - first);
+        below(*a).total_cmp(&below(*b))
+    })
+}
+
+type Indexed = GeomWithData<[f64; 2], usize>;
+
+/// Curves of a set of slow points, see [`EstimateConf::estimate`]
+#[derive(Default)]
+struct Curves {
+    k_distances: Vec<f64>,
+    time_gaps: Vec<f64>,
+}
+
+impl EstimateConf {
+    /// Estimates the parameters from a single trajectory or a sample of a fleet, every trajectory is given as the input of [`DbScanConf::run`].
+    ///
+    /// Neighbors are only searched within the same trajectory, as stops are clustered per trajectory.
+    /// [`None`] if there are too few slow points to find a knee.
+    pub fn estimate<'a, const CRS: u64>(
+        &self,
+        trajectories: impl IntoIterator<Item = &'a [(PointM<CRS>, f32)]>,
+    ) -> Option<Estimate>
+    where
+        Epsg<CRS>: Crs,
+    {
+        let mut curves = Curves::default();
+        for points in trajectories {
+            self.curves(
+                points,
+                |_| (),
+                |(), k, g| {
+                    curves.k_distances.extend(k);
+                    curves.time_gaps.extend(g);
+                },
+            );
+        }
+        self.knees(curves)
+    }
+
+    /// Like [`EstimateConf::estimate`], with separate parameters for every region, e.g. harbours and offshore.
+    ///
+    /// `region` gives the region of a point, the k-distance of a point and the time gap before it are attributed to its region.
+    /// Regions with too few slow points are left out.
+    pub fn estimate_by_region<'a, const CRS: u64, R>(
+        &self,
+        trajectories: impl IntoIterator<Item = &'a [(PointM<CRS>, f32)]>,
+        region: impl Fn(&PointM<CRS>) -> R,
+    ) -> HashMap<R, Estimate>
+    where
+        Epsg<CRS>: Crs,
+        R: Eq + Hash,
+    {
+        let mut curves: HashMap<R, Curves> = HashMap::new();
+        for points in trajectories {
+            self.curves(points, &region, |r, k, g| {
+                let c = curves.entry(r).or_default();
+                c.k_distances.extend(k);
+                c.time_gaps.extend(g);
+            });
+        }
+        curves
+            .into_iter()
+            .filter_map(|(r, c)| Some((r, self.knees(c)?)))
+            .collect()
+    }
+
+    /// Computes the k-distance of every slow point and the time gap to the previous slow point, and passes them to `sink` along with the key of the point
+    fn curves<const CRS: u64, R>(
+        &self,
+        points: &[(PointM<CRS>, f32)],
+        key: impl Fn(&PointM<CRS>) -> R,
+        mut sink: impl FnMut(R, Option<f64>, Option<f64>),
+    ) where
+        Epsg<CRS>: Crs,
+    {
+        let slow = points
+            .iter()
+            .filter(|(_, sog)| *sog < self.speed_thres)
+            .map(|(p, _)| p)
+            .collect::<Vec<_>>();
+        if slow.is_empty() {
+            return;
+        }
+
+        let plane = Plane::new::<CRS>(slow.iter().map(|p| Coord::from((p.coord.x, p.coord.y))));
+        let projected = slow
+            .iter()
+            .map(|p| plane.project(Coord::from((p.coord.x, p.coord.y))))
+            .collect::<Vec<_>>();
+        let tree = RTree::bulk_load(
+            projected
+                .iter()
+                .enumerate()
+                .map(|(i, c)| Indexed::new([c.x, c.y], i))
+                .collect(),
+        );
+
+        for (i, p) in slow.iter().enumerate() {
+            let c = projected[i];
+            // the point itself (or a duplicate of it) is the nearest
+            let k_distance = tree
+                .nearest_neighbor_iter(&[c.x, c.y])
+                .nth(self.k.get())
+                .map(|n| (n.geom()[0] - c.x).hypot(n.geom()[1] - c.y));
+            let gap = i.checked_sub(1).map(|prev| p.coord.m - slow[prev].coord.m);
+            sink(key(p), k_distance, gap);
+        }
+    }
+
+    fn knees(&self, mut curves: Curves) -> Option<Estimate> {
+        curves.k_distances.sort_by(f64::total_cmp);
+        curves.time_gaps.sort_by(f64::total_cmp);
+        let min_cluster_size = self.k.saturating_add(1);
+
+        let dist_thres = curves.k_distances[knee(&curves.k_distances)?];
+        let gap = curves.time_gaps[knee(&curves.time_gaps)?];
+        let max_time_thres = measure::to_timedelta(gap * min_cluster_size.get() as f64);
+
+        Some(Estimate {
+            dist_thres,
+            min_cluster_size,
+            max_time_thres,
+            speed_thres: self.speed_thres,
+            k_distances: curves.k_distances,
+            time_gaps: curves.time_gaps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::stop_cluster::{StopOrLs, cluster_to_traj_with_stop_object};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn knee_of_curve() {
+        assert_eq!(knee(&[1., 1., 1., 1., 2., 10.]), Some(4));
+        assert_eq!(knee(&[1., 1., 1.]), Some(2));
+        assert_eq!(knee(&[1., 2.]), None);
+    }
+
+    /// Three stops with about 5 m between positions, an hour apart, and slow points spaced 300 m apart in between
+    fn trajectory(origin: f64) -> Vec<(PointM<3857>, f32)> {
+        let mut points = vec![];
+        let mut m = 0.;
+        for stop in 0..3 {
+            for i in 0..36 {
+                // spread like the seeds of a sunflower
+                let (angle, radius) = (f64::from(i) * 2.4, 3. * f64::from(i).sqrt());
+                let x = origin + f64::from(stop) * 5000. + radius * angle.cos();
+                points.push((PointM::from((x, radius * angle.sin(), m)), 0.5));
+                m += 10.;
+            }
+            // slow steaming between the stops
+            for i in 0..7 {
+                let x = origin + f64::from(stop) * 5000. + 1000. + f64::from(i) * 300.;
+                points.push((PointM::from((x, 2000., m)), 1.));
+                m += 60.;
+            }
+            // fast, not considered
+            points.push((PointM::from((origin, 0., m)), 10.));
+            m += 3600.;
+        }
+        points
+    }
+
+    #[test]
+    fn estimate_stops() {
+        let points = trajectory(0.);
+        let estimate = EstimateConf::builder()
+            .build()
+            .estimate([points.as_slice()])
+            .unwrap();
+
+        assert!((5.0..50.).contains(&estimate.dist_thres), "{estimate:?}");
+        assert_eq!(estimate.min_cluster_size.get(), 5);
+        assert_eq!(estimate.max_time_thres, TimeDelta::seconds(300));
+        assert_eq!(estimate.k_distances.len(), 3 * 43);
+
+        let mut conf = estimate.conf(|a: &PointM<3857>, b| a.distance_m(b));
+        let classes = conf.run(&points);
+        let clusters = classes.iter().filter_map(|(_, c)| c.cluster()).max();
+        assert_eq!(clusters, Some(2));
+        assert_eq!(
+            cluster_to_traj_with_stop_object(classes)
+                .0
+                .iter()
+                .filter(|s| matches!(s, StopOrLs::Stop { .. }))
+                .count(),
+            3
+        );
+
+        assert_eq!(EstimateConf::builder().build().estimate::<3857>([]), None);
+    }
+
+    #[test]
+    fn estimate_per_region() {
+        let harbour = trajectory(0.);
+        // the same pattern offshore, but spread out ten times as far
+        let offshore = trajectory(100_000.)
+            .into_iter()
+            .map(|(p, sog)| {
+                let x = 100_000. + (p.coord.x - 100_000.) * 10.;
+                (PointM::from((x, p.coord.y * 10., p.coord.m)), sog)
+            })
+            .collect::<Vec<_>>();
+
+        let estimates = EstimateConf::builder()
+            .build()
+            .estimate_by_region([harbour.as_slice(), offshore.as_slice()], |p| {
+                p.coord.x >= 50_000.
+            });
+        assert_eq!(estimates.len(), 2);
+        let ratio = estimates[&true].dist_thres / estimates[&false].dist_thres;
+        assert!((ratio - 10.).abs() < 1e-6, "{estimates:?}");
+    }
+}
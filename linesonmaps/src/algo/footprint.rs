@@ -0,0 +1,288 @@
+//! Footprints of stops, i.e. the polygon covering the positions of a ship during a stop, see [`cluster_to_traj_with_footprint`](crate::algo::stop_cluster::cluster_to_traj_with_footprint).
+//!
+//! Apart from the convex hull, footprints are computed on a plane tangent to the mean position of the stop, so distances are in meters for degree CRSs as well.
+
+use geo::{
+    Area, Buffer, ConcaveHull, ConvexHull, Coord, LineString, MultiPoint, MultiPolygon, Polygon,
+    TriangulateDelaunay, unary_union,
+};
+
+use crate::types::crs::{Crs, Epsg, Unit};
+use crate::types::pointm::PointM;
+
+/// Number of segments of circles
+const CIRCLE_SEGMENTS: usize = 64;
+
+/// The shape of the polygon of a stop
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Footprint {
+    /// Smallest convex polygon containing every position
+    #[default]
+    ConvexHull,
+    /// A polygon containing every position that follows them more closely than the convex hull, see [`ConcaveHull`].
+    ///
+    /// Lower `concavity` gives more concave polygons, 2 is a reasonable start.
+    ConcaveHull { concavity: f64 },
+    /// Union of the Delaunay triangles of the positions with a circumradius below `alpha` meters.
+    ///
+    /// Only the largest part is kept if the triangles are not connected, and the convex hull is used if no triangle is small enough.
+    AlphaShape { alpha: f64 },
+    /// Union of circles with a radius of `radius` meters around every position.
+    ///
+    /// Only the largest part is kept if the circles are not connected, a radius of at least half the distance threshold of the clustering keeps them connected.
+    Buffer { radius: f64 },
+    /// Smallest circle containing every position, e.g. the swing circle of a ship at anchor
+    MinimumEnclosingCircle,
+}
+
+impl Footprint {
+    /// The footprint of `points`, an empty polygon if there are none
+    pub fn polygon<const CRS: u64>(&self, points: &[PointM<CRS>]) -> Polygon
+    where
+        Epsg<CRS>: Crs,
+    {
+        let coords = points.iter().map(|p| Coord::from((p.coord.x, p.coord.y)));
+        if let Footprint::ConvexHull = self {
+            return LineString::from_iter(coords).convex_hull();
+        }
+        if points.is_empty() {
+            return Polygon::new(LineString::new(vec![]), vec![]);
+        }
+
+        let plane = Plane::new::<CRS>(coords.clone());
+        let projected = coords.map(|c| plane.project(c)).collect::<Vec<_>>();
+        let polygon = match *self {
+            Footprint::ConvexHull => unreachable!("handled above"),
+            Footprint::ConcaveHull { concavity } => {
+                MultiPoint::from(projected).concave_hull(concavity)
+            }
+            Footprint::AlphaShape { alpha } => alpha_shape(projected, alpha),
+            Footprint::Buffer { radius } => largest(MultiPoint::from(projected).buffer(radius))
+                .unwrap_or_else(|| Polygon::new(LineString::new(vec![]), vec![])),
+            Footprint::MinimumEnclosingCircle => {
+                let (center, radius) = minimum_enclosing_circle(&projected);
+                circle(center, radius)
+            }
+        };
+        plane.unproject(polygon)
+    }
+}
+
+/// Equirectangular projection around `origin` for degree CRSs, a translation otherwise
+pub(crate) struct Plane {
+    origin: Coord,
+    scale: Coord,
+}
+
+impl Plane {
+    pub(crate) fn new<const CRS: u64>(coords: impl ExactSizeIterator<Item = Coord>) -> Self
+    where
+        Epsg<CRS>: Crs,
+    {
+        let n = coords.len() as f64;
+        let origin = coords.fold(Coord::zero(), |acc, c| acc + c) / n;
+        let scale = <Epsg<CRS> as Crs>::Unit::scale(origin);
+        Plane { origin, scale }
+    }
+
+    pub(crate) fn project(&self, c: Coord) -> Coord {
+        Coord::from((
+            (c.x - self.origin.x) * self.scale.x,
+            (c.y - self.origin.y) * self.scale.y,
+        ))
+    }
+
+    fn unproject(&self, polygon: Polygon) -> Polygon {
+        use geo::MapCoords;
+        polygon.map_coords(|c| {
+            Coord::from((
+                c.x / self.scale.x + self.origin.x,
+                c.y / self.scale.y + self.origin.y,
+            ))
+        })
+    }
+}
+
+fn largest(polygons: MultiPolygon) -> Option<Polygon> {
+    polygons
+        .into_iter()
+        .max_by(|a, b| a.unsigned_area().total_cmp(&b.unsigned_area()))
+}
+
+fn alpha_shape(coords: Vec<Coord>, alpha: f64) -> Polygon {
+    let points = LineString::new(coords);
+    let triangles = points
+        .unconstrained_triangulation()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|t| {
+            let [a, b, c] = t.to_array();
+            circumradius(a, b, c) < alpha
+        })
+        .map(|t| t.to_polygon())
+        .collect::<Vec<_>>();
+
+    largest(unary_union(&triangles)).unwrap_or_else(|| points.convex_hull())
+}
+
+fn circumradius(a: Coord, b: Coord, c: Coord) -> f64 {
+    let (ab, bc, ca) = (len(b - a), len(c - b), len(a - c));
+    let area2 = ((b - a).x * (c - a).y - (b - a).y * (c - a).x).abs();
+    match area2 {
+        0. => f64::INFINITY,
+        area2 => ab * bc * ca / (2. * area2),
+    }
+}
+
+fn len(c: Coord) -> f64 {
+    c.x.hypot(c.y)
+}
+
+/// Center and radius of the smallest circle containing `coords`, only the vertices of their convex hull are considered
+pub(crate) fn minimum_enclosing_circle(coords: &[Coord]) -> (Coord, f64) {
+    let hull = LineString::from(coords.to_vec())
+        .convex_hull()
+        .exterior()
+        .0
+        .clone();
+    let contains =
+        |(center, radius): (Coord, f64), p: Coord| len(p - center) <= radius * (1. + 1e-9);
+
+    // iterative version of Welzl's algorithm, the hull is small so the worst case does not matter
+    let mut circle = (hull[0], 0.);
+    for i in 1..hull.len() {
+        if contains(circle, hull[i]) {
+            continue;
+        }
+        circle = (hull[i], 0.);
+        for j in 0..i {
+            if contains(circle, hull[j]) {
+                continue;
+            }
+            circle = ((hull[i] + hull[j]) / 2., len(hull[i] - hull[j]) / 2.);
+            for k in 0..j {
+                if !contains(circle, hull[k]) {
+                    circle = circumcircle(hull[i], hull[j], hull[k]).unwrap_or(circle);
+                }
+            }
+        }
+    }
+    circle
+}
+
+/// The circle through `a`, `b` and `c`, [`None`] if they are collinear
+fn circumcircle(a: Coord, b: Coord, c: Coord) -> Option<(Coord, f64)> {
+    let (b, c) = (b - a, c - a);
+    let d = 2. * (b.x * c.y - b.y * c.x);
+    if d == 0. {
+        return None;
+    }
+    let (b2, c2) = (b.x * b.x + b.y * b.y, c.x * c.x + c.y * c.y);
+    let center = Coord::from(((c.y * b2 - b.y * c2) / d, (b.x * c2 - c.x * b2) / d));
+    Some((center + a, len(center)))
+}
+
+fn circle(center: Coord, radius: f64) -> Polygon {
+    let ring = (0..=CIRCLE_SEGMENTS)
+        .map(|i| {
+            let angle =
+                std::f64::consts::TAU * (i % CIRCLE_SEGMENTS) as f64 / CIRCLE_SEGMENTS as f64;
+            // circumscribed, such that the polygon contains the circle
+            let r = radius / (std::f64::consts::PI / CIRCLE_SEGMENTS as f64).cos();
+            center + Coord::from((angle.cos() * r, angle.sin() * r))
+        })
+        .collect();
+    Polygon::new(LineString::new(ring), vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::stop_cluster::triangulate_stop_object;
+    use crate::types::consts::METERS_PER_DEGREE;
+    use geo::{Contains, Distance, Euclidean, GeodesicArea, Point};
+    use pretty_assertions::assert_eq;
+
+    /// A ship moored along a quay shaped like a half circle with a radius of 200 m, positions alternate between both sides of the ship
+    fn quay() -> Vec<PointM<3857>> {
+        (0..=40)
+            .map(|i| {
+                let angle = std::f64::consts::PI * f64::from(i) / 40.;
+                let radius = 200. + f64::from(i % 2) * 15.;
+                PointM::from((radius * angle.cos(), radius * angle.sin(), f64::from(i)))
+            })
+            .collect()
+    }
+
+    /// Up to rounding errors of the projection and the union, points on the boundary may lie just outside
+    fn covers(polygon: &Polygon, points: &[PointM<3857>]) -> bool {
+        points
+            .iter()
+            .all(|p| Euclidean.distance(polygon, &Point::new(p.coord.x, p.coord.y)) < 1e-6)
+    }
+
+    #[test]
+    fn quay_footprints() {
+        let points = quay();
+        let convex = Footprint::ConvexHull.polygon(&points);
+        assert!(convex.contains(&Point::new(0., 100.)));
+
+        // follows the positions, but does not necessarily dig into the enclosed water
+        let concave = Footprint::ConcaveHull { concavity: 0.1 }.polygon(&points);
+        assert!(covers(&concave, &points));
+        assert!(concave.unsigned_area() < convex.unsigned_area());
+
+        for footprint in [
+            Footprint::AlphaShape { alpha: 50. },
+            Footprint::Buffer { radius: 15. },
+        ] {
+            let polygon = footprint.polygon(&points);
+            assert!(covers(&polygon, &points), "{footprint:?}");
+            // does not cover the water enclosed by the quay
+            assert!(
+                polygon.unsigned_area() < convex.unsigned_area() / 2.,
+                "{footprint:?}"
+            );
+            assert!(!polygon.contains(&Point::new(0., 100.)), "{footprint:?}");
+            assert!(!triangulate_stop_object(&polygon).unwrap().is_empty());
+        }
+
+        let circle = Footprint::MinimumEnclosingCircle.polygon(&points);
+        assert!(covers(&circle, &points));
+        // about the chord of the half circle as diameter
+        let area = circle.unsigned_area() / std::f64::consts::PI;
+        assert!((200_f64.powi(2)..215_f64.powi(2)).contains(&area), "{area}");
+
+        // too small for any triangle, falls back to the convex hull
+        let alpha = Footprint::AlphaShape { alpha: 1. }.polygon(&points);
+        assert_eq!(alpha.exterior().0.len(), convex.exterior().0.len());
+        assert!((alpha.unsigned_area() - convex.unsigned_area()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn swing_circle() {
+        // a ship swinging around its anchor 100 m away
+        let anchor = (10_f64, 56_f64);
+        let points = (0..36)
+            .map(|i| {
+                let angle = f64::from(i * 10).to_radians();
+                let (dx, dy) = (100. * angle.cos(), 100. * angle.sin());
+                PointM::<4326>::from((
+                    anchor.0 + dx / (METERS_PER_DEGREE * anchor.1.to_radians().cos()),
+                    anchor.1 + dy / METERS_PER_DEGREE,
+                    f64::from(i),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let circle = Footprint::MinimumEnclosingCircle.polygon(&points);
+        let area = std::f64::consts::PI * 100. * 100.;
+        assert!((circle.geodesic_area_unsigned() - area).abs() < area * 0.02);
+        assert!(circle.contains(&Point::new(anchor.0, anchor.1)));
+
+        assert_eq!(
+            Footprint::MinimumEnclosingCircle.polygon::<4326>(&[]),
+            Polygon::new(LineString::new(vec![]), vec![])
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use geo::{BoundingRect, Distance, Intersects, Line, Point, Polygon, Rect};
+use rstar::{AABB, RTree, RTreeObject};
+
+use crate::types::linem::{LineM, interpolation};
+use crate::types::linestringm::LineStringM;
+use crate::types::pointm::PointM;
+
+/// A segment of a trajectory in a [`SegmentIndex`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexedSegment<const CRS: u64 = 4326> {
+    /// Index of the trajectory the segment belongs to
+    pub trajectory: usize,
+    /// Index of the segment within its trajectory
+    pub segment: usize,
+    pub line: LineM<CRS>,
+}
+
+impl<const CRS: u64> IndexedSegment<CRS> {
+    /// The part of the segment between measure `from` and `to`, [`None`] if it lies outside of the interval
+    fn clip(&self, from: f64, to: f64) -> Option<Line> {
+        let first = self.line.from.coord.m.max(from);
+        let last = self.line.to.coord.m.min(to);
+        if first > last {
+            return None;
+        }
+        let start = self.line.locate_along(first, interpolation::Linear)?;
+        let end = self.line.locate_along(last, interpolation::Linear)?;
+        Some(Line::new(Point::from(start), Point::from(end)))
+    }
+}
+
+impl<const CRS: u64> RTreeObject for IndexedSegment<CRS> {
+    type Envelope = AABB<[f64; 3]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        let (from, to) = (self.line.from.coord, self.line.to.coord);
+        AABB::from_corners([from.x, from.y, from.m], [to.x, to.y, to.m])
+    }
+}
+
+/// Spatio-temporal (x, y, m) index over the segments of a collection of trajectories.
+///
+/// The index is an R-tree that is bulk loaded once, trajectories are referred to by their position in the collection it was built from.
+/// Segments are assumed to be straight lines in the coordinate space of `CRS`, as with [`Linear`](interpolation::Linear).
+#[derive(Debug, Clone)]
+pub struct SegmentIndex<const CRS: u64 = 4326> {
+    tree: RTree<IndexedSegment<CRS>>,
+}
+
+impl<const CRS: u64> SegmentIndex<CRS> {
+    pub fn new<'a, I>(trajectories: I) -> Self
+    where
+        I: IntoIterator<Item = &'a LineStringM<CRS>>,
+    {
+        let segments = trajectories
+            .into_iter()
+            .enumerate()
+            .flat_map(|(trajectory, ls)| {
+                ls.lines()
+                    .enumerate()
+                    .map(move |(segment, line)| IndexedSegment {
+                        trajectory,
+                        segment,
+                        line,
+                    })
+            })
+            .collect();
+
+        SegmentIndex {
+            tree: RTree::bulk_load(segments),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.size() == 0
+    }
+
+    /// Segments whose envelope intersects `rect` between measure `from` and `to`, without further refinement
+    pub fn candidates(
+        &self,
+        rect: Rect,
+        from: f64,
+        to: f64,
+    ) -> impl Iterator<Item = &IndexedSegment<CRS>> {
+        let envelope = AABB::from_corners(
+            [rect.min().x, rect.min().y, from],
+            [rect.max().x, rect.max().y, to],
+        );
+        self.tree.locate_in_envelope_intersecting(&envelope)
+    }
+
+    /// Segments that are within `rect` at some point between measure `from` and `to`
+    pub fn range(
+        &self,
+        rect: Rect,
+        from: f64,
+        to: f64,
+    ) -> impl Iterator<Item = &IndexedSegment<CRS>> {
+        self.candidates(rect, from, to)
+            .filter(move |s| s.clip(from, to).is_some_and(|l| l.intersects(&rect)))
+    }
+
+    /// Trajectories that are within `rect` at some point between measure `from` and `to`, sorted and deduplicated
+    pub fn trajectories_in(&self, rect: Rect, from: f64, to: f64) -> Vec<usize> {
+        dedup(self.range(rect, from, to))
+    }
+
+    /// Trajectories that intersect `polygon` at some point between measure `from` and `to`, sorted and deduplicated
+    pub fn intersecting(&self, polygon: &Polygon, from: f64, to: f64) -> Vec<usize> {
+        let Some(rect) = polygon.bounding_rect() else {
+            return vec![];
+        };
+        dedup(
+            self.candidates(rect, from, to)
+                .filter(|s| s.clip(from, to).is_some_and(|l| l.intersects(polygon))),
+        )
+    }
+
+    /// The (at most) `k` trajectories closest to `point` at measure `m`, along with their position and distance according to `metric`, closest first.
+    ///
+    /// Only trajectories that have a position at `m` are considered.
+    /// The index only narrows the search down to the segments that span `m`, which are all scanned: `metric` is not related to the coordinates of `CRS`,
+    /// so the spatial part of the index can not be used to prune them. The cost is linear in the number of trajectories underway at `m`.
+    pub fn scan_nearest_at<M>(
+        &self,
+        point: PointM<CRS>,
+        m: f64,
+        k: usize,
+        metric: &M,
+    ) -> Vec<(usize, PointM<CRS>, f64)>
+    where
+        M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+    {
+        let slab = AABB::from_corners(
+            [f64::NEG_INFINITY, f64::NEG_INFINITY, m],
+            [f64::INFINITY, f64::INFINITY, m],
+        );
+
+        let mut positions = self
+            .tree
+            .locate_in_envelope_intersecting(&slab)
+            .filter_map(|s| {
+                let position = s.line.locate_along(m, interpolation::Linear)?;
+                Some((s.trajectory, position, metric.distance(point, position)))
+            })
+            .collect::<Vec<_>>();
+        // a trajectory is found twice if m is exactly at one of its vertices
+        positions.sort_by(|a, b| a.0.cmp(&b.0).then(a.2.total_cmp(&b.2)));
+        positions.dedup_by_key(|p| p.0);
+
+        positions.sort_by(|a, b| a.2.total_cmp(&b.2));
+        positions.truncate(k);
+        positions
+    }
+}
+
+fn dedup<'a, const CRS: u64>(
+    segments: impl Iterator<Item = &'a IndexedSegment<CRS>>,
+) -> Vec<usize> {
+    let mut res = segments.map(|s| s.trajectory).collect::<Vec<_>>();
+    res.sort_unstable();
+    res.dedup();
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::coordm::CoordM;
+    use geo::{Euclidean, coord, polygon};
+    use pretty_assertions::assert_eq;
+
+    /// Three ships sailing east along y = 0, 100 and 200, the last one an hour later
+    fn fleet() -> Vec<LineStringM<3857>> {
+        [(0., 0.), (100., 0.), (200., 3600.)]
+            .map(|(y, t)| {
+                let coords: Vec<CoordM<3857>> = (0..=10)
+                    .map(|i| (i as f64 * 100., y, t + i as f64 * 60.).into())
+                    .collect();
+                LineStringM::new(coords).unwrap()
+            })
+            .to_vec()
+    }
+
+    #[test]
+    fn range_query() {
+        let index = SegmentIndex::new(&fleet());
+        assert_eq!(index.len(), 30);
+
+        let rect = Rect::new(coord! {x: 0., y: -50.}, coord! {x: 1000., y: 250.});
+        assert_eq!(index.trajectories_in(rect, 0., 600.), vec![0, 1]);
+        assert_eq!(index.trajectories_in(rect, 0., 10000.), vec![0, 1, 2]);
+
+        // the ships pass x = 500 at t = 300, so they are not in the rect yet at t = 100
+        let rect = Rect::new(coord! {x: 450., y: -50.}, coord! {x: 550., y: 250.});
+        assert_eq!(index.trajectories_in(rect, 0., 100.), Vec::<usize>::new());
+        assert_eq!(index.trajectories_in(rect, 0., 300.), vec![0, 1]);
+    }
+
+    #[test]
+    fn nearest_at_time() {
+        let index = SegmentIndex::new(&fleet());
+        let point = PointM::from((300., 90., 0.));
+
+        let nearest = index.scan_nearest_at(point, 180., 1, &Euclidean);
+        assert_eq!(nearest.len(), 1);
+        assert_eq!(nearest[0].0, 1);
+        assert_eq!(nearest[0].2, 10.);
+
+        // the third ship is not underway yet
+        let nearest = index.scan_nearest_at(point, 180., 5, &Euclidean);
+        assert_eq!(nearest.iter().map(|n| n.0).collect::<Vec<_>>(), vec![1, 0]);
+    }
+
+    #[test]
+    fn intersects_polygon() {
+        let index = SegmentIndex::new(&fleet());
+        let triangle = polygon![(x: 0., y: 150.), (x: 1000., y: 150.), (x: 500., y: 250.)];
+
+        assert_eq!(index.intersecting(&triangle, 0., 10000.), vec![2]);
+        assert!(index.intersecting(&triangle, 0., 3600.).is_empty());
+    }
+}
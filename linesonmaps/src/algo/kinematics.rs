@@ -0,0 +1,284 @@
+use std::num::NonZero;
+
+use geo::Distance;
+use typed_builder::TypedBuilder;
+
+use crate::algo::stop_cluster::MS_TO_KNOT;
+use crate::types::crs::{Crs, Epsg};
+use crate::types::linestringm::LineStringM;
+use crate::types::pointm::PointM;
+
+/// Kinematics implied by two subsequent points of a trajectory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentKinematics {
+    /// Measure of the first point of the segment
+    pub m: f64,
+    /// Seconds between the two points
+    pub duration: f64,
+    /// Meters between the two points
+    pub distance: f64,
+    /// Speed over ground in knots, [`None`] if both points share a timestamp
+    pub sog: Option<f64>,
+    /// Course over ground in degrees, [`None`] if the ship did not move
+    pub cog: Option<f64>,
+}
+
+/// Kinematics implied by the positions around a point of a trajectory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VertexKinematics {
+    /// Measure of the point
+    pub m: f64,
+    /// Speed over ground in knots
+    pub sog: Option<f64>,
+    /// Course over ground in degrees
+    pub cog: Option<f64>,
+    /// Change in speed over ground in knots per second
+    pub acceleration: Option<f64>,
+    /// Rate of turn in degrees per minute, positive when turning to starboard (as reported by AIS)
+    pub rot: Option<f64>,
+}
+
+/// Computes the implied speed and course of every segment of `ls`, using `metric` to measure distances.
+pub fn segment_kinematics<const CRS: u64, M>(
+    ls: &LineStringM<CRS>,
+    metric: &M,
+) -> Vec<SegmentKinematics>
+where
+    Epsg<CRS>: Crs,
+    M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+{
+    ls.lines()
+        .map(|l| {
+            let duration = l.to.coord.m - l.from.coord.m;
+            let distance = metric.distance(l.from, l.to);
+            SegmentKinematics {
+                m: l.from.coord.m,
+                duration,
+                distance,
+                sog: (duration > 0.).then(|| distance / duration * MS_TO_KNOT),
+                cog: moved(&l.from, &l.to).then(|| l.from.bearing_to(&l.to)),
+            }
+        })
+        .collect()
+}
+
+/// Computes the implied kinematics at every point of `ls`.
+///
+/// Speed and course at a point are averaged over the `window` points before and after it,
+/// acceleration and rate of turn are the differences between the incoming and outgoing window.
+pub fn vertex_kinematics<const CRS: u64, M>(
+    ls: &LineStringM<CRS>,
+    metric: &M,
+    window: NonZero<usize>,
+) -> Vec<VertexKinematics>
+where
+    Epsg<CRS>: Crs,
+    M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+{
+    let coords = &ls.0;
+    // cumulative distance travelled, such that the distance of any window is a single subtraction
+    let travelled = std::iter::once(0.)
+        .chain(
+            segment_kinematics(ls, metric)
+                .into_iter()
+                .scan(0., |acc, s| {
+                    *acc += s.distance;
+                    Some(*acc)
+                }),
+        )
+        .collect::<Vec<_>>();
+
+    // average speed and course between the points at index `a` and `b`
+    let average = |a: usize, b: usize| {
+        if a >= b {
+            return (None, None);
+        }
+        let (from, to) = (PointM::from(coords[a]), PointM::from(coords[b]));
+        let duration = to.coord.m - from.coord.m;
+        let sog = (duration > 0.).then(|| (travelled[b] - travelled[a]) / duration * MS_TO_KNOT);
+        let cog = moved(&from, &to).then(|| from.bearing_to(&to));
+        (sog, cog)
+    };
+
+    (0..coords.len())
+        .map(|i| {
+            let first = i.saturating_sub(window.get());
+            let last = (i + window.get()).min(coords.len() - 1);
+            let (sog, cog) = average(first, last);
+            let (sog_in, cog_in) = average(first, i);
+            let (sog_out, cog_out) = average(i, last);
+            // time between the centers of the incoming and outgoing window
+            let duration = (coords[last].m - coords[first].m) / 2.;
+
+            let acceleration = match (sog_in, sog_out) {
+                (Some(a), Some(b)) if duration > 0. => Some((b - a) / duration),
+                _ => None,
+            };
+            let rot = match (cog_in, cog_out) {
+                (Some(a), Some(b)) if duration > 0. => Some(turn(a, b) / duration * 60.),
+                _ => None,
+            };
+
+            VertexKinematics {
+                m: coords[i].m,
+                sog,
+                cog,
+                acceleration,
+                rot,
+            }
+        })
+        .collect()
+}
+
+/// Values reported by the ship itself at the time of a point
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Reported {
+    /// Speed over ground in knots
+    pub sog: Option<f64>,
+    /// Course over ground in degrees
+    pub cog: Option<f64>,
+    /// Rate of turn in degrees per minute
+    pub rot: Option<f64>,
+}
+
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Maximum difference in speed over ground (knots)
+    sog: f64,
+    /// Maximum difference in course over ground (degrees)
+    cog: f64,
+    /// Maximum difference in rate of turn (degrees per minute)
+    rot: f64,
+    /// Below this implied speed (knots) the course is mostly noise, so course and rate of turn are not compared
+    #[builder(default = 1.0)]
+    min_sog: f64,
+}
+
+/// Which reported values disagree with the implied ones, missing values never disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Disagreement {
+    pub sog: bool,
+    pub cog: bool,
+    pub rot: bool,
+}
+
+impl Disagreement {
+    pub fn any(&self) -> bool {
+        self.sog || self.cog || self.rot
+    }
+}
+
+/// Compares the implied kinematics of a point with the values reported at the same time.
+pub fn compare_reported(
+    derived: &VertexKinematics,
+    reported: &Reported,
+    tolerance: &Tolerance,
+) -> Disagreement {
+    let exceeds = |a: Option<f64>, b: Option<f64>, diff: fn(f64, f64) -> f64, tol: f64| {
+        a.zip(b).is_some_and(|(a, b)| diff(a, b).abs() > tol)
+    };
+    let has_course = derived.sog.is_some_and(|sog| sog >= tolerance.min_sog);
+
+    Disagreement {
+        sog: exceeds(derived.sog, reported.sog, |a, b| b - a, tolerance.sog),
+        cog: has_course && exceeds(derived.cog, reported.cog, turn, tolerance.cog),
+        rot: has_course && exceeds(derived.rot, reported.rot, |a, b| b - a, tolerance.rot),
+    }
+}
+
+fn moved<const CRS: u64>(from: &PointM<CRS>, to: &PointM<CRS>) -> bool {
+    (from.coord.x, from.coord.y) != (to.coord.x, to.coord.y)
+}
+
+/// Signed smallest turn in degrees from heading `a` to heading `b`, positive when turning clockwise
+pub(crate) fn turn(a: f64, b: f64) -> f64 {
+    (b - a + 540.).rem_euclid(360.) - 180.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::coordm::CoordM;
+    use geo::{Euclidean, Geodesic};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn straight_line_constant_speed() {
+        // 10 m/s due east
+        let coords: Vec<CoordM<3857>> = (0..5)
+            .map(|i| (i as f64 * 100., 0., i as f64 * 10.).into())
+            .collect();
+        let ls = LineStringM::new(coords).unwrap();
+
+        let segments = segment_kinematics(&ls, &Euclidean);
+        assert_eq!(segments.len(), 4);
+        assert!(
+            segments
+                .iter()
+                .all(|s| (s.sog.unwrap() - 10. * MS_TO_KNOT).abs() < 1e-9)
+        );
+        assert!(segments.iter().all(|s| (s.cog.unwrap() - 90.).abs() < 1e-9));
+
+        let vertices = vertex_kinematics(&ls, &Euclidean, 2.try_into().unwrap());
+        assert_eq!(vertices.len(), 5);
+        assert!(
+            vertices
+                .iter()
+                .all(|v| (v.sog.unwrap() - 10. * MS_TO_KNOT).abs() < 1e-9)
+        );
+        // no acceleration or turn at the interior points, and the end points only have one side
+        assert_eq!(vertices[0].acceleration, None);
+        assert!(
+            vertices[1..4]
+                .iter()
+                .all(|v| v.acceleration.unwrap().abs() < 1e-9)
+        );
+        assert!(vertices[1..4].iter().all(|v| v.rot.unwrap().abs() < 1e-9));
+    }
+
+    #[test]
+    fn turn_to_starboard() {
+        // north for 60 seconds, then east for 60 seconds
+        let coords: Vec<CoordM<3857>> = [(0., 0., 0.), (0., 600., 60.), (600., 600., 120.)]
+            .map(|f| f.into())
+            .to_vec();
+        let ls = LineStringM::new(coords).unwrap();
+
+        let vertices = vertex_kinematics(&ls, &Euclidean, 1.try_into().unwrap());
+        // 90 degrees over the minute between the segment centers
+        assert!((vertices[1].rot.unwrap() - 90.).abs() < 1e-9);
+        assert!((vertices[1].cog.unwrap() - 45.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flags_disagreements() {
+        let coords: Vec<CoordM<4326>> = [(10., 56., 0.), (10., 56.01, 60.), (10., 56.02, 120.)]
+            .map(|f| f.into())
+            .to_vec();
+        let ls = LineStringM::new(coords).unwrap();
+        let vertices = vertex_kinematics(&ls, &Geodesic, 1.try_into().unwrap());
+        let tolerance = Tolerance::builder().sog(1.0).cog(10.0).rot(5.0).build();
+
+        // ~1113 meters per minute is ~36 knots due north
+        let agrees = Reported {
+            sog: Some(36.0),
+            cog: Some(359.0),
+            rot: None,
+        };
+        assert!(!compare_reported(&vertices[1], &agrees, &tolerance).any());
+
+        let disagrees = Reported {
+            sog: Some(0.0),
+            cog: Some(180.0),
+            rot: Some(20.0),
+        };
+        assert_eq!(
+            compare_reported(&vertices[1], &disagrees, &tolerance),
+            Disagreement {
+                sog: true,
+                cog: true,
+                rot: true
+            }
+        );
+    }
+}
@@ -1,2 +1,16 @@
+pub mod density;
+pub mod encounter;
+pub mod estimate;
+pub mod footprint;
+pub mod index;
+pub mod kinematics;
+pub mod resample;
 pub mod segmenter;
-pub mod stop_cluster;
\ No newline at end of file
+pub mod similarity;
+pub mod simplify;
+pub mod stop_area;
+pub mod stop_cluster;
+pub mod stop_kind;
+pub mod strategy;
+pub mod stream_segmenter;
+pub mod stream_stop;
\ No newline at end of file
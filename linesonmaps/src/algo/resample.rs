@@ -0,0 +1,148 @@
+use chrono::TimeDelta;
+use typed_builder::TypedBuilder;
+
+use crate::algo::segmenter::{TrajectorySplit, segmenter};
+use crate::types::linem::{Interpolation, interpolation};
+use crate::types::linestringm::LineStringM;
+use crate::types::multilinestringm::MultiLineStringM;
+
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq)]
+pub struct ResampleConf<I = interpolation::Linear>
+where
+    I: Default,
+{
+    /// Time between two samples, samples are aligned to multiples of `period` since the unix epoch
+    pub(crate) period: TimeDelta,
+    /// Largest time interval between two original points that will be interpolated over, the output is split at larger gaps
+    pub(crate) max_gap: TimeDelta,
+    /// How the samples are placed between the original points, see [`Interpolation`]
+    #[builder(default)]
+    pub(crate) interpolation: I,
+}
+
+/// Resamples a linestring to a fixed time grid.
+///
+/// The linestring is first split wherever two subsequent points are more than [`ResampleConf::max_gap`] apart, such that no positions are invented across outages.
+/// Parts that contain less than 2 samples are dropped.
+pub fn resample<const CRS: u64, I>(
+    ls: &LineStringM<CRS>,
+    conf: &ResampleConf<I>,
+) -> MultiLineStringM<CRS>
+where
+    I: Interpolation<CRS>,
+{
+    debug_assert!(conf.period > TimeDelta::zero(), "period should be positive");
+    if ls.0.is_empty() {
+        return MultiLineStringM(vec![]);
+    }
+    let max_gap = conf.max_gap.as_seconds_f64();
+
+    segmenter(ls.clone(), |f, s| s.coord.m - f.coord.m <= max_gap)
+        .into_iter()
+        .filter_map(|ts| match ts {
+            TrajectorySplit::SubTrajectory(part) => resample_part(&part, conf),
+            TrajectorySplit::Point(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn resample_part<const CRS: u64, I>(
+    part: &LineStringM<CRS>,
+    conf: &ResampleConf<I>,
+) -> Option<LineStringM<CRS>>
+where
+    I: Interpolation<CRS>,
+{
+    let period = conf.period.as_seconds_f64();
+    let first = (part.0.first()?.m / period).ceil() as i64;
+    let last = (part.0.last()?.m / period).floor() as i64;
+
+    let samples = (first..=last)
+        .map(|k| {
+            part.locate_along(k as f64 * period, conf.interpolation)
+                .map(|p| p.coord)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    match samples.len() {
+        0 | 1 => None,
+        _ => LineStringM::new(samples),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::coordm::CoordM;
+    use pretty_assertions::assert_eq;
+    use wkb::reader::read_wkb;
+
+    #[test]
+    fn resample_aligned_to_epoch() {
+        let coords: Vec<CoordM<3857>> = [(0., 0., 7.), (100., 0., 67.), (100., 100., 97.)]
+            .map(|f| f.into())
+            .to_vec();
+        let ls = LineStringM::new(coords).unwrap();
+        let conf: ResampleConf = ResampleConf::builder()
+            .period(TimeDelta::seconds(30))
+            .max_gap(TimeDelta::seconds(120))
+            .build();
+
+        let res = resample(&ls, &conf);
+        assert_eq!(res.0.len(), 1);
+        let ms = res.0[0].0.iter().map(|c| c.m).collect::<Vec<_>>();
+        assert_eq!(ms, vec![30., 60., 90.]);
+
+        // 53 of the 60 seconds between the first two points
+        let expected: CoordM<3857> = (100. * 53. / 60., 0., 60.).into();
+        assert!((res.0[0].0[1].x - expected.x).abs() < 1e-9);
+        assert_eq!(res.0[0].0[1].y, expected.y);
+    }
+
+    #[test]
+    fn resample_does_not_cross_gaps() {
+        let coords: Vec<CoordM<3857>> = [
+            (0., 0., 0.),
+            (10., 0., 60.),
+            (20., 0., 120.),
+            (30., 0., 3600.),
+            (40., 0., 3660.),
+            (50., 0., 3720.),
+        ]
+        .map(|f| f.into())
+        .to_vec();
+        let ls = LineStringM::new(coords).unwrap();
+        let conf: ResampleConf = ResampleConf::builder()
+            .period(TimeDelta::seconds(30))
+            .max_gap(TimeDelta::seconds(300))
+            .build();
+
+        let res = resample(&ls, &conf);
+        assert_eq!(res.0.len(), 2);
+        assert!(res.0.iter().all(|ls| ls.0.len() == 5));
+        assert_eq!(res.0[1].0.first().map(|c| c.m), Some(3600.));
+    }
+
+    #[test]
+    fn resample_big_traj() {
+        const HEXSTRING: &str = include_str!("./resources/207138000.txt");
+
+        let bytea = hex::decode(HEXSTRING).unwrap();
+        let wkb = read_wkb(&bytea).unwrap();
+        let ls = LineStringM::<4326>::try_from(wkb).unwrap();
+        let conf = ResampleConf::builder()
+            .period(TimeDelta::seconds(30))
+            .max_gap(TimeDelta::seconds(600))
+            .interpolation(interpolation::Geodesic)
+            .build();
+
+        let res = resample(&ls, &conf);
+        assert!(!res.0.is_empty());
+        assert!(res.0.iter().all(|part| {
+            part.0
+                .windows(2)
+                .all(|w| w[1].m - w[0].m == 30. && w[0].m % 30. == 0.)
+        }));
+    }
+}
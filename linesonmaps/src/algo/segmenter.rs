@@ -1,11 +1,21 @@
+use std::num::NonZero;
+
 use chrono::{DateTime, TimeDelta, Utc};
+use rayon::prelude::*;
 use wkb::writer::{WriteOptions, write_line_string, write_point};
 
+use crate::algo::strategy::{SegmentationStrategy, segment};
 use crate::types::coordm::CoordM;
+use crate::types::crs::{Crs, Epsg};
+use crate::types::error::Error;
+use crate::types::ewkb::ToEwkb;
+use crate::types::geojson::{ToGeoJson, ToMfJson};
 use crate::types::linestringm::LineStringM;
 use crate::types::pointm::PointM;
+use crate::types::wkt::{FromWkt, ToWkt};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TrajectorySplit<const CRS: u64> {
     /// A split that resulted in a [`LineStringM`]
     SubTrajectory(LineStringM<CRS>),
@@ -30,6 +40,36 @@ impl<const CRS: u64> TrajectorySplit<CRS> {
         LineStringM::new(concat)
     }
 
+    /// The split consisting of `coords`, [`None`] if there are none.
+    ///
+    /// `coords` must be temporally ordered.
+    pub(crate) fn from_coords(coords: Vec<CoordM<CRS>>) -> Option<Self> {
+        match coords.as_slice() {
+            [] => None,
+            [coord] => Some(TrajectorySplit::Point(coord.into())),
+            _ => Some(TrajectorySplit::SubTrajectory(
+                LineStringM::new(coords).expect("split points are temporally ordered"),
+            )),
+        }
+    }
+
+    /// The start and duration of the split, [`None`] if a measure is out of the range of [`DateTime`]
+    pub fn interval(&self) -> Option<(DateTime<Utc>, TimeDelta)> {
+        match self {
+            TrajectorySplit::Point(p) => Some((p.time()?, TimeDelta::zero())),
+            TrajectorySplit::SubTrajectory(ls) => {
+                let first = ls.0.first()?.time()?;
+                const {
+                    // quick and dirty testing suggests a too large timestamp is somewhere between 2^42 and 2^43 (i.e. 141338-07-19 02:25:04+00 and 280707-02-04 04:50:08+00), i would be shocked if GST still uses this program by then
+                    assert!(DateTime::from_timestamp_secs(1 << 43).is_none());
+                    assert!(DateTime::from_timestamp_secs(1 << 42).is_some());
+                }
+                let last = ls.0.last()?.time()?;
+                Some((first, last - first))
+            }
+        }
+    }
+
     pub fn to_wkb(&self) -> Vec<u8> {
         let mut writer = Vec::<u8>::new();
         match self {
@@ -58,6 +98,57 @@ impl<const CRS: u64> TrajectorySplit<CRS> {
     }
 }
 
+impl<const CRS: u64> ToEwkb for TrajectorySplit<CRS> {
+    fn to_ewkb(&self) -> Vec<u8> {
+        match self {
+            Self::SubTrajectory(ls) => ls.to_ewkb(),
+            Self::Point(p) => p.to_ewkb(),
+        }
+    }
+}
+
+impl<const CRS: u64> ToWkt for TrajectorySplit<CRS> {
+    fn to_wkt(&self) -> String {
+        match self {
+            Self::SubTrajectory(ls) => ls.to_wkt(),
+            Self::Point(p) => p.to_wkt(),
+        }
+    }
+}
+
+impl<const CRS: u64> FromWkt for TrajectorySplit<CRS> {
+    fn from_wkt(wkt: &str) -> Result<Self, Error> {
+        match PointM::from_wkt(wkt) {
+            Err(Error::IncompatibleType) => LineStringM::from_wkt(wkt).map(Self::SubTrajectory),
+            p => p.map(Self::Point),
+        }
+    }
+}
+
+impl<const CRS: u64> ToGeoJson for TrajectorySplit<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_geojson(&self) -> String {
+        match self {
+            Self::SubTrajectory(ls) => ls.to_geojson(),
+            Self::Point(p) => p.to_geojson(),
+        }
+    }
+}
+
+impl<const CRS: u64> ToMfJson for TrajectorySplit<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_mf_json(&self) -> String {
+        match self {
+            Self::SubTrajectory(ls) => ls.to_mf_json(),
+            Self::Point(p) => p.to_mf_json(),
+        }
+    }
+}
+
 type Split<const CRS: u64> = Vec<TrajectorySplit<CRS>>;
 
 /// Splits a linestring into (potentially) several sub-segments using a splitting function.
@@ -131,36 +222,31 @@ where
     splits
 }
 
-pub fn segment_timestamp<const CRS: u64, F>(
+/// The time interval of every split of `ls`, as start and duration.
+///
+/// `strategy`: Where to split, e.g. a function comparing two subsequent points as with [`segmenter`], see [`SegmentationStrategy`]
+pub fn segment_timestamp<const CRS: u64, S>(
     ls: LineStringM<CRS>,
-    func: F,
+    mut strategy: S,
 ) -> Vec<(DateTime<Utc>, TimeDelta)>
 where
-    F: Fn(PointM<CRS>, PointM<CRS>) -> bool,
+    S: SegmentationStrategy<CRS>,
 {
-    let segments = segmenter(ls, func);
+    let segments = segment(ls, &mut strategy);
 
     let times = segments
-        .into_iter()
-        .map(|ts| match ts {
-            TrajectorySplit::Point(p) => Some((
-                DateTime::from_timestamp_secs(p.coord.m as i64)?,
-                TimeDelta::zero(),
-            )), // casting to integer will truncate/round down, this is probably fine for creating a start interval
-            TrajectorySplit::SubTrajectory(ls) => {
-                let first = DateTime::from_timestamp_secs(ls.0.first()?.m as i64)?; // Linestrings generated from `segmenter` always have length > 1, so there should be some points
-                const {
-                    // quick and dirty testing suggests a too large timestamp is somewhere between 2^42 and 2^43 (i.e. 141338-07-19 02:25:04+00 and 280707-02-04 04:50:08+00), i would be shocked if GST still uses this program by then
-                    assert!(DateTime::from_timestamp_secs(1 << 43).is_none());
-                    assert!(DateTime::from_timestamp_secs(1 << 42).is_some());
-                }
-                let last = DateTime::from_timestamp_secs(ls.0.last()?.m.ceil() as i64)?; // calling ceil causes some loss in precision, but ensures last point is included in interval, 
-                Some((first, last - first))
-            }
-        })
+        .iter()
+        .map(TrajectorySplit::interval)
         .collect::<Option<Vec<_>>>()
         .expect("failed to convert measure value to DateTime object, measure value may be too big");
 
+    debug_assert_non_overlapping(&times);
+
+    times
+}
+
+/// Checks that subsequent time intervals do not overlap with each other for more than 1 second, in debug builds only
+fn debug_assert_non_overlapping(times: &[(DateTime<Utc>, TimeDelta)]) {
     debug_assert!(
         times.windows(2).all(
             |p| ((p[1].0 - (p[0].0 + p[0].1)).num_milliseconds() <= 1000)
@@ -172,14 +258,85 @@ where
             .map(|p| (p[1].0 - (p[0].0 + p[0].1)).num_seconds())
             .max()
     );
+}
 
-    times
+/// Statistics of the segmentation of a single trajectory, see [`segment_batch`]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SegmentStats {
+    /// Number of splits, including single points
+    pub num_splits: usize,
+    /// Number of splits consisting of a single point
+    pub num_points: usize,
+    /// Mean number of points of the splits that are sub-trajectories, [`None`] if there are none
+    pub mean_length: Option<f64>,
+}
+
+impl SegmentStats {
+    pub fn new<const CRS: u64>(splits: &[TrajectorySplit<CRS>]) -> Self {
+        let lengths = splits
+            .iter()
+            .filter_map(|s| match s {
+                TrajectorySplit::SubTrajectory(ls) => Some(ls.0.len()),
+                TrajectorySplit::Point(_) => None,
+            })
+            .collect::<Vec<_>>();
+        SegmentStats {
+            num_splits: splits.len(),
+            num_points: splits.len() - lengths.len(),
+            mean_length: (!lengths.is_empty())
+                .then(|| lengths.iter().sum::<usize>() as f64 / lengths.len() as f64),
+        }
+    }
+}
+
+/// Segments many trajectories in parallel, each with its own copy of `strategy`, see [`segment`].
+///
+/// `trajectories` are consumed `chunk_size` at a time as the returned iterator is advanced, so at most one chunk is held in memory.
+/// Results are returned in the order of `trajectories`, along with the key (e.g. the MMSI) of every trajectory.
+pub fn segment_batch<const CRS: u64, K, I, S>(
+    trajectories: I,
+    strategy: &S,
+    chunk_size: NonZero<usize>,
+) -> impl Iterator<Item = (K, Split<CRS>, SegmentStats)>
+where
+    K: Send,
+    I: IntoIterator<Item = (K, LineStringM<CRS>)>,
+    S: SegmentationStrategy<CRS> + Clone + Sync,
+{
+    let mut trajectories = trajectories.into_iter();
+    std::iter::from_fn(move || {
+        let chunk = trajectories
+            .by_ref()
+            .take(chunk_size.get())
+            .collect::<Vec<_>>();
+        (!chunk.is_empty()).then(|| {
+            chunk
+                .into_par_iter()
+                .map(|(key, ls)| {
+                    let splits = segment(ls, &mut strategy.clone());
+                    if cfg!(debug_assertions)
+                        && let Some(times) = splits
+                            .iter()
+                            .map(TrajectorySplit::interval)
+                            .collect::<Option<Vec<_>>>()
+                    {
+                        debug_assert_non_overlapping(&times);
+                    }
+                    let stats = SegmentStats::new(&splits);
+                    (key, splits, stats)
+                })
+                .collect::<Vec<_>>()
+        })
+    })
+    .flatten()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::types::coordm::CoordM;
+    use crate::types::linem::interpolation;
     use geo::{Distance, Geodesic};
     use pretty_assertions::{assert_eq, assert_ne};
     use wkb::reader::read_wkb;
@@ -407,7 +564,7 @@ mod tests {
                     lsm_s
                         .points()
                         .filter(move |p| {
-                            let t = DateTime::<Utc>::from_timestamp_secs(p.coord.m as i64).unwrap();
+                            let t = p.time().unwrap();
                             t >= tz && t < tz + i
                         })
                         .map(|p| p.coord)
@@ -469,7 +626,7 @@ mod tests {
                     lsm_s
                         .points()
                         .filter(move |p| {
-                            let t = DateTime::<Utc>::from_timestamp_secs(p.coord.m as i64).unwrap();
+                            let t = p.time().unwrap();
                             t >= tz && t < tz + i
                         })
                         .map(|p| p.coord)
@@ -491,4 +648,120 @@ mod tests {
 
         assert!(max_dist<1000.0, "{max_dist}");
     }
+
+    #[test]
+    fn segment_timestamp_to_geometry() {
+        const HEXSTRING: &str = include_str!("./resources/207138000.txt");
+
+        let bytea = hex::decode(HEXSTRING).unwrap();
+        let wkb = read_wkb(&bytea).unwrap();
+        let lsm = LineStringM::<4326>::try_from(wkb).unwrap();
+
+        let func = |f: PointM, s: PointM| {
+            geo::algorithm::line_measures::metric_spaces::Geodesic.distance(f, s) <= 1000.
+                && s.coord.m - f.coord.m <= 60.
+        };
+
+        let intervals = segment_timestamp(lsm.clone(), func);
+        let slices = intervals
+            .iter()
+            .filter(|(_, i)| !i.is_zero())
+            .map(|(tz, i)| lsm.slice_between(*tz, *tz + *i, interpolation::Geodesic))
+            .collect::<Option<Vec<_>>>()
+            .expect("non-empty intervals should be turned back into geometry");
+
+        assert!(slices.iter().all(|ls| ls.0.len() > 1));
+    }
+
+    #[test]
+    fn batch_in_order() {
+        let fixtures = [
+            include_str!("./resources/207138000.txt"),
+            include_str!("./resources/205689000.txt"),
+            include_str!("./resources/219013708.txt"),
+        ]
+        .map(|hexstring| {
+            let bytea = hex::decode(hexstring.replace('"', "").trim()).unwrap();
+            LineStringM::<4326>::try_from(read_wkb(&bytea).unwrap()).unwrap()
+        });
+        let func =
+            |f: PointM, s: PointM| Geodesic.distance(f, s) <= 1000. && s.coord.m - f.coord.m <= 60.;
+
+        // chunks smaller than the input, the last one not full
+        let batch = segment_batch(
+            fixtures.iter().cloned().enumerate(),
+            &func,
+            NonZero::new(2).unwrap(),
+        )
+        .collect::<Vec<_>>();
+
+        assert_eq!(batch.len(), fixtures.len());
+        for ((key, splits, stats), ls) in batch.into_iter().zip(fixtures) {
+            let expected = segmenter(ls, func);
+            assert_eq!(stats, SegmentStats::new(&expected));
+            assert_eq!(splits, expected);
+            assert_eq!(stats.num_splits, splits.len());
+            assert!(stats.mean_length.is_some_and(|l| l >= 2.), "{key}");
+        }
+
+        let stats = SegmentStats::new(&[
+            TrajectorySplit::<4326>::Point((1., 2., 3.).into()),
+            TrajectorySplit::SubTrajectory(
+                LineStringM::new(vec![(1., 2., 4.).into(), (1., 2., 5.).into()]).unwrap(),
+            ),
+            TrajectorySplit::SubTrajectory(
+                LineStringM::new(vec![
+                    (1., 2., 6.).into(),
+                    (1., 2., 7.).into(),
+                    (1., 2., 8.).into(),
+                ])
+                .unwrap(),
+            ),
+        ]);
+        assert_eq!(
+            stats,
+            SegmentStats {
+                num_splits: 3,
+                num_points: 1,
+                mean_length: Some(2.5)
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let ls = LineStringM::<4326>::new(vec![(1., 2., 0.).into(), (1., 3., 1.).into()]).unwrap();
+        let splits = vec![
+            TrajectorySplit::SubTrajectory(ls),
+            TrajectorySplit::Point((1., 4., 2.).into()),
+        ];
+        let json = serde_json::to_string(&splits).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"SubTrajectory":[[1.0,2.0,0.0],[1.0,3.0,1.0]]},{"Point":[1.0,4.0,2.0]}]"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Vec<TrajectorySplit<4326>>>(&json).unwrap(),
+            splits
+        );
+
+        // unordered sub-trajectories are rejected
+        assert!(
+            serde_json::from_str::<TrajectorySplit<4326>>(
+                r#"{"SubTrajectory":[[1.0,2.0,1.0],[1.0,3.0,0.0]]}"#
+            )
+            .is_err()
+        );
+
+        let stats = SegmentStats::new(&splits);
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(json, r#"{"num_splits":2,"num_points":1,"mean_length":2.0}"#);
+        assert_eq!(serde_json::from_str::<SegmentStats>(&json).unwrap(), stats);
+        let empty = SegmentStats::default();
+        assert_eq!(
+            serde_json::from_str::<SegmentStats>(&serde_json::to_string(&empty).unwrap()).unwrap(),
+            empty
+        );
+    }
 }
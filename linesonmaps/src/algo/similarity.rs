@@ -0,0 +1,343 @@
+use std::ops::Range;
+
+use geo::Distance;
+use typed_builder::TypedBuilder;
+
+use crate::types::coordm::CoordM;
+use crate::types::linestringm::LineStringM;
+use crate::types::pointm::PointM;
+
+/// Shared configuration of the trajectory similarity measures.
+///
+/// The measures take the ground metric between two points separately, e.g. [`geo::Geodesic`], [`geo::Haversine`] or [`geo::Euclidean`].
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq, Default)]
+pub struct SimilarityConf {
+    /// Meters per second of time difference, makes the ground distance spatio-temporal: `sqrt(dist² + (time_scale * Δm)²)`
+    #[builder(default, setter(strip_option))]
+    pub(crate) time_scale: Option<f64>,
+    /// Sakoe-Chiba band, i.e. how far (in points) a warping path may stray from the diagonal.
+    /// It is widened if needed, such that a warping path always exists for linestrings of different length.
+    #[builder(default, setter(strip_option))]
+    pub(crate) band: Option<usize>,
+    /// Distances are abandoned (and [`None`] returned) as soon as they are known to exceed this value
+    #[builder(default, setter(strip_option))]
+    pub(crate) abandon_above: Option<f64>,
+}
+
+impl SimilarityConf {
+    fn ground<const CRS: u64, M>(&self, metric: &M, a: &CoordM<CRS>, b: &CoordM<CRS>) -> f64
+    where
+        M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+    {
+        let dist = metric.distance(PointM::from(a), PointM::from(b));
+        match self.time_scale {
+            Some(scale) => dist.hypot((a.m - b.m) * scale),
+            None => dist,
+        }
+    }
+
+    /// Indices of the second linestring (of length `m`) that may be matched with index `i` of the first (of length `n`)
+    fn band_range(&self, i: usize, n: usize, m: usize) -> Range<usize> {
+        let Some(band) = self.band else {
+            return 0..m;
+        };
+        let slope = if n > 1 {
+            (m - 1) as f64 / (n - 1) as f64
+        } else {
+            0.
+        };
+        let band = (band as f64).max(slope.max(1.) / 2.);
+        let center = i as f64 * slope;
+        let first = (center - band).ceil().max(0.) as usize;
+        let last = ((center + band).floor() as usize).min(m - 1);
+        first..last + 1
+    }
+
+    fn abandoned(&self, row: &[f64]) -> bool {
+        self.abandon_above
+            .is_some_and(|t| row.iter().copied().fold(f64::INFINITY, f64::min) > t)
+    }
+}
+
+/// Dynamic Time Warping distance, the minimal sum of ground distances over all warping paths.
+pub fn dtw<const CRS: u64, M>(
+    a: &LineStringM<CRS>,
+    b: &LineStringM<CRS>,
+    metric: &M,
+    conf: &SimilarityConf,
+) -> Option<f64>
+where
+    M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+{
+    warping_path(a, b, metric, conf, |cost, best| cost + best)
+}
+
+/// Discrete Fréchet distance, the minimal largest ground distance over all warping paths.
+pub fn discrete_frechet<const CRS: u64, M>(
+    a: &LineStringM<CRS>,
+    b: &LineStringM<CRS>,
+    metric: &M,
+    conf: &SimilarityConf,
+) -> Option<f64>
+where
+    M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+{
+    warping_path(a, b, metric, conf, f64::max)
+}
+
+/// Shared dynamic programming of [`dtw`] and [`discrete_frechet`], `combine` accumulates the ground distance of a cell with the best predecessor.
+fn warping_path<const CRS: u64, M, F>(
+    a: &LineStringM<CRS>,
+    b: &LineStringM<CRS>,
+    metric: &M,
+    conf: &SimilarityConf,
+    combine: F,
+) -> Option<f64>
+where
+    M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+    F: Fn(f64, f64) -> f64,
+{
+    let (a, b) = (&a.0, &b.0);
+    let (n, m) = (a.len(), b.len());
+    if n == 0 || m == 0 {
+        return None;
+    }
+
+    let mut prev = vec![f64::INFINITY; m];
+    let mut curr = vec![f64::INFINITY; m];
+    for (i, p) in a.iter().enumerate() {
+        curr.fill(f64::INFINITY);
+        for j in conf.band_range(i, n, m) {
+            let best = match (i, j) {
+                (0, 0) => 0.,
+                (0, _) => curr[j - 1],
+                (_, 0) => prev[j],
+                _ => prev[j].min(curr[j - 1]).min(prev[j - 1]),
+            };
+            curr[j] = combine(conf.ground(metric, p, &b[j]), best);
+        }
+        // costs never decrease along a warping path
+        if conf.abandoned(&curr) {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    Some(prev[m - 1]).filter(|d| d.is_finite())
+}
+
+/// Symmetric Hausdorff distance, the largest ground distance from a point of either linestring to the closest point of the other.
+///
+/// Ignores [`SimilarityConf::band`], as every pair of points is a candidate.
+pub fn hausdorff<const CRS: u64, M>(
+    a: &LineStringM<CRS>,
+    b: &LineStringM<CRS>,
+    metric: &M,
+    conf: &SimilarityConf,
+) -> Option<f64>
+where
+    M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+{
+    let forward = directed_hausdorff(&a.0, &b.0, metric, conf, 0.)?;
+    directed_hausdorff(&b.0, &a.0, metric, conf, forward)
+}
+
+/// Directed Hausdorff distance from `a` to `b`, which is at least `lower`
+fn directed_hausdorff<const CRS: u64, M>(
+    a: &[CoordM<CRS>],
+    b: &[CoordM<CRS>],
+    metric: &M,
+    conf: &SimilarityConf,
+    lower: f64,
+) -> Option<f64>
+where
+    M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+{
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let mut max = lower;
+    for p in a {
+        let mut min = f64::INFINITY;
+        for q in b {
+            min = min.min(conf.ground(metric, p, q));
+            // p can not raise the maximum anymore
+            if min <= max {
+                break;
+            }
+        }
+        max = max.max(min);
+        if conf.abandon_above.is_some_and(|t| max > t) {
+            return None;
+        }
+    }
+    Some(max)
+}
+
+/// Edit Distance on Real sequences, the number of edits needed to turn `a` into `b`,
+/// where two points are equal if their ground distance is at most `eps`.
+pub fn edr<const CRS: u64, M>(
+    a: &LineStringM<CRS>,
+    b: &LineStringM<CRS>,
+    metric: &M,
+    conf: &SimilarityConf,
+    eps: f64,
+) -> Option<usize>
+where
+    M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+{
+    let (a, b) = (&a.0, &b.0);
+    let (n, m) = (a.len(), b.len());
+    if n == 0 || m == 0 {
+        return None;
+    }
+
+    // row i holds the edits between the first i points of `a` and the first j points of `b`
+    let mut prev = (0..=m).map(|j| j as f64).collect::<Vec<_>>();
+    let mut curr = vec![f64::INFINITY; m + 1];
+    for i in 1..=n {
+        curr.fill(f64::INFINITY);
+        curr[0] = i as f64;
+        for j in conf.band_range(i - 1, n, m).map(|j| j + 1) {
+            let substitute = if conf.ground(metric, &a[i - 1], &b[j - 1]) <= eps {
+                0.
+            } else {
+                1.
+            };
+            curr[j] = (prev[j - 1] + substitute)
+                .min(prev[j] + 1.)
+                .min(curr[j - 1] + 1.);
+        }
+        if conf.abandoned(&curr) {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    Some(prev[m]).filter(|d| d.is_finite()).map(|d| d as usize)
+}
+
+/// Longest Common SubSequence similarity, the fraction of points of the shorter linestring that can be matched in order,
+/// where two points match if their ground distance is at most `eps`.
+///
+/// As this is a similarity rather than a distance, [`SimilarityConf::abandon_above`] is ignored.
+pub fn lcss<const CRS: u64, M>(
+    a: &LineStringM<CRS>,
+    b: &LineStringM<CRS>,
+    metric: &M,
+    conf: &SimilarityConf,
+    eps: f64,
+) -> Option<f64>
+where
+    M: Distance<f64, PointM<CRS>, PointM<CRS>>,
+{
+    let (a, b) = (&a.0, &b.0);
+    let (n, m) = (a.len(), b.len());
+    if n == 0 || m == 0 {
+        return None;
+    }
+
+    let mut prev = vec![0_usize; m + 1];
+    let mut curr = vec![0_usize; m + 1];
+    for i in 1..=n {
+        curr.fill(0);
+        let band = conf.band_range(i - 1, n, m);
+        for j in 1..=m {
+            curr[j] = if band.contains(&(j - 1)) && conf.ground(metric, &a[i - 1], &b[j - 1]) <= eps
+            {
+                prev[j - 1] + 1
+            } else {
+                prev[j].max(curr[j - 1])
+            };
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    Some(prev[m] as f64 / n.min(m) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Euclidean, Haversine};
+    use pretty_assertions::assert_eq;
+    use wkb::reader::read_wkb;
+
+    fn line(y: f64, t_offset: f64) -> LineStringM<3857> {
+        LineStringM::new(
+            (0..10)
+                .map(|i| (i as f64 * 10., y, i as f64 + t_offset).into())
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn identical_trajectories() {
+        let a = line(0., 0.);
+        let conf = SimilarityConf::default();
+
+        assert_eq!(dtw(&a, &a, &Euclidean, &conf), Some(0.));
+        assert_eq!(discrete_frechet(&a, &a, &Euclidean, &conf), Some(0.));
+        assert_eq!(hausdorff(&a, &a, &Euclidean, &conf), Some(0.));
+        assert_eq!(edr(&a, &a, &Euclidean, &conf, 1.), Some(0));
+        assert_eq!(lcss(&a, &a, &Euclidean, &conf, 1.), Some(1.));
+    }
+
+    #[test]
+    fn parallel_trajectories() {
+        let (a, b) = (line(0., 0.), line(5., 0.));
+        let conf = SimilarityConf::default();
+
+        assert_eq!(dtw(&a, &b, &Euclidean, &conf), Some(50.));
+        assert_eq!(discrete_frechet(&a, &b, &Euclidean, &conf), Some(5.));
+        assert_eq!(hausdorff(&a, &b, &Euclidean, &conf), Some(5.));
+        assert_eq!(edr(&a, &b, &Euclidean, &conf, 1.), Some(10));
+        assert_eq!(edr(&a, &b, &Euclidean, &conf, 5.), Some(0));
+        assert_eq!(lcss(&a, &b, &Euclidean, &conf, 1.), Some(0.));
+    }
+
+    #[test]
+    fn spatio_temporal() {
+        // same path, but 10 seconds later
+        let (a, b) = (line(0., 0.), line(0., 10.));
+        let spatial = SimilarityConf::default();
+        let temporal = SimilarityConf::builder().time_scale(1.).build();
+
+        assert_eq!(discrete_frechet(&a, &b, &Euclidean, &spatial), Some(0.));
+        assert_eq!(discrete_frechet(&a, &b, &Euclidean, &temporal), Some(10.));
+    }
+
+    #[test]
+    fn band_and_abandon() {
+        let (a, b) = (line(0., 0.), line(5., 0.));
+        let banded = SimilarityConf::builder().band(1).build();
+        assert_eq!(dtw(&a, &b, &Euclidean, &banded), Some(50.));
+
+        // a band of 0 still has to allow a warping path between different lengths
+        let c = LineStringM::new(a.0.iter().copied().step_by(2).collect()).unwrap();
+        let narrow = SimilarityConf::builder().band(0).build();
+        assert!(dtw(&a, &c, &Euclidean, &narrow).is_some());
+
+        let abandon = SimilarityConf::builder().abandon_above(20.).build();
+        assert_eq!(dtw(&a, &b, &Euclidean, &abandon), None);
+        assert_eq!(discrete_frechet(&a, &b, &Euclidean, &abandon), Some(5.));
+        assert_eq!(edr(&a, &b, &Euclidean, &abandon, 1.), Some(10));
+    }
+
+    #[test]
+    fn big_traj_bounds() {
+        const HEXSTRING: &str = include_str!("./resources/207138000.txt");
+
+        let bytea = hex::decode(HEXSTRING).unwrap();
+        let wkb = read_wkb(&bytea).unwrap();
+        let ls = LineStringM::<4326>::try_from(wkb).unwrap();
+        let half = LineStringM::new(ls.0.iter().copied().step_by(2).collect()).unwrap();
+
+        let conf = SimilarityConf::builder().band(50).build();
+        let d = discrete_frechet(&ls, &half, &Haversine, &conf).unwrap();
+        let h = hausdorff(&ls, &half, &Haversine, &conf).unwrap();
+        // the hausdorff distance is a lower bound of the fréchet distance
+        assert!(h <= d, "{h} {d}");
+    }
+}
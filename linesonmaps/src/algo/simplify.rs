@@ -0,0 +1,255 @@
+use typed_builder::TypedBuilder;
+
+use crate::types::coordm::CoordM;
+use crate::types::crs::{Crs, Epsg};
+use crate::types::error::Error;
+use crate::types::linem::{LineM, interpolation};
+use crate::types::linestringm::LineStringM;
+
+/// Configuration of the time-aware top-down Douglas-Peucker (TD-TR) simplification, see [`simplify`]
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq)]
+pub struct SimplifyConf {
+    /// Maximum Synchronized Euclidean Distance (SED) in meters, i.e. the distance between an original point and the simplified trajectory at the same timestamp
+    pub(crate) sed_thres: f64,
+    /// Maximum difference in speed (m/s) between an original segment and the simplified segment replacing it
+    #[builder(default, setter(strip_option))]
+    pub(crate) speed_thres: Option<f64>,
+    /// Maximum difference in heading (degrees) between an original segment and the simplified segment replacing it
+    #[builder(default, setter(strip_option))]
+    pub(crate) heading_thres: Option<f64>,
+}
+
+/// Simplifies a linestring such that no original point is further than [`SimplifyConf::sed_thres`] meters away from where the simplified linestring places the ship at the same timestamp.
+///
+/// Distances are geodesic for degree based CRS's and euclidean for metric ones.
+/// The first and last point are always kept, and the output is a temporally ordered subset of the input.
+///
+/// Fails with [`Error::Timestamp`] if the points are not ordered by their measure.
+pub fn simplify<const CRS: u64>(
+    ls: &LineStringM<CRS>,
+    conf: &SimplifyConf,
+) -> Result<LineStringM<CRS>, Error>
+where
+    Epsg<CRS>: Crs,
+{
+    let coords = &ls.0;
+    if !coords.iter().map(|c| c.m).is_sorted() {
+        return Err(Error::Timestamp);
+    }
+    if coords.len() <= 2 {
+        return Ok(ls.clone());
+    }
+
+    let mut keep = vec![false; coords.len()];
+    keep[0] = true;
+    keep[coords.len() - 1] = true;
+
+    let mut stack = vec![(0, coords.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        if last <= first + 1 {
+            continue;
+        }
+        if let Some(split) = split_index(&coords[first..=last], conf) {
+            let split = first + split;
+            keep[split] = true;
+            stack.push((first, split));
+            stack.push((split, last));
+        }
+    }
+
+    let simplified = coords
+        .iter()
+        .zip(keep)
+        .filter_map(|(c, k)| k.then_some(*c))
+        .collect::<Vec<_>>();
+
+    LineStringM::new(simplified).ok_or(Error::Timestamp)
+}
+
+/// Finds the (relative) index of the point to split `coords` at, or [`None`] if the segment between the first and last point is a sufficient approximation
+fn split_index<const CRS: u64>(coords: &[CoordM<CRS>], conf: &SimplifyConf) -> Option<usize>
+where
+    Epsg<CRS>: Crs,
+{
+    let first = coords.first()?;
+    let last = coords.last()?;
+    let approx = LineM::from((*first, *last));
+
+    let (max_idx, max_sed) = coords
+        .iter()
+        .enumerate()
+        .skip(1)
+        .take(coords.len() - 2)
+        .filter_map(|(i, c)| {
+            let synced = approx.locate_along(c.m, interpolation::Native)?;
+            Some((i, synced.distance_m(&c.into())))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    if max_sed > conf.sed_thres {
+        return Some(max_idx);
+    }
+
+    // segments (i, i+1) that deviate too much from the approximation, the split is placed at an inner point of the segment
+    let inner = |i: usize| if i == 0 { 1 } else { i };
+
+    if let Some(speed_thres) = conf.speed_thres {
+        let approx_speed = speed(&approx);
+        let (i, diff) = coords
+            .windows(2)
+            .map(|w| (speed(&LineM::from((w[0], w[1]))) - approx_speed).abs())
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if diff > speed_thres {
+            return Some(inner(i));
+        }
+    }
+
+    if let Some(heading_thres) = conf.heading_thres {
+        // headings of short segments are mostly noise, and their deviation is bounded by the SED anyway
+        let is_long = |l: &LineM<CRS>| l.from.distance_m(&l.to) >= conf.sed_thres;
+        if is_long(&approx) {
+            let approx_heading = approx.from.bearing_to(&approx.to);
+            let (i, diff) = coords
+                .windows(2)
+                .map(|w| LineM::from((w[0], w[1])))
+                .enumerate()
+                .filter(|(_, l)| is_long(l))
+                .map(|(i, l)| (i, heading_diff(l.from.bearing_to(&l.to), approx_heading)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap_or((0, 0.));
+            if diff > heading_thres {
+                return Some(inner(i));
+            }
+        }
+    }
+
+    None
+}
+
+/// Speed in m/s, points with identical timestamps are considered stationary
+fn speed<const CRS: u64>(line: &LineM<CRS>) -> f64
+where
+    Epsg<CRS>: Crs,
+{
+    let dt = line.to.coord.m - line.from.coord.m;
+    if dt > 0. {
+        line.from.distance_m(&line.to) / dt
+    } else {
+        0.
+    }
+}
+
+/// Smallest angle in degrees between two headings
+fn heading_diff(a: f64, b: f64) -> f64 {
+    let diff = (a - b).rem_euclid(360.);
+    diff.min(360. - diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use wkb::reader::read_wkb;
+
+    #[test]
+    fn straight_line_is_reduced_to_endpoints() {
+        let coords: Vec<CoordM<3857>> = (0..10)
+            .map(|i| (i as f64 * 10., 0., i as f64).into())
+            .collect();
+        let ls = LineStringM::new(coords).unwrap();
+        let conf = SimplifyConf::builder().sed_thres(1.0).build();
+
+        let simplified = simplify(&ls, &conf).unwrap();
+        assert_eq!(simplified.0, vec![ls.0[0], ls.0[9]]);
+    }
+
+    #[test]
+    fn speed_change_is_kept() {
+        // spatially a straight line, but the ship waits at x = 50 for 100 seconds
+        let coords: Vec<CoordM<3857>> = [
+            (0., 0., 0.),
+            (50., 0., 5.),
+            (50., 0., 105.),
+            (100., 0., 110.),
+        ]
+        .map(|f| f.into())
+        .to_vec();
+        let ls = LineStringM::new(coords).unwrap();
+
+        // plain douglas-peucker would drop the middle points, but the SED is ~45 meters
+        let conf = SimplifyConf::builder().sed_thres(10.0).build();
+        assert_eq!(simplify(&ls, &conf).unwrap(), ls);
+
+        let conf = SimplifyConf::builder()
+            .sed_thres(100.0)
+            .speed_thres(1.0)
+            .build();
+        assert_eq!(simplify(&ls, &conf).unwrap(), ls);
+
+        let conf = SimplifyConf::builder().sed_thres(100.0).build();
+        assert_eq!(simplify(&ls, &conf).unwrap().0.len(), 2);
+    }
+
+    #[test]
+    fn heading_change_is_kept() {
+        let coords: Vec<CoordM<3857>> = [
+            (0., 0., 0.),
+            (100., 0., 10.),
+            (100., 30., 13.),
+            (200., 30., 23.),
+        ]
+        .map(|f| f.into())
+        .to_vec();
+        let ls = LineStringM::new(coords).unwrap();
+
+        let conf = SimplifyConf::builder().sed_thres(25.0).build();
+        assert_eq!(simplify(&ls, &conf).unwrap().0.len(), 2);
+
+        let conf = SimplifyConf::builder()
+            .sed_thres(25.0)
+            .heading_thres(10.0)
+            .build();
+        assert!(simplify(&ls, &conf).unwrap().0.len() > 2);
+    }
+
+    #[test]
+    fn unordered_input_is_rejected() {
+        let coords: Vec<CoordM<3857>> = [(0., 0., 0.), (50., 0., 10.), (100., 0., 5.)]
+            .map(|f| f.into())
+            .to_vec();
+        let ls = LineStringM(coords);
+        let conf = SimplifyConf::builder().sed_thres(1.0).build();
+
+        assert_eq!(simplify(&ls, &conf), Err(Error::Timestamp));
+    }
+
+    #[test]
+    fn simplify_big_traj_within_sed() {
+        const HEXSTRING: &str = include_str!("./resources/207138000.txt");
+
+        let bytea = hex::decode(HEXSTRING).unwrap();
+        let wkb = read_wkb(&bytea).unwrap();
+        let ls = LineStringM::<4326>::try_from(wkb).unwrap();
+        let conf = SimplifyConf::builder().sed_thres(25.0).build();
+
+        let simplified = simplify(&ls, &conf).unwrap();
+        assert!(simplified.0.len() < ls.0.len());
+        assert!(LineStringM::new(simplified.0.clone()).is_some());
+
+        // the ship can not be at several positions at once, so points sharing a timestamp are skipped
+        let max_sed = ls
+            .0
+            .windows(3)
+            .filter(|w| w[0].m < w[1].m && w[1].m < w[2].m)
+            .map(|w| {
+                let synced = simplified
+                    .locate_along(w[1].m, interpolation::Geodesic)
+                    .unwrap();
+                synced.distance_m(&w[1].into())
+            })
+            .max_by(f64::total_cmp)
+            .unwrap();
+        assert!(max_sed <= 25.0 + 1e-6, "{max_sed}");
+    }
+}
@@ -0,0 +1,384 @@
+//! Aggregation of the stops of many ships into the areas where ships stop in general, e.g. anchorages and berths.
+//!
+//! Stops (see [`StopOrLs::Stop`]) whose polygons lie within a distance threshold of each other are merged into one [`StopArea`], transitively,
+//! such that a row of berths along a quay becomes one area if the ships moored there are close enough.
+
+use std::collections::HashMap;
+use std::num::NonZero;
+use std::ops::RangeInclusive;
+
+use chrono::TimeDelta;
+use geo::{BoundingRect, Distance, Euclidean, MapCoords, Polygon, Rect};
+use typed_builder::TypedBuilder;
+
+use crate::algo::density::UnionFind;
+use crate::algo::footprint::{Footprint, Plane};
+use crate::algo::stop_cluster::StopOrLs;
+use crate::types::consts::METERS_PER_DEGREE;
+use crate::types::geojson::{ToGeoJson, feature, polygon_geometry, string};
+use crate::types::pointm::PointM;
+use crate::types::wkt::{ToWkt, polygon_to_wkt};
+
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq)]
+pub struct StopAreaConf {
+    /// Stops whose polygons are closer than this many meters belong to the same area
+    pub(crate) dist_thres: f64,
+    /// Areas with fewer stops are dropped
+    #[builder(default = NonZero::<usize>::MIN)]
+    pub(crate) min_visits: NonZero<usize>,
+    /// Areas visited by fewer distinct ships are dropped, e.g. the home berth of a single ferry
+    #[builder(default = NonZero::<usize>::MIN)]
+    pub(crate) min_vessels: NonZero<usize>,
+    /// The polygon of an area is the footprint of the vertices of the polygons of its stops
+    #[builder(default)]
+    pub(crate) footprint: Footprint,
+}
+
+/// Distribution of the durations of the stops in an area, quantiles are nearest-rank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DwellTime {
+    pub min: TimeDelta,
+    pub q1: TimeDelta,
+    pub median: TimeDelta,
+    pub q3: TimeDelta,
+    pub max: TimeDelta,
+    pub mean: TimeDelta,
+    pub total: TimeDelta,
+}
+
+impl DwellTime {
+    /// [`None`] if there are no durations
+    fn new(mut durations: Vec<TimeDelta>) -> Option<Self> {
+        durations.sort_unstable();
+        let n = durations.len();
+        let quantile = |q: f64| durations[((n - 1) as f64 * q).round() as usize];
+        let total = durations.iter().sum::<TimeDelta>();
+        Some(DwellTime {
+            min: *durations.first()?,
+            q1: quantile(0.25),
+            median: quantile(0.5),
+            q3: quantile(0.75),
+            max: *durations.last()?,
+            mean: total / i32::try_from(n).unwrap_or(i32::MAX),
+            total,
+        })
+    }
+}
+
+/// An area where ships stop, with statistics over the stops within it
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StopArea<K> {
+    /// Number of the area, areas are numbered by descending number of visits
+    pub id: usize,
+    /// Name of the area, e.g. from a port register, areas are only numbered by [`StopAreaConf::aggregate`]
+    pub name: Option<String>,
+    pub polygon: Polygon,
+    /// Number of stops in the area
+    pub visits: usize,
+    /// The distinct ships that stopped in the area, in the order of their first stop in the input
+    pub vessels: Vec<K>,
+    pub dwell_time: DwellTime,
+    /// Range of the width of the ships in meters, [`None`] if none of their sizes are known
+    pub width: Option<RangeInclusive<f64>>,
+    /// Range of the length of the ships in meters, [`None`] if none of their sizes are known
+    pub length: Option<RangeInclusive<f64>>,
+}
+
+impl<K> StopArea<K> {
+    /// The name of the area, or its number if it has none
+    pub fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("area {}", self.id))
+    }
+
+    /// Distance in meters from the area to `polygon` (e.g. of a stop), 0 if they overlap
+    pub fn distance(&self, polygon: &Polygon) -> f64 {
+        distance(&self.polygon, polygon)
+    }
+}
+
+/// Distance in meters between two polygons, on a plane tangent to their vertices
+fn distance(a: &Polygon, b: &Polygon) -> f64 {
+    let coords = a.exterior().coords().chain(b.exterior().coords()).copied();
+    let plane = Plane::new::<4326>(coords.collect::<Vec<_>>().into_iter());
+    let (a, b) = (
+        a.map_coords(|c| plane.project(c)),
+        b.map_coords(|c| plane.project(c)),
+    );
+    Euclidean.distance(&a, &b)
+}
+
+/// A stop of one ship
+struct Visit<'a, K> {
+    vessel: K,
+    polygon: &'a Polygon,
+    duration: TimeDelta,
+    rect: Rect,
+}
+
+fn range(values: impl Iterator<Item = f64>) -> Option<RangeInclusive<f64>> {
+    values.fold(None, |acc, v| match acc {
+        None => Some(v..=v),
+        Some(r) => Some(r.start().min(v)..=r.end().max(v)),
+    })
+}
+
+impl StopAreaConf {
+    /// Merges the stops of many ships into areas, linestrings and empty polygons are ignored.
+    ///
+    /// `stops` are the stops along with the ship (e.g. the MMSI) that made them, and `size` gives the width and length of a ship in meters, if known.
+    /// Returns the areas that are visited often enough, ordered (and numbered) by descending number of visits.
+    pub fn aggregate<'a, K>(
+        &self,
+        stops: impl IntoIterator<Item = (K, &'a StopOrLs<4326>)>,
+        size: impl Fn(&K) -> Option<(f64, f64)>,
+    ) -> Vec<StopArea<K>>
+    where
+        K: Clone + PartialEq,
+    {
+        let visits = stops
+            .into_iter()
+            .filter_map(|(vessel, stop)| match stop {
+                StopOrLs::Stop {
+                    polygon, tz_tange, ..
+                } => Some(Visit {
+                    vessel,
+                    polygon,
+                    duration: tz_tange.1 - tz_tange.0,
+                    rect: polygon.bounding_rect()?,
+                }),
+                StopOrLs::LS(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut sets = UnionFind::new(visits.len());
+        let mut order = (0..visits.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| visits[*a].rect.min().y.total_cmp(&visits[*b].rect.min().y));
+
+        let dlat = self.dist_thres / METERS_PER_DEGREE;
+        for (n, &i) in order.iter().enumerate() {
+            let a = &visits[i];
+            let lat = a.rect.max().y.abs().max(a.rect.min().y.abs()).min(89.);
+            let dlon = dlat / lat.to_radians().cos();
+            for &j in order[n + 1..]
+                .iter()
+                .take_while(|j| visits[**j].rect.min().y <= a.rect.max().y + dlat)
+            {
+                let b = &visits[j];
+                if b.rect.min().x > a.rect.max().x + dlon || b.rect.max().x < a.rect.min().x - dlon
+                {
+                    continue;
+                }
+                if distance(a.polygon, b.polygon) <= self.dist_thres {
+                    sets.union(i, j);
+                }
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for i in 0..visits.len() {
+            groups.entry(sets.find(i)).or_default().push(i);
+        }
+        // members are in input order, so the first member orders areas with the same number of visits
+        let mut groups = groups.into_values().collect::<Vec<_>>();
+        groups.sort_by_key(|g| (std::cmp::Reverse(g.len()), g[0]));
+
+        groups
+            .into_iter()
+            .filter(|g| g.len() >= self.min_visits.get())
+            .filter_map(|g| {
+                let mut vessels: Vec<K> = vec![];
+                for i in &g {
+                    if !vessels.contains(&visits[*i].vessel) {
+                        vessels.push(visits[*i].vessel.clone());
+                    }
+                }
+                if vessels.len() < self.min_vessels.get() {
+                    return None;
+                }
+
+                let vertices = g
+                    .iter()
+                    .flat_map(|i| visits[*i].polygon.exterior().coords())
+                    .map(|c| PointM::from((c.x, c.y, 0.)))
+                    .collect::<Vec<_>>();
+                let sizes = vessels.iter().filter_map(&size).collect::<Vec<_>>();
+                Some(StopArea {
+                    id: 0,
+                    name: None,
+                    polygon: self.footprint.polygon::<4326>(&vertices),
+                    visits: g.len(),
+                    dwell_time: DwellTime::new(g.iter().map(|i| visits[*i].duration).collect())?,
+                    width: range(sizes.iter().map(|s| s.0)),
+                    length: range(sizes.iter().map(|s| s.1)),
+                    vessels,
+                })
+            })
+            .enumerate()
+            .map(|(id, area)| StopArea { id, ..area })
+            .collect()
+    }
+}
+
+impl<K> ToWkt for StopArea<K> {
+    fn to_wkt(&self) -> String {
+        polygon_to_wkt(&self.polygon)
+    }
+}
+
+/// The polygon of the area, with its label and statistics (durations in seconds) as properties
+impl<K> ToGeoJson for StopArea<K> {
+    fn to_geojson(&self) -> String {
+        let range = |r: &Option<RangeInclusive<f64>>| match r {
+            Some(r) => format!("[{},{}]", r.start(), r.end()),
+            None => String::from("null"),
+        };
+        let d = &self.dwell_time;
+        feature(
+            &polygon_geometry::<4326>(&self.polygon),
+            &format!(
+                r#"{{"id":{},"name":{},"visits":{},"vessels":{},"dwell_time":{{"min":{},"q1":{},"median":{},"q3":{},"max":{},"mean":{}}},"width":{},"length":{}}}"#,
+                self.id,
+                string(&self.label()),
+                self.visits,
+                self.vessels.len(),
+                d.min.num_seconds(),
+                d.q1.num_seconds(),
+                d.median.num_seconds(),
+                d.q3.num_seconds(),
+                d.max.num_seconds(),
+                d.mean.num_seconds(),
+                range(&self.width),
+                range(&self.length),
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::geojson::feature_collection;
+    use chrono::{DateTime, Utc};
+    use geo::{Contains, Point};
+    use pretty_assertions::assert_eq;
+
+    /// A square stop of about 50 m at (`x`, `y`), lasting `hours`
+    fn stop(x: f64, y: f64, hours: i64) -> StopOrLs<4326> {
+        let d = 50. / METERS_PER_DEGREE;
+        let start = DateTime::<Utc>::UNIX_EPOCH;
+        StopOrLs::Stop {
+            polygon: Polygon::new(
+                vec![
+                    (x, y),
+                    (x + d * 2., y),
+                    (x + d * 2., y + d),
+                    (x, y + d),
+                    (x, y),
+                ]
+                .into(),
+                vec![],
+            ),
+            tz_tange: (start, start + TimeDelta::hours(hours)),
+            kind: None,
+        }
+    }
+
+    #[test]
+    fn anchorage_and_berth() {
+        let stops = [
+            // three ships at an anchorage, about 60 m apart
+            (1, stop(10., 56., 2)),
+            (2, stop(10.001, 56., 4)),
+            (3, stop(10.002, 56., 6)),
+            (1, stop(10.001, 56.0005, 8)),
+            // a berth about 2 km to the north, visited twice by the same ferry
+            (4, stop(10., 56.02, 1)),
+            (4, stop(10., 56.0201, 1)),
+            // far away
+            (5, stop(11., 57., 1)),
+        ];
+        let size = |mmsi: &i32| (*mmsi != 3).then(|| (f64::from(*mmsi), f64::from(*mmsi) * 10.));
+
+        let conf = StopAreaConf::builder().dist_thres(100.).build();
+        let areas = conf.aggregate(stops.iter().map(|(mmsi, s)| (*mmsi, s)), size);
+        assert_eq!(areas.len(), 3);
+
+        let anchorage = &areas[0];
+        assert_eq!(anchorage.id, 0);
+        assert_eq!(anchorage.label(), "area 0");
+        assert_eq!(anchorage.visits, 4);
+        assert_eq!(anchorage.vessels, vec![1, 2, 3]);
+        assert_eq!(anchorage.dwell_time.min, TimeDelta::hours(2));
+        assert_eq!(anchorage.dwell_time.median, TimeDelta::hours(6));
+        assert_eq!(anchorage.dwell_time.max, TimeDelta::hours(8));
+        assert_eq!(anchorage.dwell_time.mean, TimeDelta::hours(5));
+        assert_eq!(anchorage.dwell_time.total, TimeDelta::hours(20));
+        assert_eq!(anchorage.width, Some(1.0..=2.0));
+        assert_eq!(anchorage.length, Some(10.0..=20.0));
+        assert!(anchorage.polygon.contains(&Point::new(10.0015, 56.0002)));
+
+        let berth = &areas[1];
+        assert_eq!((berth.visits, berth.vessels.clone()), (2, vec![4]));
+        assert_eq!((areas[2].visits, areas[2].vessels.clone()), (1, vec![5]));
+
+        let conf = StopAreaConf::builder()
+            .dist_thres(100.)
+            .min_visits(2.try_into().unwrap())
+            .min_vessels(2.try_into().unwrap())
+            .build();
+        let areas = conf.aggregate(stops.iter().map(|(mmsi, s)| (*mmsi, s)), |_| None);
+        assert_eq!(areas.len(), 1);
+        assert_eq!(areas[0].visits, 4);
+        assert_eq!(areas[0].width, None);
+
+        // closer than the spacing of the anchorage, only the overlapping stops at the berth are merged
+        let conf = StopAreaConf::builder().dist_thres(1.).build();
+        assert_eq!(
+            conf.aggregate(stops.iter().map(|(mmsi, s)| (*mmsi, s)), size)
+                .len(),
+            6
+        );
+    }
+
+    #[test]
+    fn named_geojson() {
+        let stops = [(1, stop(10., 56., 1)), (2, stop(10., 56., 3))];
+        let conf = StopAreaConf::builder().dist_thres(10.).build();
+        let mut areas = conf.aggregate(stops.iter().map(|(mmsi, s)| (*mmsi, s)), |_| None);
+        areas[0].name = Some(String::from("Aarhus \"Ø\""));
+
+        let json = feature_collection(areas.iter().map(ToGeoJson::to_geojson));
+        assert!(json.contains(r#""name":"Aarhus \"Ø\"""#), "{json}");
+        assert!(json.contains(r#""visits":2,"vessels":2"#), "{json}");
+        assert!(json.contains(r#""median":10800"#), "{json}");
+        assert!(json.contains(r#""width":null"#), "{json}");
+        assert!(areas[0].to_wkt().starts_with("POLYGON (("));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let stops = [(1, stop(10., 56., 1)), (2, stop(10., 56., 3))];
+        let conf = StopAreaConf::builder().dist_thres(10.).build();
+        let areas = conf.aggregate(stops.iter().map(|(mmsi, s)| (*mmsi, s)), |mmsi: &i32| {
+            Some((f64::from(*mmsi), 10.))
+        });
+
+        let json = serde_json::to_value(&areas).unwrap();
+        assert_eq!(json[0]["vessels"], serde_json::json!([1, 2]));
+        assert_eq!(
+            json[0]["width"],
+            serde_json::json!({"start": 1.0, "end": 2.0})
+        );
+        // chrono writes durations as [seconds, nanoseconds]
+        assert_eq!(json[0]["dwell_time"]["max"], serde_json::json!([10800, 0]));
+        assert_eq!(
+            serde_json::from_value::<Vec<StopArea<i32>>>(json).unwrap(),
+            areas
+        );
+    }
+}
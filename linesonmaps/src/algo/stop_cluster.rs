@@ -1,20 +1,30 @@
 use chrono::{DateTime, TimeDelta, Utc};
-use geo::ConvexHull;
-use geo::Distance;
+use geo::{Coord, Distance};
 // use itertools::*;
 use itertools::Itertools;
 use rayon::prelude::*;
+use rstar::primitives::GeomWithData;
+use rstar::{AABB, RTree};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::num::NonZero;
 use typed_builder::TypedBuilder;
 
+use crate::algo::footprint::Footprint;
+use crate::algo::stop_kind::StopKind;
+use crate::types::crs::{Crs, Epsg, Unit};
+use crate::types::geojson::{
+    ToGeoJson, ToMfJson, datetime, feature, feature_collection, mf_feature, polygon_geometry,
+};
 use crate::types::linestringm::LineStringM;
+use crate::types::measure;
 use crate::types::pointm::PointM;
+use crate::types::wkt::{ToWkt, polygon_to_wkt};
 
 pub const MS_TO_KNOT: f64 = 1.9438400;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Classification {
     Core(usize),
     Edge(usize),
@@ -23,7 +33,7 @@ pub enum Classification {
 }
 
 impl Classification {
-    fn cluster(&self) -> Option<usize> {
+    pub(crate) fn cluster(&self) -> Option<usize> {
         match self {
             &Classification::Core(c) | &Classification::Edge(c) => Some(c),
             _ => None,
@@ -31,6 +41,23 @@ impl Classification {
     }
 }
 
+/// How [`DbScanConf`] finds the neighbors of a point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Neighborhood {
+    /// Walks outward from the point in the order of the input, until a point is too far away.
+    ///
+    /// Cheap for trajectories ordered by time, but misses neighbors that are not contiguous with the point, e.g. when a ship returns to an anchorage.
+    #[default]
+    Scan,
+    /// Queries an R-tree over position and time, independent of the order of the input.
+    ///
+    /// Candidates are pre-filtered by their distance on a local projection, and confirmed with the distance function,
+    /// so it has to measure in meters (e.g. [`Geodesic`](geo::Geodesic) for degrees).
+    RTree,
+}
+
+type Indexed = GeomWithData<[f64; 3], usize>;
+
 #[derive(TypedBuilder, Debug)]
 pub struct DbScanConf<Dist, const CRS: u64>
 where
@@ -41,17 +68,23 @@ where
     /// Distance from a [Classification::Edge] Point to a [Classification::Core] Point
     pub(crate) dist: Dist,
     /// Maximum distance to a [Classification::Core] point
-    dist_thres: f64,
+    pub(crate) dist_thres: f64,
     /// Maximum Speed Over Ground (SOG) for a point to be clustered
     pub(crate) speed_thres: f32,
     /// Maximum time interval before any succeeding points are left out of cluster
     pub(crate) max_time_thres: TimeDelta,
+    /// How neighbors are found
+    #[builder(default)]
+    pub(crate) neighborhood: Neighborhood,
     #[builder(setter(skip),default=Vec::new())]
     classes: Vec<Classification>,
+    #[builder(setter(skip), default)]
+    tree: Option<RTree<Indexed>>,
 }
 
 impl<Dist, const CRS: u64> DbScanConf<Dist, CRS>
 where
+    Epsg<CRS>: Crs,
     Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
 {
     // inpsired by existing DBSCAN implementation https://docs.rs/dbscan/latest/src/dbscan/lib.rs.html#184-205
@@ -103,6 +136,16 @@ where
         use Classification::{Noise, Unclassified};
 
         self.classes = vec![Unclassified; points.len()];
+        self.tree = match self.neighborhood {
+            Neighborhood::Scan => None,
+            Neighborhood::RTree => Some(RTree::bulk_load(
+                points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (p, _))| Indexed::new([p.coord.x, p.coord.y, p.coord.m], i))
+                    .collect(),
+            )),
+        };
 
         let mut cluster = 0_usize;
         let mut queue = Vec::<usize>::new();
@@ -123,6 +166,7 @@ where
             .map(|(p, _)| p)
             .zip(std::mem::take(&mut self.classes))
             .collect();
+        self.tree = None;
 
         res
     }
@@ -132,12 +176,24 @@ where
         (qp, idx): (&'p PointM<CRS>, usize),
         points: &'p [(PointM<CRS>, f32)],
         dist_thres: f64,
+    ) -> Vec<usize> {
+        match &self.tree {
+            Some(tree) => self.range_query_tree(tree, qp, points, dist_thres),
+            None => self.range_query_scan((qp, idx), points, dist_thres),
+        }
+    }
+
+    fn range_query_scan<'p>(
+        &self,
+        (qp, idx): (&'p PointM<CRS>, usize),
+        points: &'p [(PointM<CRS>, f32)],
+        dist_thres: f64,
     ) -> Vec<usize> {
         // if qp is points[i], and points[n] is not a neighbor, then points[n-1] cannot be as well, same for points[m] and points[m+1] with n<i<m
-        let mut neighbors = points // linestrings are ordered, so 'neighbors' will only be subslice of points
+        let neighbors = points // linestrings are ordered, so 'neighbors' will only be subslice of points
             .iter()
             .enumerate()
-            .skip(idx - 1)
+            .skip(idx)
             .take_while(|(_, (fp, f_sog))| {
                 (self.dist)(qp, fp) < dist_thres && self.temporal_sog_close(qp, fp, *f_sog)
             })
@@ -147,7 +203,7 @@ where
             .iter()
             .enumerate()
             .rev()
-            .skip(points.len() - idx) //TODO +- 1?
+            .skip(points.len() - idx)
             .take_while(|(_, (fp, f_sog))| {
                 (self.dist)(qp, fp) < dist_thres && self.temporal_sog_close(qp, fp, *f_sog)
             })
@@ -156,28 +212,170 @@ where
         rev_neighbors.extend(neighbors);
         rev_neighbors
     }
+    fn range_query_tree(
+        &self,
+        tree: &RTree<Indexed>,
+        qp: &PointM<CRS>,
+        points: &[(PointM<CRS>, f32)],
+        dist_thres: f64,
+    ) -> Vec<usize> {
+        // the local projection is off by less than 1% from the geodesic distance, mostly due to the flattening of the earth
+        let approx_thres = dist_thres * 1.01;
+        let dy = approx_thres / <Epsg<CRS> as Crs>::Unit::scale(Coord::from((qp.coord.x, qp.coord.y))).y;
+        // for degrees, a meter spans the most longitude at the edge of the envelope closest to a pole
+        let edge = Coord::from((qp.coord.x, (qp.coord.y.abs() + dy).min(89.)));
+        let dx = approx_thres / <Epsg<CRS> as Crs>::Unit::scale(edge).x;
+        let dt = self.max_time_thres.as_seconds_f64();
+        let envelope = AABB::from_corners(
+            [qp.coord.x - dx, qp.coord.y - dy, qp.coord.m - dt],
+            [qp.coord.x + dx, qp.coord.y + dy, qp.coord.m + dt],
+        );
+
+        let mut neighbors = tree
+            .locate_in_envelope(&envelope)
+            .map(|candidate| candidate.data)
+            .filter(|&i| {
+                let (fp, f_sog) = &points[i];
+                // the cheap distance rules out most candidates, the remaining ones are confirmed with `dist`
+                local_distance(qp, fp) < approx_thres
+                    && self.temporal_sog_close(qp, fp, *f_sog)
+                    && (self.dist)(qp, fp) < dist_thres
+            })
+            .collect::<Vec<_>>();
+        // the tree yields candidates in no particular order
+        neighbors.sort_unstable();
+        neighbors
+    }
+
     #[inline(always)]
-    fn temporal_sog_close(&self, qp: &PointM<CRS>, f: &PointM<CRS>, sog: f32) -> bool {
-        let f_dt = DateTime::<Utc>::from_timestamp_secs(f.coord.m as i64).unwrap();
-        let qp_dt = DateTime::<Utc>::from_timestamp_secs(qp.coord.m as i64).unwrap();
-        let temporally_close = (f_dt - qp_dt).abs() < self.max_time_thres;
+    pub(crate) fn temporal_sog_close(&self, qp: &PointM<CRS>, f: &PointM<CRS>, sog: f32) -> bool {
+        let temporally_close =
+            measure::to_timedelta(f.coord.m - qp.coord.m).abs() < self.max_time_thres;
 
         sog < self.speed_thres && temporally_close
     }
 }
 
+/// Distance in meters on the plane tangent to `a`, a cheap approximation of the geodesic distance for points close to each other
+fn local_distance<const CRS: u64>(a: &PointM<CRS>, b: &PointM<CRS>) -> f64
+where
+    Epsg<CRS>: Crs,
+{
+    let scale = <Epsg<CRS> as Crs>::Unit::scale(Coord::from((a.coord.x, a.coord.y)));
+    ((b.coord.x - a.coord.x) * scale.x).hypot((b.coord.y - a.coord.y) * scale.y)
+}
+
+/// The time range of a stop is serialized as RFC 3339 timestamps
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StopOrLs<const CRS: u64> {
     Stop {
         polygon: geo::Polygon,
         tz_tange: (DateTime<Utc>, DateTime<Utc>),
+        /// What the ship was doing, [`None`] until classified, see [`StopKindConf`](crate::algo::stop_kind::StopKindConf)
+        #[cfg_attr(feature = "serde", serde(default))]
+        kind: Option<StopKind>,
     },
     LS(LineStringM<CRS>),
 }
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct Trajectory<const CRS: u64>(pub Vec<StopOrLs<CRS>>);
 
+/// Stops are written as their (measureless) polygon
+impl<const CRS: u64> ToWkt for StopOrLs<CRS> {
+    fn to_wkt(&self) -> String {
+        match self {
+            StopOrLs::Stop { polygon, .. } => polygon_to_wkt(polygon),
+            StopOrLs::LS(ls) => ls.to_wkt(),
+        }
+    }
+}
+
+/// Stops are written as polygons with `start` and `end` properties, and their `kind` if classified
+impl<const CRS: u64> ToGeoJson for StopOrLs<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_geojson(&self) -> String {
+        match self {
+            StopOrLs::Stop {
+                polygon,
+                tz_tange,
+                kind,
+            } => feature(
+                &polygon_geometry::<CRS>(polygon),
+                &format!(
+                    r#"{{"start":{},"end":{}{}}}"#,
+                    datetime(tz_tange.0),
+                    datetime(tz_tange.1),
+                    kind.map(|k| format!(r#","kind":"{}""#, k.name()))
+                        .unwrap_or_default()
+                ),
+            ),
+            StopOrLs::LS(ls) => ls.to_geojson(),
+        }
+    }
+}
+
+/// Stops are written as static polygons that exist during the `time` of the stop
+impl<const CRS: u64> ToMfJson for StopOrLs<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_mf_json(&self) -> String {
+        match self {
+            StopOrLs::Stop {
+                polygon, tz_tange, ..
+            } => mf_feature(
+                &polygon_geometry::<CRS>(polygon),
+                None,
+                Some((datetime(tz_tange.0), datetime(tz_tange.1))),
+            ),
+            StopOrLs::LS(ls) => ls.to_mf_json(),
+        }
+    }
+}
+
+/// A `FeatureCollection` of the stops and linestrings in order
+impl<const CRS: u64> ToGeoJson for Trajectory<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_geojson(&self) -> String {
+        feature_collection(self.0.iter().map(ToGeoJson::to_geojson))
+    }
+}
+
+/// A `FeatureCollection` of the stops and linestrings in order
+impl<const CRS: u64> ToMfJson for Trajectory<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_mf_json(&self) -> String {
+        feature_collection(self.0.iter().map(ToMfJson::to_mf_json))
+    }
+}
+
 pub fn cluster_to_traj_with_stop_object<const CRS: u64>(
     classes: Vec<(&PointM<CRS>, Classification)>,
-) -> Trajectory<CRS> {
+) -> Trajectory<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    cluster_to_traj_with_footprint(classes, &Footprint::ConvexHull)
+}
+
+/// Like [`cluster_to_traj_with_stop_object`], with the polygons of the stops given by `footprint`
+pub fn cluster_to_traj_with_footprint<const CRS: u64>(
+    classes: Vec<(&PointM<CRS>, Classification)>,
+    footprint: &Footprint,
+) -> Trajectory<CRS>
+where
+    Epsg<CRS>: Crs,
+{
     // use Classification::{Core, Edge, Noise, Unclassified};
     use Classification as C;
 
@@ -198,33 +396,27 @@ pub fn cluster_to_traj_with_stop_object<const CRS: u64>(
             })
             .map(|c| {
                 if matches!(c.first(), Some((_, C::Core(_))) | Some((_, C::Edge(_)))) {
-                    let time_start = DateTime::from_timestamp_secs(
-                        c.iter()
-                            .map(|(p, c)| p)
-                            .min_by(|a, b| a.coord.m.total_cmp(&b.coord.m))
-                            .expect("classes should be nonempty")
-                            .coord
-                            .m as i64,
-                    )
-                    .expect("timestamp should be well within bounds");
-                    let time_end = DateTime::from_timestamp_secs(
-                        c.iter()
-                            .map(|(p, c)| p)
-                            .max_by(|a, b| a.coord.m.total_cmp(&b.coord.m))
-                            .expect("classes should be nonempty")
-                            .coord
-                            .m as i64,
-                    )
-                    .expect("timestamp should be well within bounds");
-
-                    let a = geo::LineString::from_iter(
-                        c.iter().map(|(p, c)| geo::Point::new(p.coord.x, p.coord.y)),
-                    )
-                    .convex_hull();
+                    let time_start = c
+                        .iter()
+                        .map(|(p, c)| p)
+                        .min_by(|a, b| a.coord.m.total_cmp(&b.coord.m))
+                        .expect("classes should be nonempty")
+                        .time()
+                        .expect("timestamp should be well within bounds");
+                    let time_end = c
+                        .iter()
+                        .map(|(p, c)| p)
+                        .max_by(|a, b| a.coord.m.total_cmp(&b.coord.m))
+                        .expect("classes should be nonempty")
+                        .time()
+                        .expect("timestamp should be well within bounds");
+
+                    let a = footprint.polygon(&c.iter().map(|(p, _)| **p).collect_vec());
 
                     StopOrLs::Stop {
                         polygon: a,
                         tz_tange: (time_start, time_end),
+                        kind: None,
                     }
                 } else {
                     StopOrLs::LS(
@@ -249,16 +441,21 @@ pub fn triangulate_stop_object(
 pub mod test {
     use std::fs::File;
 
-    use chrono::TimeDelta;
-    use geo::{Distance, Euclidean, Geodesic};
+    use chrono::{DateTime, TimeDelta};
+    use geo::{Distance, Euclidean, Geodesic, polygon};
     use geo_traits::LineStringTrait;
     use itertools::Itertools;
     use wkb::reader::read_wkb;
 
     use super::Classification::*;
-    use crate::algo::stop_cluster::{DbScanConf, StopOrLs, cluster_to_traj_with_stop_object};
+    use crate::algo::stop_cluster::{
+        Classification, DbScanConf, Neighborhood, StopOrLs, Trajectory,
+        cluster_to_traj_with_stop_object,
+    };
+    use crate::types::geojson::ToGeoJson;
     use crate::types::linestringm::LineStringM;
     use crate::types::pointm::PointM;
+    use crate::types::wkt::ToWkt;
 
     #[test]
     fn build_conf() {
@@ -275,6 +472,34 @@ pub mod test {
         assert_eq!(conf.speed_thres, 1.5);
     }
 
+    #[test]
+    fn stop_geojson() {
+        let stop = StopOrLs::<4326>::Stop {
+            polygon: polygon![(x: 10., y: 56.), (x: 10.1, y: 56.), (x: 10., y: 56.1)],
+            tz_tange: (
+                DateTime::from_timestamp_secs(1700000000).unwrap(),
+                DateTime::from_timestamp_secs(1700003600).unwrap(),
+            ),
+            kind: None,
+        };
+        assert_eq!(stop.to_wkt(), "POLYGON ((10 56, 10.1 56, 10 56.1, 10 56))");
+
+        let ls = LineStringM::new(vec![
+            (10., 56.1, 1700003600.).into(),
+            (10., 56.2, 1700003660.).into(),
+        ])
+        .unwrap();
+        assert_eq!(
+            Trajectory(vec![stop, StopOrLs::LS(ls)]).to_geojson(),
+            concat!(
+                r#"{"type":"FeatureCollection","features":["#,
+                r#"{"type":"Feature","geometry":{"type":"Polygon","coordinates":[[[10,56],[10.1,56],[10,56.1],[10,56]]]},"properties":{"start":"2023-11-14T22:13:20Z","end":"2023-11-14T23:13:20Z"}},"#,
+                r#"{"type":"Feature","geometry":{"type":"LineString","coordinates":[[10,56.1],[10,56.2]]},"properties":{"datetimes":["2023-11-14T23:13:20Z","2023-11-14T23:14:20Z"]}}"#,
+                r#"]}"#
+            )
+        );
+    }
+
     #[test]
     fn simple_cluster_fr_fr() {
         let mut conf = DbScanConf::builder()
@@ -358,14 +583,16 @@ pub mod test {
             traj.next(),
             Some(StopOrLs::Stop {
                 polygon: _,
-                tz_tange: _
+                tz_tange: _,
+                ..
             })
         ));
         assert!(matches!(
             traj.next(),
             Some(StopOrLs::Stop {
                 polygon: _,
-                tz_tange: _
+                tz_tange: _,
+                ..
             })
         ));
         assert!(matches!(traj.next(), Some(StopOrLs::LS(_))));
@@ -399,14 +626,16 @@ pub mod test {
     }
     #[test]
     fn cluster_big_traj_aarhus_odden() {
-        let mut conf = DbScanConf::builder()
-            // .dist(|a, b| Geodesic.distance(*a, *b))
-            .dist(|a: &PointM<4326>, b| Geodesic.distance(*a, *b))
-            .max_time_thres(TimeDelta::new(30 * 60, 0).unwrap())
-            .min_cluster_size(100.try_into().unwrap())
-            .speed_thres(1.5)
-            .dist_thres(50.0)
-            .build();
+        let conf = |neighborhood| {
+            DbScanConf::builder()
+                .dist(|a: &PointM<4326>, b| Geodesic.distance(*a, *b))
+                .max_time_thres(TimeDelta::new(30 * 60, 0).unwrap())
+                .min_cluster_size(100.try_into().unwrap())
+                .speed_thres(1.5)
+                .dist_thres(50.0)
+                .neighborhood(neighborhood)
+                .build()
+        };
 
         let a = include_str!("./resources/219705000_aarhus_odden.txt");
         let a = a.replace("\"", "");
@@ -420,32 +649,147 @@ pub mod test {
             .points()
             .zip(std::iter::repeat(1.0_f32))
             .collect::<Vec<_>>(); // all SOGS are below speed threshold.
-        let clusters = conf.run(&point_with_synthetic_sog);
 
-        let stops = cluster_to_traj_with_stop_object(clusters);
+        // whether every point is part of a cluster, ordered by time, since cluster ids depend on the order the points are visited in
+        let clustered = |classes: &[(&PointM<4326>, Classification)]| {
+            classes
+                .iter()
+                .map(|(p, c)| (p.coord.m, c.cluster().is_some()))
+                .sorted_by(|a, b| a.0.total_cmp(&b.0))
+                .collect_vec()
+        };
 
-        let stops = stops
+        let scan = conf(Neighborhood::Scan).run(&point_with_synthetic_sog);
+        let tree = conf(Neighborhood::RTree).run(&point_with_synthetic_sog);
+        assert_eq!(clustered(&scan), clustered(&tree));
+
+        // the scan relies on the points being ordered, the tree does not
+        let mut shuffled = point_with_synthetic_sog.clone();
+        shuffled.reverse();
+        let third = shuffled.len() / 3;
+        shuffled.rotate_left(third);
+        let shuffled_tree = conf(Neighborhood::RTree).run(&shuffled);
+        assert_eq!(clustered(&tree), clustered(&shuffled_tree));
+
+        let stops = cluster_to_traj_with_stop_object(tree)
             .0
             .into_iter()
-            .filter_map(|p| match p {
-                StopOrLs::Stop {
-                    polygon,
-                    tz_tange: _,
-                } => Some(polygon),
-                _ => None,
+            .filter(|p| matches!(p, StopOrLs::Stop { .. }))
+            .count();
+        assert_eq!(stops, 0)
+    }
+
+    #[test]
+    fn return_to_anchorage() {
+        // anchored for 10 points, a short excursion, and anchored at the same spot again
+        let inputs = (0..25)
+            .map(|i| {
+                let (x, sog) = match i {
+                    10..15 => (1000., 8.),
+                    _ => (f64::from(i % 4) * 5., 0.5),
+                };
+                (PointM::<3857>::from((x, 0., f64::from(i) * 10.)), sog)
+            })
+            .collect::<Vec<_>>();
+        let conf = |neighborhood| {
+            DbScanConf::builder()
+                .dist(|a: &PointM<3857>, b| Euclidean.distance(*a, *b))
+                .max_time_thres(TimeDelta::minutes(10))
+                .min_cluster_size(12.try_into().unwrap())
+                .speed_thres(1.5)
+                .dist_thres(50.0)
+                .neighborhood(neighborhood)
+                .build()
+        };
+
+        // neither visit is large enough on its own
+        let scan = conf(Neighborhood::Scan).run(&inputs);
+        assert!(scan.iter().all(|(_, c)| matches!(c, Noise)));
+
+        let expected = (0..25)
+            .map(|i| match i {
+                10..15 => Noise,
+                _ => Core(0),
             })
             .collect_vec();
+        let tree = conf(Neighborhood::RTree).run(&inputs);
+        assert_eq!(tree.into_iter().map(|(_, c)| c).collect_vec(), expected);
+
+        // the order of the input does not matter
+        let mut shuffled = inputs.clone();
+        shuffled.reverse();
+        shuffled.swap(3, 17);
+        let tree = conf(Neighborhood::RTree).run(&shuffled);
+        for (p, c) in tree {
+            let i = (p.coord.m / 10.) as usize;
+            assert_eq!(c, expected[i], "{i}");
+        }
+    }
 
-        let mp = geo::MultiPolygon::new(stops.clone());
-        let opt = wkb::writer::WriteOptions {
-            endianness: wkb::Endianness::LittleEndian,
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use crate::algo::stop_kind::StopKind;
+
+        let stop = StopOrLs::<4326>::Stop {
+            polygon: polygon![(x: 10., y: 56.), (x: 10.1, y: 56.), (x: 10., y: 56.1)],
+            tz_tange: (
+                DateTime::from_timestamp_secs(1700000000).unwrap(),
+                DateTime::from_timestamp_secs(1700003600).unwrap(),
+            ),
+            kind: Some(StopKind::Anchored),
         };
+        let ls = LineStringM::new(vec![
+            (10., 56.1, 1700003600.).into(),
+            (10., 56.2, 1700003660.).into(),
+        ])
+        .unwrap();
 
-        let mut w = Vec::<u8>::new();
-        let _ = wkb::writer::write_multi_polygon(&mut w, &mp, &opt).unwrap();
-        let hex = hex::encode(w);
-        std::fs::write("aarhus_odden_stops.txt", hex).unwrap();
-        dbg!(mp.0.iter().min_by_key(|x| x.exterior().num_coords()).unwrap().exterior().num_coords());
-        assert_eq!(stops.len(), 0)
+        let json = serde_json::to_value(Trajectory(vec![stop, StopOrLs::LS(ls.clone())])).unwrap();
+        assert_eq!(
+            json[0]["Stop"]["tz_tange"],
+            serde_json::json!(["2023-11-14T22:13:20Z", "2023-11-14T23:13:20Z"])
+        );
+        assert_eq!(json[0]["Stop"]["kind"], "Anchored");
+        assert_eq!(
+            json[1]["LS"],
+            serde_json::json!([[10., 56.1, 1700003600.], [10., 56.2, 1700003660.]])
+        );
+
+        let traj: Trajectory<4326> = serde_json::from_value(json).unwrap();
+        match traj.0.as_slice() {
+            [
+                StopOrLs::Stop {
+                    polygon,
+                    tz_tange,
+                    kind,
+                },
+                StopOrLs::LS(round_trip),
+            ] => {
+                assert_eq!(polygon.exterior().0.len(), 4);
+                assert_eq!(tz_tange.0.timestamp(), 1700000000);
+                assert_eq!(tz_tange.1.timestamp(), 1700003600);
+                assert_eq!(*kind, Some(StopKind::Anchored));
+                assert_eq!(*round_trip, ls);
+            }
+            _ => panic!("unexpected trajectory"),
+        }
+
+        // stops written before they could be classified have no kind
+        let json = r#"{"Stop":{"polygon":{"exterior":[{"x":10.0,"y":56.0},{"x":10.1,"y":56.0},{"x":10.0,"y":56.1},{"x":10.0,"y":56.0}],"interiors":[]},"tz_tange":["2023-11-14T22:13:20Z","2023-11-14T23:13:20+01:00"]}}"#;
+        match serde_json::from_str::<StopOrLs<4326>>(json).unwrap() {
+            StopOrLs::Stop { tz_tange, kind, .. } => {
+                assert_eq!(tz_tange.1.timestamp(), 1700000000);
+                assert_eq!(kind, None);
+            }
+            StopOrLs::LS(_) => panic!("expected a stop"),
+        }
+
+        for c in [Core(3), Edge(1), Noise, Unclassified] {
+            let json = serde_json::to_string(&c).unwrap();
+            assert_eq!(serde_json::from_str::<Classification>(&json).unwrap(), c);
+        }
+        assert_eq!(serde_json::to_string(&Core(3)).unwrap(), r#"{"Core":3}"#);
+        assert_eq!(serde_json::to_string(&Noise).unwrap(), r#""Noise""#);
     }
 }
@@ -0,0 +1,289 @@
+//! Classification of stops, such that port calls can be told apart from waiting at an anchorage and drifting.
+//!
+//! The shape of the polygon of a stop is combined with evidence from other sources, see [`StopEvidence`].
+//! The result is stored in the `kind` of [`StopOrLs::Stop`], see [`StopKindConf::classify_trajectory`].
+
+use chrono::{DateTime, Utc};
+use geo::{Area, Coord, Polygon};
+use typed_builder::TypedBuilder;
+
+use crate::algo::footprint::{Plane, minimum_enclosing_circle};
+use crate::algo::stop_cluster::{StopOrLs, Trajectory};
+
+/// What a ship was doing during a stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StopKind {
+    /// Alongside a berth, e.g. a port call
+    Moored,
+    /// Swinging around an anchor
+    Anchored,
+    /// Moving with the wind and current, without keeping to a place
+    Drifting,
+    /// Slow and erratic movement while fishing.
+    ///
+    /// Only ever taken from [`StopEvidence::reported`], the polygon of a stop does not tell erratic movement apart from drifting.
+    Fishing,
+    /// Keeping position away from any known stop area, e.g. waiting for a pilot or a free berth
+    Waiting,
+}
+
+impl StopKind {
+    /// Lowercase name, as written to GeoJSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            StopKind::Moored => "moored",
+            StopKind::Anchored => "anchored",
+            StopKind::Drifting => "drifting",
+            StopKind::Fishing => "fishing",
+            StopKind::Waiting => "waiting",
+        }
+    }
+}
+
+/// What is known about a stop besides its polygon, every field is optional
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StopEvidence {
+    /// The kind implied by the navigational status the ship reported for most of the stop
+    pub reported: Option<StopKind>,
+    /// Absolute change of the draught in meters from the start to the end of the stop, i.e. cargo was loaded or unloaded
+    pub draught_change: Option<f64>,
+    /// Distance in meters to the closest known stop area, see [`StopArea::distance`](crate::algo::stop_area::StopArea::distance)
+    pub area_distance: Option<f64>,
+}
+
+/// Shape of the polygon of a stop, in meters on a plane tangent to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spread {
+    /// Radius of the smallest circle containing the polygon
+    pub radius: f64,
+    /// Area of the polygon relative to that circle, close to 1 for the swing circle of a ship at anchor and close to 0 for a straight drift
+    pub circularity: f64,
+}
+
+impl Spread {
+    /// The spread of a polygon in longitude/latitude, [`None`] if it is empty
+    pub fn new(polygon: &Polygon) -> Option<Self> {
+        let coords = polygon.exterior().coords().copied().collect::<Vec<_>>();
+        if coords.is_empty() {
+            return None;
+        }
+        let plane = Plane::new::<4326>(coords.iter().copied());
+        let projected = coords
+            .into_iter()
+            .map(|c| plane.project(c))
+            .collect::<Vec<Coord>>();
+        let (_, radius) = minimum_enclosing_circle(&projected);
+        let area = Polygon::new(projected.into(), vec![]).unsigned_area();
+        let circularity = match radius {
+            0. => 0.,
+            radius => area / (std::f64::consts::PI * radius * radius),
+        };
+        Some(Spread {
+            radius,
+            circularity,
+        })
+    }
+}
+
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq)]
+pub struct StopKindConf {
+    /// Stops with a spread up to this radius in meters are moored or waiting, a moored ship only moves as far as its lines allow
+    #[builder(default = 50.)]
+    pub(crate) moored_radius: f64,
+    /// Stops with a larger spread are drifting, no anchor chain is that long
+    #[builder(default = 500.)]
+    pub(crate) max_swing_radius: f64,
+    /// Stops with a less circular spread are drifting, see [`Spread::circularity`]
+    #[builder(default = 0.3)]
+    pub(crate) min_circularity: f64,
+    /// Stops in which the draught changed at least this many meters are moored
+    #[builder(default = 0.3)]
+    pub(crate) min_draught_change: f64,
+    /// Stops closer than this many meters to a stop area are at a known berth or anchorage
+    #[builder(default = 100.)]
+    pub(crate) area_distance: f64,
+}
+
+impl StopKindConf {
+    /// Classifies a stop with the given polygon (in longitude/latitude).
+    ///
+    /// A reported status takes precedence, followed by a change of draught, since cargo is only handled alongside.
+    /// Otherwise the spread decides: small stops are moored at known stop areas and waiting elsewhere, and larger stops are anchored if they are circular enough and drifting if not.
+    /// [`StopKind::Fishing`] is therefore only returned if it was reported.
+    pub fn classify(&self, polygon: &Polygon, evidence: &StopEvidence) -> StopKind {
+        if let Some(kind) = evidence.reported {
+            return kind;
+        }
+        if evidence
+            .draught_change
+            .is_some_and(|d| d >= self.min_draught_change)
+        {
+            return StopKind::Moored;
+        }
+
+        let Some(spread) = Spread::new(polygon) else {
+            return StopKind::Waiting;
+        };
+        if spread.radius <= self.moored_radius {
+            match evidence.area_distance {
+                Some(d) if d <= self.area_distance => StopKind::Moored,
+                _ => StopKind::Waiting,
+            }
+        } else if spread.radius <= self.max_swing_radius
+            && spread.circularity >= self.min_circularity
+        {
+            StopKind::Anchored
+        } else {
+            StopKind::Drifting
+        }
+    }
+
+    /// Classifies every stop of `trajectory` and stores the result in its `kind`, `evidence` is asked for the evidence of every stop given its polygon and time range
+    pub fn classify_trajectory(
+        &self,
+        trajectory: &mut Trajectory<4326>,
+        evidence: impl Fn(&Polygon, (DateTime<Utc>, DateTime<Utc>)) -> StopEvidence,
+    ) {
+        for stop in trajectory.0.iter_mut() {
+            if let StopOrLs::Stop {
+                polygon,
+                tz_tange,
+                kind,
+            } = stop
+            {
+                *kind = Some(self.classify(polygon, &evidence(polygon, *tz_tange)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::consts::METERS_PER_DEGREE;
+    use crate::types::linestringm::LineStringM;
+    use geo::LineString;
+    use pretty_assertions::assert_eq;
+
+    /// A polygon of points at `(dx, dy)` meters from (10, 56)
+    fn polygon(offsets: impl Iterator<Item = (f64, f64)>) -> Polygon {
+        let scale = METERS_PER_DEGREE * 56_f64.to_radians().cos();
+        let mut coords = offsets
+            .map(|(dx, dy)| Coord::from((10. + dx / scale, 56. + dy / METERS_PER_DEGREE)))
+            .collect::<Vec<_>>();
+        coords.push(coords[0]);
+        Polygon::new(coords.into(), vec![])
+    }
+
+    fn circle(radius: f64) -> Polygon {
+        polygon((0..36).map(|i| {
+            let angle = f64::from(i * 10).to_radians();
+            (radius * angle.cos(), radius * angle.sin())
+        }))
+    }
+
+    /// A thin strip of `length` meters
+    fn strip(length: f64) -> Polygon {
+        polygon([(0., 0.), (length, 0.), (length, 10.), (0., 10.)].into_iter())
+    }
+
+    #[test]
+    fn spread() {
+        let spread = Spread::new(&circle(200.)).unwrap();
+        assert!((spread.radius - 200.).abs() < 2., "{spread:?}");
+        assert!(spread.circularity > 0.95, "{spread:?}");
+
+        let spread = Spread::new(&strip(1000.)).unwrap();
+        assert!((spread.radius - 500.).abs() < 2., "{spread:?}");
+        assert!(spread.circularity < 0.05, "{spread:?}");
+
+        assert_eq!(
+            Spread::new(&Polygon::new(LineString::new(vec![]), vec![])),
+            None
+        );
+    }
+
+    #[test]
+    fn classify_by_shape_and_evidence() {
+        let conf = StopKindConf::builder().build();
+        let none = StopEvidence::default();
+
+        assert_eq!(conf.classify(&circle(150.), &none), StopKind::Anchored);
+        assert_eq!(conf.classify(&circle(800.), &none), StopKind::Drifting);
+        assert_eq!(conf.classify(&strip(300.), &none), StopKind::Drifting);
+        assert_eq!(conf.classify(&circle(20.), &none), StopKind::Waiting);
+
+        let at_berth = StopEvidence {
+            area_distance: Some(10.),
+            ..none
+        };
+        assert_eq!(conf.classify(&circle(20.), &at_berth), StopKind::Moored);
+        // an anchorage is a stop area as well
+        assert_eq!(conf.classify(&circle(150.), &at_berth), StopKind::Anchored);
+
+        let loaded = StopEvidence {
+            draught_change: Some(1.5),
+            ..none
+        };
+        assert_eq!(conf.classify(&circle(20.), &loaded), StopKind::Moored);
+        let fishing = StopEvidence {
+            reported: Some(StopKind::Fishing),
+            ..loaded
+        };
+        assert_eq!(conf.classify(&strip(300.), &fishing), StopKind::Fishing);
+    }
+
+    #[test]
+    fn classify_trajectory() {
+        let start = DateTime::<Utc>::UNIX_EPOCH;
+        let mut trajectory = Trajectory(vec![
+            StopOrLs::Stop {
+                polygon: circle(150.),
+                tz_tange: (start, start),
+                kind: None,
+            },
+            StopOrLs::LS(LineStringM(vec![])),
+            StopOrLs::Stop {
+                polygon: circle(20.),
+                tz_tange: (start, start),
+                kind: None,
+            },
+        ]);
+        StopKindConf::builder()
+            .build()
+            .classify_trajectory(&mut trajectory, |_, _| StopEvidence::default());
+
+        let kinds = trajectory
+            .0
+            .iter()
+            .filter_map(|s| match s {
+                StopOrLs::Stop { kind, .. } => Some(*kind),
+                StopOrLs::LS(_) => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![Some(StopKind::Anchored), Some(StopKind::Waiting)]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        for kind in [
+            StopKind::Moored,
+            StopKind::Anchored,
+            StopKind::Drifting,
+            StopKind::Fishing,
+            StopKind::Waiting,
+        ] {
+            let json = serde_json::to_string(&kind).unwrap();
+            assert_eq!(serde_json::from_str::<StopKind>(&json).unwrap(), kind);
+        }
+        assert_eq!(
+            serde_json::to_string(&StopKind::Moored).unwrap(),
+            r#""Moored""#
+        );
+    }
+}
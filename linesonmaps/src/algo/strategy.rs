@@ -0,0 +1,456 @@
+use std::num::NonZero;
+
+use chrono::TimeDelta;
+use geo::{Coord, Distance};
+use typed_builder::TypedBuilder;
+
+use crate::algo::kinematics::{SegmentKinematics, segment_kinematics, turn, vertex_kinematics};
+use crate::algo::segmenter::TrajectorySplit;
+use crate::types::coordm::CoordM;
+use crate::types::crs::{Crs, Epsg, Unit};
+use crate::types::linestringm::LineStringM;
+use crate::types::measure;
+use crate::types::pointm::PointM;
+
+/// Decides where a trajectory is split by [`segment`].
+///
+/// Strategies see the whole trajectory on every call, so they can look at a window around the current point, and they can keep state between calls.
+/// Additional data, e.g. reported navigational status, is given to the strategy when it is constructed (see [`StatusChange`]).
+///
+/// Every closure `Fn(PointM, PointM) -> bool` is a strategy, splitting where it returns `false` for two subsequent points (as with [`segmenter`](crate::algo::segmenter::segmenter)).
+/// A tuple of two strategies splits wherever either of them does.
+pub trait SegmentationStrategy<const CRS: u64> {
+    /// Called once per trajectory before any call to [`SegmentationStrategy::split_before`], e.g. to precompute values or reset state
+    fn prepare(&mut self, _ls: &LineStringM<CRS>) {}
+
+    /// Whether `ls` should be split between point `i - 1` and point `i`, i.e. if point `i` starts a new split.
+    ///
+    /// Called for every `1 <= i < ls.0.len()` in increasing order.
+    fn split_before(&mut self, ls: &LineStringM<CRS>, i: usize) -> bool;
+}
+
+impl<const CRS: u64, F> SegmentationStrategy<CRS> for F
+where
+    F: FnMut(PointM<CRS>, PointM<CRS>) -> bool,
+{
+    fn split_before(&mut self, ls: &LineStringM<CRS>, i: usize) -> bool {
+        !self(ls.0[i - 1].into(), ls.0[i].into())
+    }
+}
+
+impl<const CRS: u64, A, B> SegmentationStrategy<CRS> for (A, B)
+where
+    A: SegmentationStrategy<CRS>,
+    B: SegmentationStrategy<CRS>,
+{
+    fn prepare(&mut self, ls: &LineStringM<CRS>) {
+        self.0.prepare(ls);
+        self.1.prepare(ls);
+    }
+
+    fn split_before(&mut self, ls: &LineStringM<CRS>, i: usize) -> bool {
+        // both are asked, such that stateful strategies see every point
+        self.0.split_before(ls, i) | self.1.split_before(ls, i)
+    }
+}
+
+/// Splits a linestring into (potentially) several sub-segments wherever `strategy` decides to.
+///
+/// Points are never dropped or reordered, splits of a single point are returned as [`TrajectorySplit::Point`].
+pub fn segment<const CRS: u64, S>(
+    ls: LineStringM<CRS>,
+    strategy: &mut S,
+) -> Vec<TrajectorySplit<CRS>>
+where
+    S: SegmentationStrategy<CRS> + ?Sized,
+{
+    strategy.prepare(&ls);
+
+    let mut splits = vec![];
+    let mut current = vec![];
+    for i in 0..ls.0.len() {
+        if i > 0 && strategy.split_before(&ls, i) {
+            splits.extend(TrajectorySplit::from_coords(std::mem::take(&mut current)));
+        }
+        current.push(ls.0[i]);
+    }
+    splits.extend(TrajectorySplit::from_coords(current));
+    splits
+}
+
+/// Distances in meters according to [`PointM::distance_m`]
+struct Native;
+
+impl<const CRS: u64> Distance<f64, PointM<CRS>, PointM<CRS>> for Native
+where
+    Epsg<CRS>: Crs,
+{
+    fn distance(&self, origin: PointM<CRS>, destination: PointM<CRS>) -> f64 {
+        origin.distance_m(&destination)
+    }
+}
+
+/// Splits where two subsequent points are too far apart in space or time, e.g. at gaps in AIS coverage
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq)]
+pub struct DistanceTime {
+    /// Subsequent points must be less than this many meters apart
+    pub(crate) max_distance: f64,
+    /// Subsequent points must be less than this far apart in time
+    pub(crate) max_gap: TimeDelta,
+}
+
+impl<const CRS: u64> SegmentationStrategy<CRS> for DistanceTime
+where
+    Epsg<CRS>: Crs,
+{
+    fn split_before(&mut self, ls: &LineStringM<CRS>, i: usize) -> bool {
+        let (a, b) = (PointM::from(ls.0[i - 1]), PointM::from(ls.0[i]));
+        a.distance_m(&b) >= self.max_distance
+            || measure::to_timedelta(b.coord.m - a.coord.m) >= self.max_gap
+    }
+}
+
+/// Splits at transitions between stops and movement.
+///
+/// A point is part of a stop if the speed averaged over the `window` points around it is below `speed_thres`.
+#[derive(TypedBuilder, Debug, Clone, PartialEq)]
+pub struct StopMove {
+    /// Speed over ground in knots below which the ship is considered stopped
+    pub(crate) speed_thres: f64,
+    #[builder(default = NonZero::<usize>::MIN)]
+    pub(crate) window: NonZero<usize>,
+    #[builder(default, setter(skip))]
+    stopped: Vec<bool>,
+}
+
+impl<const CRS: u64> SegmentationStrategy<CRS> for StopMove
+where
+    Epsg<CRS>: Crs,
+{
+    fn prepare(&mut self, ls: &LineStringM<CRS>) {
+        self.stopped = vertex_kinematics(ls, &Native, self.window)
+            .into_iter()
+            .map(|v| v.sog.is_some_and(|sog| sog < self.speed_thres))
+            .collect();
+    }
+
+    fn split_before(&mut self, _ls: &LineStringM<CRS>, i: usize) -> bool {
+        self.stopped[i - 1] != self.stopped[i]
+    }
+}
+
+/// Splits once the heading has changed by more than `max_turn` since the start of the split.
+///
+/// Turns are summed with their sign, so zig-zagging around a course does not add up.
+#[derive(TypedBuilder, Debug, Clone, PartialEq)]
+pub struct HeadingChange {
+    /// Largest cumulative turn in degrees within a split
+    pub(crate) max_turn: f64,
+    /// Segments slower than this many knots have no meaningful course, and are skipped
+    #[builder(default = 0.5)]
+    pub(crate) min_sog: f64,
+    #[builder(default, setter(skip))]
+    segments: Vec<SegmentKinematics>,
+    #[builder(default, setter(skip))]
+    heading: Option<f64>,
+    #[builder(default, setter(skip))]
+    turned: f64,
+}
+
+impl<const CRS: u64> SegmentationStrategy<CRS> for HeadingChange
+where
+    Epsg<CRS>: Crs,
+{
+    fn prepare(&mut self, ls: &LineStringM<CRS>) {
+        self.segments = segment_kinematics(ls, &Native);
+        self.heading = None;
+        self.turned = 0.;
+    }
+
+    fn split_before(&mut self, _ls: &LineStringM<CRS>, i: usize) -> bool {
+        let segment = self.segments[i - 1];
+        let Some(cog) = segment
+            .cog
+            .filter(|_| segment.sog.is_some_and(|sog| sog >= self.min_sog))
+        else {
+            return false;
+        };
+
+        if let Some(heading) = self.heading.replace(cog) {
+            self.turned += turn(heading, cog);
+        }
+        if self.turned.abs() > self.max_turn {
+            self.turned = 0.;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Splits where the speed between two subsequent segments changes by more than `max_change`
+#[derive(TypedBuilder, Debug, Clone, PartialEq)]
+pub struct SpeedChange {
+    /// Largest change in speed over ground in knots between two segments
+    pub(crate) max_change: f64,
+    #[builder(default, setter(skip))]
+    segments: Vec<SegmentKinematics>,
+}
+
+impl<const CRS: u64> SegmentationStrategy<CRS> for SpeedChange
+where
+    Epsg<CRS>: Crs,
+{
+    fn prepare(&mut self, ls: &LineStringM<CRS>) {
+        self.segments = segment_kinematics(ls, &Native);
+    }
+
+    fn split_before(&mut self, _ls: &LineStringM<CRS>, i: usize) -> bool {
+        // the speed into point i - 1 and out of it
+        let (Some(before), Some(after)) = (i.checked_sub(2), Some(i - 1)) else {
+            return false;
+        };
+        match (self.segments[before].sog, self.segments[after].sog) {
+            (Some(a), Some(b)) => (b - a).abs() > self.max_change,
+            _ => false,
+        }
+    }
+}
+
+/// Splits at the characteristic points found by the approximate minimum description length (MDL) partitioning of TRACLUS
+/// (Lee, Han & Whang, 2007, "Trajectory Clustering: A Partition-and-Group Framework").
+///
+/// A partition is extended as long as describing it by its endpoints is not more expensive than describing all of its segments.
+/// Lengths are in meters in a local plane, and offset by 1 before taking the logarithm such that short distances do not have negative cost.
+/// Unlike TRACLUS, partitions do not share their characteristic point, it starts the next split.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mdl {
+    characteristic: Vec<usize>,
+}
+
+impl Mdl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<const CRS: u64> SegmentationStrategy<CRS> for Mdl
+where
+    Epsg<CRS>: Crs,
+{
+    fn prepare(&mut self, ls: &LineStringM<CRS>) {
+        let points = plane(ls);
+        self.characteristic.clear();
+
+        let (mut start, mut length) = (0, 1);
+        while start + length < points.len() {
+            let current = start + length;
+            if mdl_par(&points[start..=current]) > mdl_nopar(&points[start..=current]) {
+                self.characteristic.push(current - 1);
+                start = current - 1;
+                length = 1;
+            } else {
+                length += 1;
+            }
+        }
+    }
+
+    fn split_before(&mut self, _ls: &LineStringM<CRS>, i: usize) -> bool {
+        self.characteristic.binary_search(&i).is_ok()
+    }
+}
+
+/// Positions in meters relative to the first point, degrees are projected onto the plane tangent to it
+fn plane<const CRS: u64>(ls: &LineStringM<CRS>) -> Vec<(f64, f64)>
+where
+    Epsg<CRS>: Crs,
+{
+    let Some(origin) = ls.0.first() else {
+        return vec![];
+    };
+    let scale = <Epsg<CRS> as Crs>::Unit::scale(Coord::from((origin.x, origin.y)));
+    ls.0.iter()
+        .map(|c| ((c.x - origin.x) * scale.x, (c.y - origin.y) * scale.y))
+        .collect()
+}
+
+fn cost(length: f64) -> f64 {
+    (1. + length).log2()
+}
+
+/// Cost of describing `points` by the segment between its endpoints, plus the deviation of every segment from it
+fn mdl_par(points: &[(f64, f64)]) -> f64 {
+    let (Some(&start), Some(&end)) = (points.first(), points.last()) else {
+        return 0.;
+    };
+    let deviations: f64 = points
+        .windows(2)
+        .map(|w| deviation(start, end, w[0], w[1]))
+        .map(|(perpendicular, angle)| cost(perpendicular) + cost(angle))
+        .sum();
+    cost(hypot(start, end)) + deviations
+}
+
+/// Cost of describing every segment of `points`
+fn mdl_nopar(points: &[(f64, f64)]) -> f64 {
+    points.windows(2).map(|w| cost(hypot(w[0], w[1]))).sum()
+}
+
+fn hypot(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+/// Perpendicular and angular distance of the segment `s1`-`s2` to the segment `l1`-`l2`, as defined by TRACLUS
+fn deviation(l1: (f64, f64), l2: (f64, f64), s1: (f64, f64), s2: (f64, f64)) -> (f64, f64) {
+    let (lx, ly) = (l2.0 - l1.0, l2.1 - l1.1);
+    let length = lx.hypot(ly);
+    if length == 0. {
+        return (hypot(l1, s1).max(hypot(l1, s2)), hypot(s1, s2));
+    }
+    // distance of a point to the line through l1 and l2
+    let distance = |p: (f64, f64)| ((p.0 - l1.0) * ly - (p.1 - l1.1) * lx).abs() / length;
+    let (d1, d2) = (distance(s1), distance(s2));
+    let perpendicular = if d1 + d2 == 0. {
+        0.
+    } else {
+        (d1 * d1 + d2 * d2) / (d1 + d2)
+    };
+
+    let (sx, sy) = (s2.0 - s1.0, s2.1 - s1.1);
+    let s_length = sx.hypot(sy);
+    let cos = if s_length == 0. {
+        1.
+    } else {
+        (lx * sx + ly * sy) / (length * s_length)
+    };
+    let angle = if cos < 0. {
+        s_length
+    } else {
+        s_length * (1. - cos.min(1.).powi(2)).sqrt()
+    };
+    (perpendicular, angle)
+}
+
+/// Splits where the status of the ship changes, e.g. its reported navigational status.
+///
+/// `status` gives the status at a point, points without a known status keep the status of the point before them.
+#[derive(Debug, Clone)]
+pub struct StatusChange<F, S> {
+    status: F,
+    last: Option<S>,
+}
+
+impl<F, S> StatusChange<F, S> {
+    pub fn new(status: F) -> Self {
+        StatusChange { status, last: None }
+    }
+}
+
+impl<const CRS: u64, F, S> SegmentationStrategy<CRS> for StatusChange<F, S>
+where
+    F: FnMut(&CoordM<CRS>) -> Option<S>,
+    S: PartialEq,
+{
+    fn prepare(&mut self, ls: &LineStringM<CRS>) {
+        self.last = ls.0.first().and_then(&mut self.status);
+    }
+
+    fn split_before(&mut self, ls: &LineStringM<CRS>, i: usize) -> bool {
+        let Some(status) = (self.status)(&ls.0[i]) else {
+            return false;
+        };
+        match self.last.replace(status) {
+            Some(last) => Some(&last) != self.last.as_ref(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::segmenter::segmenter;
+    use pretty_assertions::assert_eq;
+    use wkb::reader::read_wkb;
+
+    fn lengths<const CRS: u64>(splits: &[TrajectorySplit<CRS>]) -> Vec<usize> {
+        splits
+            .iter()
+            .map(|s| match s {
+                TrajectorySplit::Point(_) => 1,
+                TrajectorySplit::SubTrajectory(ls) => ls.0.len(),
+            })
+            .collect()
+    }
+
+    /// East at 10 m/s for a minute, a stop of a minute, then north at 10 m/s
+    fn stop_and_turn() -> LineStringM<3857> {
+        let east = (0..=6).map(|i| (i as f64 * 100., 0., i as f64 * 10.));
+        let stop = (1..=6).map(|i| (600. + i as f64 * 0.1, 0., 60. + i as f64 * 10.));
+        let north = (1..=6).map(|i| (600.6, i as f64 * 100., 120. + i as f64 * 10.));
+        LineStringM::new(east.chain(stop).chain(north).map(CoordM::from).collect()).unwrap()
+    }
+
+    #[test]
+    fn closures_are_strategies() {
+        const HEXSTRING: &str = include_str!("./resources/207138000.txt");
+
+        let bytea = hex::decode(HEXSTRING).unwrap();
+        let ls = LineStringM::<4326>::try_from(read_wkb(&bytea).unwrap()).unwrap();
+        let func = |f: PointM, s: PointM| s.coord.m - f.coord.m <= 60.;
+
+        let mut strategy = DistanceTime::builder()
+            .max_distance(f64::INFINITY)
+            .max_gap(TimeDelta::milliseconds(60_001))
+            .build();
+        assert_eq!(
+            segment(ls.clone(), &mut { func }),
+            segmenter(ls.clone(), func)
+        );
+        assert_eq!(segment(ls.clone(), &mut strategy), segmenter(ls, func));
+    }
+
+    #[test]
+    fn kinematic_strategies() {
+        let ls = stop_and_turn();
+
+        let mut stop_move = StopMove::builder().speed_thres(1.).build();
+        assert_eq!(lengths(&segment(ls.clone(), &mut stop_move)), vec![7, 5, 7]);
+
+        let mut speed = SpeedChange::builder().max_change(5.).build();
+        assert_eq!(lengths(&segment(ls.clone(), &mut speed)), vec![7, 6, 6]);
+
+        // the turn only counts once the ship is moving again
+        let mut heading = HeadingChange::builder().max_turn(45.).build();
+        assert_eq!(lengths(&segment(ls.clone(), &mut heading)), vec![13, 6]);
+
+        let mut both = (stop_move, heading);
+        assert_eq!(lengths(&segment(ls, &mut both)), vec![7, 5, 1, 6]);
+    }
+
+    #[test]
+    fn mdl_partitioning() {
+        let ls = stop_and_turn();
+        let splits = segment(ls.clone(), &mut Mdl::new());
+        // the straight legs are not partitioned
+        assert_eq!(splits.len(), 2);
+        assert_eq!(TrajectorySplit::concat_to_linestring(splits).unwrap(), ls);
+
+        let straight = LineStringM::<3857>::new(
+            (0..10)
+                .map(|i| (i as f64 * 100., 0., i as f64).into())
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(segment(straight, &mut Mdl::new()).len(), 1);
+    }
+
+    #[test]
+    fn status_changes() {
+        let ls = stop_and_turn();
+        // a status that is only reported every 30 seconds, and changes at 60 seconds
+        let mut status =
+            StatusChange::new(|c: &CoordM<3857>| (c.m % 30. == 0.).then_some(c.m >= 60.));
+        assert_eq!(lengths(&segment(ls, &mut status)), vec![6, 13]);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::algo::segmenter::TrajectorySplit;
+use crate::types::coordm::CoordM;
+use crate::types::error::Error;
+use crate::types::pointm::PointM;
+
+/// The open tail of a [`StreamSegmenter`], i.e. the points of the split that has not been closed by the splitting function yet.
+///
+/// Storing the checkpoint and passing it to [`StreamSegmenter::resume`] continues the segmentation as if it was never interrupted.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint<const CRS: u64 = 4326> {
+    pub(crate) tail: Vec<CoordM<CRS>>,
+}
+
+impl<const CRS: u64> Checkpoint<CRS> {
+    /// Measure of the last point seen, points before it will be rejected after resuming
+    pub fn last_m(&self) -> Option<f64> {
+        self.tail.last().map(|c| c.m)
+    }
+}
+
+/// Incremental version of [`segmenter`](crate::algo::segmenter::segmenter), for live feeds and trajectories that do not fit in memory.
+///
+/// Points are pushed one at a time (or in chunks, e.g. pages of a database query), and a [`TrajectorySplit`] is emitted as soon as the splitting function closes it.
+/// The splits emitted by [`StreamSegmenter::push`] followed by [`StreamSegmenter::finish`] are the same as those of `segmenter` on the whole linestring.
+///
+/// A segmenter follows a single trajectory, use one per ship.
+#[derive(Debug, Clone)]
+pub struct StreamSegmenter<const CRS: u64, F> {
+    func: F,
+    tail: Vec<CoordM<CRS>>,
+}
+
+impl<const CRS: u64, F> StreamSegmenter<CRS, F>
+where
+    F: Fn(PointM<CRS>, PointM<CRS>) -> bool,
+{
+    /// `func`: A function that compares to subsequent points, the trajectory will be split if the function returns `false`
+    pub fn new(func: F) -> Self {
+        Self::resume(func, Checkpoint::default())
+    }
+
+    /// Continues from a [`Checkpoint`] of a segmenter with the same splitting function
+    pub fn resume(func: F, checkpoint: Checkpoint<CRS>) -> Self {
+        StreamSegmenter {
+            func,
+            tail: checkpoint.tail,
+        }
+    }
+
+    /// The open tail, see [`Checkpoint`]
+    pub fn checkpoint(&self) -> Checkpoint<CRS> {
+        Checkpoint {
+            tail: self.tail.clone(),
+        }
+    }
+
+    /// Adds the next point of the trajectory, returning the split it closed, if any.
+    ///
+    /// Fails with [`Error::Timestamp`] if the point lies before the previous one, the point is then ignored.
+    pub fn push(&mut self, coord: CoordM<CRS>) -> Result<Option<TrajectorySplit<CRS>>, Error> {
+        let Some(last) = self.tail.last() else {
+            self.tail.push(coord);
+            return Ok(None);
+        };
+        if coord.m < last.m {
+            return Err(Error::Timestamp);
+        }
+
+        if (self.func)(last.into(), coord.into()) {
+            self.tail.push(coord);
+            Ok(None)
+        } else {
+            let closed = std::mem::replace(&mut self.tail, vec![coord]);
+            Ok(TrajectorySplit::from_coords(closed))
+        }
+    }
+
+    /// Adds a chunk of points, yielding the splits they close and the points that were rejected by [`StreamSegmenter::push`].
+    ///
+    /// Points are only consumed as the returned iterator is advanced.
+    pub fn extend<'a, I>(
+        &'a mut self,
+        coords: I,
+    ) -> impl Iterator<Item = Result<TrajectorySplit<CRS>, Error>> + 'a
+    where
+        I: IntoIterator<Item = CoordM<CRS>>,
+        I::IntoIter: 'a,
+    {
+        coords
+            .into_iter()
+            .filter_map(move |coord| self.push(coord).transpose())
+    }
+
+    /// Closes the open tail at the end of the trajectory, [`None`] if no points were pushed since the last split
+    pub fn finish(self) -> Option<TrajectorySplit<CRS>> {
+        TrajectorySplit::from_coords(self.tail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::segmenter::segmenter;
+    use crate::types::linestringm::LineStringM;
+    use geo::{Distance, Haversine};
+    use pretty_assertions::assert_eq;
+    use wkb::reader::read_wkb;
+
+    #[test]
+    fn push_points() {
+        let func = |f: PointM, s: PointM| (s.coord.m - f.coord.m) <= 1.1;
+        let mut seg = StreamSegmenter::new(func);
+
+        assert_eq!(seg.push((1.0, 2.0, 0.0).into()), Ok(None));
+        assert_eq!(seg.push((2.0, 3.0, 1.0).into()), Ok(None));
+        assert_eq!(
+            seg.push((3.0, 4.0, 3.0).into()),
+            Ok(Some(TrajectorySplit::SubTrajectory(
+                LineStringM::new(vec![(1.0, 2.0, 0.0).into(), (2.0, 3.0, 1.0).into()]).unwrap()
+            )))
+        );
+        assert_eq!(
+            seg.push((4.0, 5.0, 10.0).into()),
+            Ok(Some(TrajectorySplit::Point((3.0, 4.0, 3.0).into())))
+        );
+        // out of order
+        assert_eq!(seg.push((4.0, 5.0, 9.0).into()), Err(Error::Timestamp));
+        assert_eq!(seg.checkpoint().last_m(), Some(10.0));
+
+        assert_eq!(
+            seg.finish(),
+            Some(TrajectorySplit::Point((4.0, 5.0, 10.0).into()))
+        );
+        assert_eq!(StreamSegmenter::new(func).finish(), None);
+    }
+
+    #[test]
+    fn resumed_in_pages() {
+        const HEXSTRING: &str = include_str!("./resources/207138000.txt");
+
+        let bytea = hex::decode(HEXSTRING).unwrap();
+        let lsm = LineStringM::<4326>::try_from(read_wkb(&bytea).unwrap()).unwrap();
+        let func = |f: PointM, s: PointM| {
+            Haversine.distance(f, s) <= 1000. && s.coord.m - f.coord.m <= 60.
+        };
+
+        let expected = segmenter(lsm.clone(), func);
+        assert!(expected.len() > 1);
+
+        let mut splits = vec![];
+        let mut checkpoint = Checkpoint::default();
+        for page in lsm.0.chunks(1000) {
+            // a fresh segmenter per page, as if the process was restarted in between
+            let mut seg = StreamSegmenter::resume(func, checkpoint);
+            splits.extend(seg.extend(page.iter().copied()).map(Result::unwrap));
+            checkpoint = seg.checkpoint();
+        }
+        splits.extend(StreamSegmenter::resume(func, checkpoint).finish());
+
+        assert_eq!(splits, expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let func = |f: PointM, s: PointM| (s.coord.m - f.coord.m) <= 1.1;
+        let mut seg = StreamSegmenter::new(func);
+        for m in [0., 1., 2.] {
+            seg.push((1., 2., m).into()).unwrap();
+        }
+
+        let json = serde_json::to_string(&seg.checkpoint()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"tail":[[1.0,2.0,0.0],[1.0,2.0,1.0],[1.0,2.0,2.0]]}"#
+        );
+        let checkpoint = serde_json::from_str::<Checkpoint>(&json).unwrap();
+        assert_eq!(checkpoint, seg.checkpoint());
+    }
+}
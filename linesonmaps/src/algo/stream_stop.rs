@@ -0,0 +1,473 @@
+//! Incremental version of the stop detection of [`DbScanConf`], for live feeds of many ships.
+//!
+//! A stop is started as soon as a point is a core point, i.e. it has `min_cluster_size` slow points within `dist_thres` and `max_time_thres` of it (itself included),
+//! and it grows as long as new points are density-reachable from it. It ends once no point joined it for `max_time_thres`,
+//! or when the ship starts another stop elsewhere.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::num::NonZero;
+
+use chrono::{DateTime, Utc};
+use geo::{ConvexHull, LineString, Polygon};
+
+use crate::algo::stop_cluster::{DbScanConf, StopOrLs};
+use crate::types::crs::{Crs, Epsg};
+use crate::types::error::Error;
+use crate::types::measure;
+use crate::types::pointm::PointM;
+
+/// What happened to the stop of a ship
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopEvent<K> {
+    /// The ship stopped, the polygon is provisional and covers the points of the stop so far
+    Started {
+        vessel: K,
+        start: DateTime<Utc>,
+        polygon: Polygon,
+    },
+    /// The stop is over, with its final polygon
+    Ended {
+        vessel: K,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        polygon: Polygon,
+    },
+}
+
+impl<K> StopEvent<K> {
+    /// The ship of the event
+    pub fn vessel(&self) -> &K {
+        match self {
+            StopEvent::Started { vessel, .. } | StopEvent::Ended { vessel, .. } => vessel,
+        }
+    }
+
+    /// The finished stop, as found by [`cluster_to_traj_with_stop_object`](crate::algo::stop_cluster::cluster_to_traj_with_stop_object), [`None`] for started stops
+    pub fn into_stop<const CRS: u64>(self) -> Option<StopOrLs<CRS>> {
+        match self {
+            StopEvent::Started { .. } => None,
+            StopEvent::Ended {
+                start,
+                end,
+                polygon,
+                ..
+            } => Some(StopOrLs::Stop {
+                polygon,
+                tz_tange: (start, end),
+                kind: None,
+            }),
+        }
+    }
+}
+
+/// A recent slow point
+struct Entry<const CRS: u64> {
+    point: PointM<CRS>,
+    sog: f32,
+    /// Number of neighbors, itself included
+    neighbors: usize,
+    /// Whether the point belongs to the open stop
+    member: bool,
+}
+
+/// A stop that has not ended yet
+struct OpenStop<const CRS: u64> {
+    start: f64,
+    end: f64,
+    /// Points of the stop, reduced to the vertices of their convex hull when there are too many
+    points: Vec<PointM<CRS>>,
+}
+
+impl<const CRS: u64> OpenStop<CRS> {
+    fn polygon(&self) -> Polygon {
+        LineString::from_iter(self.points.iter().map(|p| (p.coord.x, p.coord.y))).convex_hull()
+    }
+
+    fn add(&mut self, point: PointM<CRS>, max_points: usize) {
+        self.start = self.start.min(point.coord.m);
+        self.end = self.end.max(point.coord.m);
+        self.points.push(point);
+        if self.points.len() > max_points {
+            self.points = self
+                .polygon()
+                .exterior()
+                .coords()
+                .map(|c| PointM::from((c.x, c.y, 0.)))
+                .collect();
+        }
+    }
+}
+
+struct Vessel<const CRS: u64> {
+    window: VecDeque<Entry<CRS>>,
+    stop: Option<OpenStop<CRS>>,
+    last_m: Option<f64>,
+}
+
+impl<const CRS: u64> Default for Vessel<CRS> {
+    fn default() -> Self {
+        Vessel {
+            window: VecDeque::new(),
+            stop: None,
+            last_m: None,
+        }
+    }
+}
+
+/// Detects stops of many ships from their points as they arrive, using the thresholds of a [`DbScanConf`].
+///
+/// Only a bounded window of recent slow points is kept per ship, so a stop is only found if enough of its points arrive within the window.
+/// Polygons are convex hulls, as with [`cluster_to_traj_with_stop_object`](crate::algo::stop_cluster::cluster_to_traj_with_stop_object).
+pub struct StopDetector<K, Dist, const CRS: u64>
+where
+    Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+{
+    conf: DbScanConf<Dist, CRS>,
+    window: NonZero<usize>,
+    vessels: BTreeMap<K, Vessel<CRS>>,
+}
+
+impl<K, Dist, const CRS: u64> StopDetector<K, Dist, CRS>
+where
+    Epsg<CRS>: Crs,
+    K: Clone + Ord,
+    Dist: Fn(&PointM<CRS>, &PointM<CRS>) -> f64 + Send + Sync,
+{
+    /// `window`: Maximum number of recent slow points kept per ship, older points are dropped once they are `max_time_thres` old anyway.
+    /// It is raised to `min_cluster_size` if smaller, since no point could become a core point otherwise.
+    pub fn new(conf: DbScanConf<Dist, CRS>, window: NonZero<usize>) -> Self {
+        StopDetector {
+            window: window.max(conf.min_cluster_size),
+            conf,
+            vessels: BTreeMap::new(),
+        }
+    }
+
+    fn close(&self, a: &PointM<CRS>, b: &Entry<CRS>) -> bool {
+        (self.conf.dist)(a, &b.point) < self.conf.dist_thres
+            && self.conf.temporal_sog_close(a, &b.point, b.sog)
+    }
+
+    fn ended(vessel: K, stop: OpenStop<CRS>) -> StopEvent<K> {
+        StopEvent::Ended {
+            vessel,
+            start: measure::to_datetime(stop.start)
+                .expect("timestamp should be well within bounds"),
+            end: measure::to_datetime(stop.end).expect("timestamp should be well within bounds"),
+            polygon: stop.polygon(),
+        }
+    }
+
+    /// Adds the next point of a ship along with its SOG, returning the events it caused: at most the end of a stop followed by the start of the next.
+    ///
+    /// Fails with [`Error::Timestamp`] if the point lies before the previous point of the ship,
+    /// and with [`Error::MeasureRange`] if its measure is not a valid timestamp, the point is then ignored.
+    pub fn push(
+        &mut self,
+        vessel: K,
+        point: PointM<CRS>,
+        sog: f32,
+    ) -> Result<Vec<StopEvent<K>>, Error> {
+        // the timestamps of the events are taken from the points, so they have to be valid
+        if measure::to_datetime(point.coord.m).is_none() {
+            return Err(Error::MeasureRange);
+        }
+        let mut state = self.vessels.remove(&vessel).unwrap_or_default();
+        if state.last_m.is_some_and(|m| point.coord.m < m) {
+            self.vessels.insert(vessel, state);
+            return Err(Error::Timestamp);
+        }
+        state.last_m = Some(point.coord.m);
+
+        let mut events = vec![];
+        let max_time = self.conf.max_time_thres.as_seconds_f64();
+        if let Some(stop) = state.stop.take_if(|s| point.coord.m - s.end >= max_time) {
+            events.push(Self::ended(vessel.clone(), stop));
+            state.window.iter_mut().for_each(|e| e.member = false);
+        }
+        if sog < self.conf.speed_thres {
+            self.add(&vessel, &mut state, point, sog, &mut events);
+        }
+
+        self.vessels.insert(vessel, state);
+        Ok(events)
+    }
+
+    fn add(
+        &self,
+        vessel: &K,
+        state: &mut Vessel<CRS>,
+        point: PointM<CRS>,
+        sog: f32,
+        events: &mut Vec<StopEvent<K>>,
+    ) {
+        let max_time = self.conf.max_time_thres.as_seconds_f64();
+        let min = self.conf.min_cluster_size.get();
+        let max_points = self.window.get();
+        let window = &mut state.window;
+        while window
+            .front()
+            .is_some_and(|e| point.coord.m - e.point.coord.m >= max_time)
+            || window.len() >= max_points
+        {
+            window.pop_front();
+        }
+
+        let neighbors = (0..window.len())
+            .filter(|&i| self.close(&point, &window[i]))
+            .collect::<Vec<_>>();
+        window.push_back(Entry {
+            point,
+            sog,
+            neighbors: neighbors.len() + 1,
+            member: false,
+        });
+        let new = window.len() - 1;
+
+        // points that just became core, the new point and its neighbors with one more neighbor
+        let mut cores = vec![];
+        for &i in &neighbors {
+            window[i].neighbors += 1;
+            if window[i].neighbors == min {
+                cores.push(i);
+            }
+        }
+        if window[new].neighbors >= min {
+            cores.push(new);
+        }
+        // a new edge point of the stop
+        if state.stop.is_some()
+            && neighbors
+                .iter()
+                .any(|&i| window[i].member && window[i].neighbors >= min)
+        {
+            window[new].member = true;
+            if let Some(stop) = &mut state.stop {
+                stop.add(point, max_points);
+            }
+        }
+
+        for core in cores {
+            let reachable = (0..window.len())
+                .filter(|&i| i == core || self.close(&window[core].point, &window[i]))
+                .collect::<Vec<_>>();
+            let connected = reachable.iter().any(|&i| window[i].member);
+            if state.stop.is_some() && !connected {
+                // the ship stopped somewhere else
+                if let Some(stop) = state.stop.take() {
+                    events.push(Self::ended(vessel.clone(), stop));
+                }
+                window.iter_mut().for_each(|e| e.member = false);
+            }
+
+            let started = state.stop.is_none();
+            let stop = state.stop.get_or_insert_with(|| OpenStop {
+                start: window[core].point.coord.m,
+                end: window[core].point.coord.m,
+                points: vec![],
+            });
+            for i in reachable {
+                if !window[i].member {
+                    window[i].member = true;
+                    stop.add(window[i].point, max_points);
+                }
+            }
+            if started {
+                events.push(StopEvent::Started {
+                    vessel: vessel.clone(),
+                    start: measure::to_datetime(stop.start)
+                        .expect("timestamp should be well within bounds"),
+                    polygon: stop.polygon(),
+                });
+            }
+        }
+    }
+
+    /// The provisional polygon and start of the open stop of `vessel`, if any
+    pub fn open_stop(&self, vessel: &K) -> Option<(DateTime<Utc>, Polygon)> {
+        let stop = self.vessels.get(vessel)?.stop.as_ref()?;
+        Some((measure::to_datetime(stop.start)?, stop.polygon()))
+    }
+
+    /// Ends the stops that no point joined for `max_time_thres` before `now`, e.g. of ships that went silent, and forgets ships without recent points.
+    ///
+    /// The events are ordered by vessel.
+    pub fn flush(&mut self, now: DateTime<Utc>) -> Vec<StopEvent<K>> {
+        let (now, max_time) = (
+            measure::from_datetime(now),
+            self.conf.max_time_thres.as_seconds_f64(),
+        );
+        let mut events = vec![];
+        for (vessel, state) in self.vessels.iter_mut() {
+            if let Some(stop) = state.stop.take_if(|s| now - s.end >= max_time) {
+                events.push(Self::ended(vessel.clone(), stop));
+            }
+        }
+        self.vessels
+            .retain(|_, state| state.last_m.is_some_and(|m| now - m < max_time));
+        events
+    }
+
+    /// Ends every open stop, e.g. at the end of a chunked trajectory, ordered by vessel
+    pub fn finish(self) -> Vec<StopEvent<K>> {
+        self.vessels
+            .into_iter()
+            .filter_map(|(vessel, state)| Some(Self::ended(vessel, state.stop?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::stop_cluster::cluster_to_traj_with_stop_object;
+    use chrono::TimeDelta;
+    use geo::Area;
+    use pretty_assertions::assert_eq;
+
+    fn conf() -> DbScanConf<impl Fn(&PointM<3857>, &PointM<3857>) -> f64 + Send + Sync, 3857> {
+        DbScanConf::builder()
+            .dist(|a: &PointM<3857>, b: &PointM<3857>| a.distance_m(b))
+            .max_time_thres(TimeDelta::seconds(60))
+            .min_cluster_size(5.try_into().unwrap())
+            .speed_thres(1.5)
+            .dist_thres(50.)
+            .build()
+    }
+
+    /// Sails east, stops for 200 s with positions jittering within 20 m, and sails on, every 10 s
+    fn trajectory(offset: f64) -> Vec<(PointM<3857>, f32)> {
+        (0..60)
+            .map(|i| {
+                let m = offset + f64::from(i) * 10.;
+                match i {
+                    0..20 => ((f64::from(i) * 100., 0., m).into(), 10.),
+                    20..40 => {
+                        let jitter = f64::from(i % 5) * 5.;
+                        ((2000. + jitter, jitter, m).into(), 0.2)
+                    }
+                    _ => ((2000. + f64::from(i - 39) * 100., 0., m).into(), 10.),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_stop_as_batch() {
+        let points = trajectory(0.);
+        let mut detector = StopDetector::new(conf(), 100.try_into().unwrap());
+
+        let mut events = vec![];
+        for (i, (p, sog)) in points.iter().enumerate() {
+            let new = detector.push(1, *p, *sog).unwrap();
+            if matches!(new.first(), Some(StopEvent::Started { .. })) {
+                // as soon as there are enough slow points
+                assert_eq!(i, 24);
+                assert!(detector.open_stop(&1).is_some());
+            }
+            events.extend(new);
+        }
+        assert_eq!(detector.push(1, points[0].0, 0.), Err(Error::Timestamp));
+        assert_eq!(
+            detector.push(1, PointM::from((0., 0., f64::MAX)), 0.),
+            Err(Error::MeasureRange)
+        );
+        assert!(detector.finish().is_empty());
+
+        assert_eq!(events.len(), 2);
+        let StopEvent::Started { start, .. } = &events[0] else {
+            panic!("{events:?}")
+        };
+        assert_eq!(*start, measure::to_datetime(200.).unwrap());
+
+        let batch = cluster_to_traj_with_stop_object(conf().run(&points))
+            .0
+            .into_iter()
+            .filter(|s| matches!(s, StopOrLs::Stop { .. }))
+            .collect::<Vec<_>>();
+        let streamed = events[1].clone().into_stop::<3857>().unwrap();
+        match (&batch[..], &streamed) {
+            (
+                [
+                    StopOrLs::Stop {
+                        polygon: a,
+                        tz_tange: ta,
+                        ..
+                    },
+                ],
+                StopOrLs::Stop {
+                    polygon: b,
+                    tz_tange: tb,
+                    ..
+                },
+            ) => {
+                assert_eq!(ta, tb);
+                assert_eq!(a.unsigned_area(), b.unsigned_area());
+            }
+            _ => panic!("expected a single stop, found {}", batch.len()),
+        }
+    }
+
+    #[test]
+    fn many_vessels() {
+        let (a, b) = (trajectory(0.), trajectory(1000.));
+        // the window is too small to hold a whole stop, but large enough for a core point
+        let mut detector = StopDetector::new(conf(), 5.try_into().unwrap());
+
+        let mut events = vec![];
+        for (p, sog) in &a {
+            events.extend(detector.push("a", *p, *sog).unwrap());
+        }
+        // b has not left its stop yet
+        for (p, sog) in &b[..35] {
+            events.extend(detector.push("b", *p, *sog).unwrap());
+        }
+        let vessels = events.iter().map(|e| *e.vessel()).collect::<Vec<_>>();
+        assert_eq!(vessels, vec!["a", "a", "b"]);
+
+        // b went silent
+        let events = detector.flush(measure::to_datetime(1000. + 350. + 60.).unwrap());
+        assert!(matches!(
+            &events[..],
+            [StopEvent::Ended { vessel: "b", .. }]
+        ));
+        assert!(detector.open_stop(&"b").is_none());
+        assert!(detector.finish().is_empty());
+    }
+
+    #[test]
+    fn events_ordered_by_vessel() {
+        let mut detector = StopDetector::new(conf(), 100.try_into().unwrap());
+        // none of the ships has left its stop yet
+        for vessel in ["c", "a", "d", "b"] {
+            for (p, sog) in &trajectory(0.)[..35] {
+                detector.push(vessel, *p, *sog).unwrap();
+            }
+        }
+
+        let vessels = |events: Vec<StopEvent<&'static str>>| {
+            events.iter().map(|e| *e.vessel()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vessels(detector.flush(measure::to_datetime(350. + 60.).unwrap())),
+            vec!["a", "b", "c", "d"]
+        );
+
+        for vessel in ["d", "b", "c", "a"] {
+            for (p, sog) in &trajectory(1000.)[..35] {
+                detector.push(vessel, *p, *sog).unwrap();
+            }
+        }
+        assert_eq!(vessels(detector.finish()), vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn window_smaller_than_cluster() {
+        let mut detector = StopDetector::new(conf(), 1.try_into().unwrap());
+        let events = trajectory(0.)
+            .into_iter()
+            .flat_map(|(p, sog)| detector.push(1, p, sog).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 2);
+    }
+}
@@ -0,0 +1,286 @@
+//! A compact encoding of [`LineStringM`]s for storage and transfer, at a fraction of the 24 bytes per vertex of WKB.
+//!
+//! Coordinates are quantized to a fixed number of decimal digits (see [`CodecConf`]), and every vertex is stored as the difference to the previous one,
+//! as zigzag encoded LEB128 varints. Consecutive AIS positions are close in space and time, so most deltas fit in one or two bytes.
+//!
+//! The encoding starts with a header of a version byte, the SRID (varint), the number of digits of x/y and m (one byte each) and the number of vertices (varint).
+
+use std::io::{ErrorKind, Read};
+
+use typed_builder::TypedBuilder;
+
+use crate::types::coordm::CoordM;
+use crate::types::error::Error;
+use crate::types::linestringm::LineStringM;
+
+const VERSION: u8 = 1;
+
+/// Precision of the encoding, values are rounded to `digits` decimal digits.
+///
+/// The quantized values have to fit in an `i64`, i.e. `value * 10^digits` should stay well below `9.2e18`.
+#[derive(TypedBuilder, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodecConf {
+    /// Decimal digits of x and y, 7 digits is about a centimeter for degrees
+    #[builder(default = 7)]
+    pub(crate) xy_digits: u8,
+    /// Decimal digits of m, 3 digits is milliseconds for epoch seconds
+    #[builder(default = 3)]
+    pub(crate) m_digits: u8,
+}
+
+impl Default for CodecConf {
+    fn default() -> Self {
+        CodecConf::builder().build()
+    }
+}
+
+impl CodecConf {
+    /// Encodes `ls`, see the [module documentation](self) for the format
+    pub fn encode<const CRS: u64>(&self, ls: &LineStringM<CRS>) -> Vec<u8> {
+        let (xy, m) = (scale(self.xy_digits), scale(self.m_digits));
+
+        // most deltas take 1 to 3 bytes
+        let mut buf = Vec::with_capacity(16 + ls.0.len() * 6);
+        buf.push(VERSION);
+        varint(&mut buf, CRS);
+        buf.push(self.xy_digits);
+        buf.push(self.m_digits);
+        varint(&mut buf, ls.0.len() as u64);
+
+        let mut prev = [0_i64; 3];
+        for c in &ls.0 {
+            let q = [quantize(c.x, xy), quantize(c.y, xy), quantize(c.m, m)];
+            for (q, prev) in q.into_iter().zip(&mut prev) {
+                varint(&mut buf, zigzag(q.wrapping_sub(*prev)));
+                *prev = q;
+            }
+        }
+        buf
+    }
+}
+
+/// Decodes a complete linestring, see [`Decoder`] to decode vertex by vertex
+pub fn decode<const CRS: u64>(buf: &[u8]) -> Result<LineStringM<CRS>, Error> {
+    let coords = Decoder::<_, CRS>::new(buf)?.collect::<Result<Vec<_>, _>>()?;
+    LineStringM::try_from(coords)
+}
+
+/// Streaming decoder that yields the vertices of an encoded linestring as they are read from `R`.
+///
+/// Only the bytes of one linestring are consumed, so several linestrings can be decoded from one stream in turn.
+#[derive(Debug)]
+pub struct Decoder<R, const CRS: u64 = 4326> {
+    reader: R,
+    conf: CodecConf,
+    remaining: usize,
+    prev: [i64; 3],
+}
+
+impl<R: Read, const CRS: u64> Decoder<R, CRS> {
+    /// Reads the header, failing with [`Error::Srid`] if the linestring was encoded in another `CRS`
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let version = read_byte(&mut reader)?;
+        if version != VERSION {
+            return Err(Error::Codec(format!("unsupported version {version}")));
+        }
+        let srid = read_varint(&mut reader)?;
+        if srid != CRS {
+            return Err(Error::Srid {
+                expected: CRS,
+                found: u32::try_from(srid).ok(),
+            });
+        }
+        let conf = CodecConf {
+            xy_digits: read_byte(&mut reader)?,
+            m_digits: read_byte(&mut reader)?,
+        };
+        let remaining =
+            usize::try_from(read_varint(&mut reader)?).map_err(|e| Error::Codec(e.to_string()))?;
+
+        Ok(Decoder {
+            reader,
+            conf,
+            remaining,
+            prev: [0; 3],
+        })
+    }
+
+    /// The precision the linestring was encoded with
+    pub fn conf(&self) -> CodecConf {
+        self.conf
+    }
+
+    fn next_coord(&mut self) -> Result<CoordM<CRS>, Error> {
+        for prev in &mut self.prev {
+            let delta = unzigzag(read_varint(&mut self.reader)?);
+            *prev = prev.wrapping_add(delta);
+        }
+        let (xy, m) = (scale(self.conf.xy_digits), scale(self.conf.m_digits));
+        Ok(CoordM {
+            x: self.prev[0] as f64 / xy,
+            y: self.prev[1] as f64 / xy,
+            m: self.prev[2] as f64 / m,
+        })
+    }
+}
+
+impl<R: Read, const CRS: u64> Iterator for Decoder<R, CRS> {
+    type Item = Result<CoordM<CRS>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let coord = self.next_coord();
+        // nothing sensible can be read after a broken vertex
+        self.remaining = match coord {
+            Ok(_) => self.remaining - 1,
+            Err(_) => 0,
+        };
+        Some(coord)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+fn scale(digits: u8) -> f64 {
+    10_f64.powi(i32::from(digits))
+}
+
+fn quantize(value: f64, scale: f64) -> i64 {
+    (value * scale).round() as i64
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+fn varint(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+fn read_byte(reader: &mut impl Read) -> Result<u8, Error> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof => Error::Codec("unexpected end of input".to_string()),
+        _ => Error::Codec(e.to_string()),
+    })?;
+    Ok(byte[0])
+}
+
+fn read_varint(reader: &mut impl Read) -> Result<u64, Error> {
+    let mut n = 0_u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_byte(reader)?;
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(Error::Codec("varint longer than 64 bits".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use wkb::reader::read_wkb;
+
+    fn fixture(hexstring: &str) -> LineStringM<4326> {
+        let bytea = hex::decode(hexstring.replace('"', "").trim()).unwrap();
+        LineStringM::try_from(read_wkb(&bytea).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn zigzag_varint() {
+        for n in [0, 1, -1, 63, -64, 64, i64::MAX, i64::MIN] {
+            assert_eq!(unzigzag(zigzag(n)), n);
+
+            let mut buf = vec![];
+            varint(&mut buf, zigzag(n));
+            assert_eq!(read_varint(&mut buf.as_slice()), Ok(zigzag(n)));
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+
+    #[test]
+    fn roundtrip_fixtures() {
+        let conf = CodecConf::default();
+        for hexstring in [
+            include_str!("../algo/resources/207138000.txt"),
+            include_str!("../algo/resources/205689000.txt"),
+            include_str!("../algo/resources/219013708.txt"),
+        ] {
+            let ls = fixture(hexstring);
+            let encoded = conf.encode(&ls);
+            assert!(encoded.len() * 3 < ls.0.len() * 24);
+
+            let decoded = decode::<4326>(&encoded).unwrap();
+            assert_eq!(decoded.0.len(), ls.0.len());
+            assert!(decoded.0.iter().zip(&ls.0).all(|(a, b)| {
+                (a.x - b.x).abs() <= 0.5e-7
+                    && (a.y - b.y).abs() <= 0.5e-7
+                    && (a.m - b.m).abs() <= 0.5e-3
+            }));
+            // quantizing is idempotent
+            assert_eq!(conf.encode(&decoded), encoded);
+        }
+    }
+
+    #[test]
+    fn streaming_decode() {
+        let ls = LineStringM::<4326>::new(vec![
+            (10.1234567, 56.7654321, 1700000000.123).into(),
+            (10.1234, 56.7654, 1700000001.5).into(),
+            (10.2, 56.8, 1700000060.).into(),
+        ])
+        .unwrap();
+        let conf = CodecConf::builder().xy_digits(4).m_digits(0).build();
+
+        // two linestrings back to back in one stream
+        let stream = [conf.encode(&ls), CodecConf::default().encode(&ls)].concat();
+        let mut reader = stream.as_slice();
+
+        let decoder = Decoder::<_, 4326>::new(&mut reader).unwrap();
+        assert_eq!(decoder.conf(), conf);
+        assert_eq!(
+            decoder.collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                (10.1235, 56.7654, 1700000000.).into(),
+                (10.1234, 56.7654, 1700000002.).into(),
+                (10.2, 56.8, 1700000060.).into(),
+            ])
+        );
+
+        let decoder = Decoder::<_, 4326>::new(&mut reader).unwrap();
+        assert_eq!(decoder.collect::<Result<Vec<_>, _>>(), Ok(ls.0.clone()));
+        assert!(reader.is_empty());
+
+        // truncated input
+        let encoded = CodecConf::default().encode(&ls);
+        let mut decoder = Decoder::<_, 4326>::new(&encoded[..encoded.len() - 1]).unwrap();
+        assert!(decoder.next().unwrap().is_ok());
+        assert!(decoder.next().unwrap().is_ok());
+        assert!(matches!(decoder.next(), Some(Err(Error::Codec(_)))));
+        assert!(decoder.next().is_none());
+
+        assert_eq!(
+            Decoder::<_, 3857>::new(encoded.as_slice()).err(),
+            Some(Error::Srid {
+                expected: 3857,
+                found: Some(4326)
+            })
+        );
+    }
+}
@@ -1,3 +1,6 @@
 // Expand if needed
-pub const METRIC_CRS: [u64;1] = [3857];
-pub const DEGREE_CRS: [u64;1] = [4326];
\ No newline at end of file
+
+/// Mean earth radius in meters
+pub(crate) const EARTH_RADIUS: f64 = 6_371_008.8;
+/// Meters per degree of latitude
+pub(crate) const METERS_PER_DEGREE: f64 = EARTH_RADIUS * std::f64::consts::PI / 180.;
@@ -1,7 +1,16 @@
+use chrono::{DateTime, Utc};
 use geo_traits::CoordTrait;
 use geo_traits::GeometryTrait;
 use geo_traits::PointTrait;
+
+use crate::types::measure;
+/// Serialized as a compact `[x, y, m]` array, with `m` in epoch seconds
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "(f64, f64, f64)", into = "(f64, f64, f64)")
+)]
 pub struct CoordM<const CRS: u64 = 4326> {
     pub x: f64,
     pub y: f64,
@@ -16,7 +25,12 @@ impl<const CRS: u64> std::hash::Hash for CoordM<CRS>{
     }
 }
 
-impl<const CRS: u64> CoordM<CRS> {}
+impl<const CRS: u64> CoordM<CRS> {
+    /// The measure as a timestamp, see [`measure::to_datetime`]
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        measure::to_datetime(self.m)
+    }
+}
 
 impl<const CRS: u64> From<(f64, f64, f64)> for CoordM<CRS> {
     fn from((first, second, third): (f64, f64, f64)) -> Self {
@@ -28,6 +42,22 @@ impl<const CRS: u64> From<(f64, f64, f64)> for CoordM<CRS> {
     }
 }
 
+impl<const CRS: u64> From<(f64, f64, DateTime<Utc>)> for CoordM<CRS> {
+    fn from((first, second, time): (f64, f64, DateTime<Utc>)) -> Self {
+        CoordM {
+            x: first,
+            y: second,
+            m: measure::from_datetime(time),
+        }
+    }
+}
+
+impl<const CRS: u64> From<CoordM<CRS>> for (f64, f64, f64) {
+    fn from(value: CoordM<CRS>) -> Self {
+        (value.x, value.y, value.m)
+    }
+}
+
 impl<const CRS: u64> TryFrom<wkb::reader::Wkb<'_>> for CoordM<CRS> {
     type Error = super::error::Error;
 
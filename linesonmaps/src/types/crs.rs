@@ -0,0 +1,470 @@
+//! Compile-time information about the coordinate reference systems used as the `CRS` parameter of the geometry types.
+//!
+//! Every supported EPSG code has an implementation of [`Crs`] on [`Epsg`], which carries the unit and axis order of its coordinates,
+//! and how to convert them to and from WGS84. Code that depends on the unit bounds on it, e.g.
+//!
+//! ```compile_fail
+//! use geo::{Distance, Euclidean};
+//! use linesonmaps::types::pointm::PointM;
+//!
+//! // euclidean distances between degrees are meaningless
+//! let a = PointM::<4326>::from((10., 56., 0.));
+//! Euclidean.distance(a, a);
+//! ```
+//!
+//! ```compile_fail
+//! use geo::{Distance, Geodesic};
+//! use linesonmaps::types::pointm::PointM;
+//!
+//! // geodesic distances between meters are meaningless
+//! let a = PointM::<3857>::from((1000., 2000., 0.));
+//! Geodesic.distance(a, a);
+//! ```
+//!
+//! Distances that follow the unit, e.g. [`PointM::distance_m`], are only available for supported CRSs
+//!
+//! ```compile_fail
+//! use linesonmaps::types::pointm::PointM;
+//!
+//! // the unit of an unknown CRS is unknown as well
+//! let a = PointM::<1234>::from((1000., 2000., 0.));
+//! a.distance_m(&a);
+//! ```
+//!
+//! ```compile_fail
+//! use linesonmaps::types::linem::interpolation;
+//! use linesonmaps::types::linestringm::LineStringM;
+//!
+//! // there is no geodesic between meters
+//! let ls = LineStringM::<3857>::new(vec![(0., 0., 0.).into(), (10., 0., 10.).into()]).unwrap();
+//! ls.locate_along(5., interpolation::Geodesic);
+//! ```
+
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use geo::{Bearing, Coord, Distance, Euclidean, Geodesic, InterpolatePoint, Point};
+
+use crate::types::consts::METERS_PER_DEGREE;
+use crate::types::coordm::CoordM;
+use crate::types::linem::LineM;
+use crate::types::linestringm::LineStringM;
+use crate::types::multilinestringm::MultiLineStringM;
+use crate::types::pointm::PointM;
+
+/// EPSG code of WGS84 longitude/latitude
+pub const WGS84: u64 = 4326;
+/// EPSG code of (pseudo) Web Mercator
+pub const WEB_MERCATOR: u64 = 3857;
+
+/// EPSG code of the WGS84 UTM `zone` (1 to 60) on the northern or southern hemisphere
+pub const fn utm(zone: u8, north: bool) -> u64 {
+    assert!(zone >= 1 && zone <= 60, "UTM zones range from 1 to 60");
+    match north {
+        true => 32600 + zone as u64,
+        false => 32700 + zone as u64,
+    }
+}
+
+/// Unit of the coordinates of a [`Crs`], and how to measure in it
+pub trait Unit {
+    /// Distance in meters between two points
+    fn distance(a: Point, b: Point) -> f64;
+
+    /// Bearing in degrees (North: 0°, East: 90°) from `a` towards `b`
+    fn bearing(a: Point, b: Point) -> f64;
+
+    /// The point `ratio` (0 to 1) of the way from `a` to `b`, see [`Native`](crate::types::linem::interpolation::Native)
+    fn point_at_ratio(a: Point, b: Point, ratio: f64) -> Point;
+
+    /// Meters per unit along `x` and `y` close to `at`
+    fn scale(at: Coord) -> Coord;
+}
+
+/// Coordinates are angles in degrees
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degree {}
+
+/// Coordinates are distances in meters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Meter {}
+
+/// Measured along the WGS84 geodesic, scales are those of the equirectangular projection
+impl Unit for Degree {
+    fn distance(a: Point, b: Point) -> f64 {
+        Geodesic.distance(a, b)
+    }
+
+    fn bearing(a: Point, b: Point) -> f64 {
+        Geodesic.bearing(a, b)
+    }
+
+    fn point_at_ratio(a: Point, b: Point, ratio: f64) -> Point {
+        Geodesic.point_at_ratio_between(a, b, ratio)
+    }
+
+    fn scale(at: Coord) -> Coord {
+        Coord::from((
+            at.y.to_radians().cos() * METERS_PER_DEGREE,
+            METERS_PER_DEGREE,
+        ))
+    }
+}
+
+impl Unit for Meter {
+    fn distance(a: Point, b: Point) -> f64 {
+        Euclidean.distance(a, b)
+    }
+
+    fn bearing(a: Point, b: Point) -> f64 {
+        Euclidean.bearing(a, b)
+    }
+
+    fn point_at_ratio(a: Point, b: Point, ratio: f64) -> Point {
+        Euclidean.point_at_ratio_between(a, b, ratio)
+    }
+
+    fn scale(_at: Coord) -> Coord {
+        Coord::from((1., 1.))
+    }
+}
+
+/// Meaning of the `x` and `y` of a coordinate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AxisOrder {
+    /// `x` is the longitude and `y` the latitude, regardless of the axis order of the EPSG definition
+    LonLat,
+    /// `x` is the easting and `y` the northing
+    EastingNorthing,
+}
+
+/// Marker type of the CRS with EPSG code `CODE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Epsg<const CODE: u64>;
+
+pub trait Crs {
+    const CODE: u64;
+    const AXIS_ORDER: AxisOrder;
+    type Unit: Unit;
+
+    /// Converts a coordinate of this CRS to WGS84 (longitude, latitude) in degrees
+    fn to_wgs84(x: f64, y: f64) -> (f64, f64);
+
+    /// Converts WGS84 (longitude, latitude) in degrees to a coordinate of this CRS
+    fn from_wgs84(lon: f64, lat: f64) -> (f64, f64);
+}
+
+impl Crs for Epsg<WGS84> {
+    const CODE: u64 = WGS84;
+    const AXIS_ORDER: AxisOrder = AxisOrder::LonLat;
+    type Unit = Degree;
+
+    fn to_wgs84(x: f64, y: f64) -> (f64, f64) {
+        (x, y)
+    }
+
+    fn from_wgs84(lon: f64, lat: f64) -> (f64, f64) {
+        (lon, lat)
+    }
+}
+
+/// Semi-major axis of the WGS84 ellipsoid in meters
+const WGS84_A: f64 = 6_378_137.;
+/// Flattening of the WGS84 ellipsoid
+const WGS84_F: f64 = 1. / 298.257_223_563;
+
+/// Half the circumference of the web mercator world in meters
+pub const WEB_MERCATOR_EXTENT: f64 = WGS84_A * std::f64::consts::PI;
+
+impl Crs for Epsg<WEB_MERCATOR> {
+    const CODE: u64 = WEB_MERCATOR;
+    const AXIS_ORDER: AxisOrder = AxisOrder::EastingNorthing;
+    type Unit = Meter;
+
+    fn to_wgs84(x: f64, y: f64) -> (f64, f64) {
+        let lon = (x / WGS84_A).to_degrees();
+        let lat = (2. * (y / WGS84_A).exp().atan() - FRAC_PI_2).to_degrees();
+        (lon, lat)
+    }
+
+    fn from_wgs84(lon: f64, lat: f64) -> (f64, f64) {
+        let x = WGS84_A * lon.to_radians();
+        let y = WGS84_A * (FRAC_PI_4 + lat.to_radians() / 2.).tan().ln();
+        (x, y)
+    }
+}
+
+/// Transverse mercator projection of a UTM zone, using the Krüger series up to the fourth order (accurate to well below a millimeter within the zone)
+mod utm {
+    use super::{WGS84_A, WGS84_F};
+
+    const K0: f64 = 0.9996;
+    const FALSE_EASTING: f64 = 500_000.;
+    const FALSE_NORTHING_SOUTH: f64 = 10_000_000.;
+
+    struct Series {
+        /// Rectifying radius
+        a: f64,
+        alpha: [f64; 4],
+        beta: [f64; 4],
+        delta: [f64; 4],
+        /// 2√n / (1 + n)
+        e: f64,
+    }
+
+    fn series() -> Series {
+        let n = WGS84_F / (2. - WGS84_F);
+        let (n2, n3, n4) = (n.powi(2), n.powi(3), n.powi(4));
+        Series {
+            a: WGS84_A / (1. + n) * (1. + n2 / 4. + n4 / 64.),
+            alpha: [
+                n / 2. - 2. * n2 / 3. + 5. * n3 / 16. + 41. * n4 / 180.,
+                13. * n2 / 48. - 3. * n3 / 5. + 557. * n4 / 1440.,
+                61. * n3 / 240. - 103. * n4 / 140.,
+                49561. * n4 / 161280.,
+            ],
+            beta: [
+                n / 2. - 2. * n2 / 3. + 37. * n3 / 96. - n4 / 360.,
+                n2 / 48. + n3 / 15. - 437. * n4 / 1440.,
+                17. * n3 / 480. - 37. * n4 / 840.,
+                4397. * n4 / 161280.,
+            ],
+            delta: [
+                2. * n - 2. * n2 / 3. - 2. * n3 + 116. * n4 / 45.,
+                7. * n2 / 3. - 8. * n3 / 5. - 227. * n4 / 45.,
+                56. * n3 / 15. - 136. * n4 / 35.,
+                4279. * n4 / 630.,
+            ],
+            e: 2. * n.sqrt() / (1. + n),
+        }
+    }
+
+    fn central_meridian(zone: u64) -> f64 {
+        (zone as f64 * 6. - 183.).to_radians()
+    }
+
+    pub(super) fn forward(zone: u64, north: bool, lon: f64, lat: f64) -> (f64, f64) {
+        let s = series();
+        let (phi, lambda) = (lat.to_radians(), lon.to_radians() - central_meridian(zone));
+
+        let t = (phi.sin().atanh() - s.e * (s.e * phi.sin()).atanh()).sinh();
+        let xi = t.atan2(lambda.cos());
+        let eta = (lambda.sin() / (1. + t * t).sqrt()).atanh();
+
+        let (mut easting, mut northing) = (eta, xi);
+        for (j, alpha) in s.alpha.iter().enumerate() {
+            let k = 2. * (j + 1) as f64;
+            easting += alpha * (k * xi).cos() * (k * eta).sinh();
+            northing += alpha * (k * xi).sin() * (k * eta).cosh();
+        }
+
+        let false_northing = if north { 0. } else { FALSE_NORTHING_SOUTH };
+        (
+            FALSE_EASTING + K0 * s.a * easting,
+            false_northing + K0 * s.a * northing,
+        )
+    }
+
+    pub(super) fn inverse(zone: u64, north: bool, x: f64, y: f64) -> (f64, f64) {
+        let s = series();
+        let false_northing = if north { 0. } else { FALSE_NORTHING_SOUTH };
+        let xi = (y - false_northing) / (K0 * s.a);
+        let eta = (x - FALSE_EASTING) / (K0 * s.a);
+
+        let (mut xi_p, mut eta_p) = (xi, eta);
+        for (j, beta) in s.beta.iter().enumerate() {
+            let k = 2. * (j + 1) as f64;
+            xi_p -= beta * (k * xi).sin() * (k * eta).cosh();
+            eta_p -= beta * (k * xi).cos() * (k * eta).sinh();
+        }
+
+        let chi = (xi_p.sin() / eta_p.cosh()).asin();
+        let phi = chi
+            + s.delta
+                .iter()
+                .enumerate()
+                .map(|(j, delta)| delta * (2. * (j + 1) as f64 * chi).sin())
+                .sum::<f64>();
+        let lambda = central_meridian(zone) + eta_p.sinh().atan2(xi_p.cos());
+
+        (lambda.to_degrees(), phi.to_degrees())
+    }
+}
+
+macro_rules! utm_zones {
+    ($($zone:literal)*) => {
+        $(
+            impl Crs for Epsg<{ utm($zone, true) }> {
+                const CODE: u64 = utm($zone, true);
+                const AXIS_ORDER: AxisOrder = AxisOrder::EastingNorthing;
+                type Unit = Meter;
+
+                fn to_wgs84(x: f64, y: f64) -> (f64, f64) {
+                    utm::inverse($zone, true, x, y)
+                }
+
+                fn from_wgs84(lon: f64, lat: f64) -> (f64, f64) {
+                    utm::forward($zone, true, lon, lat)
+                }
+            }
+
+            impl Crs for Epsg<{ utm($zone, false) }> {
+                const CODE: u64 = utm($zone, false);
+                const AXIS_ORDER: AxisOrder = AxisOrder::EastingNorthing;
+                type Unit = Meter;
+
+                fn to_wgs84(x: f64, y: f64) -> (f64, f64) {
+                    utm::inverse($zone, false, x, y)
+                }
+
+                fn from_wgs84(lon: f64, lat: f64) -> (f64, f64) {
+                    utm::forward($zone, false, lon, lat)
+                }
+            }
+        )*
+    };
+}
+
+utm_zones!(
+    1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20
+    21 22 23 24 25 26 27 28 29 30 31 32 33 34 35 36 37 38 39 40
+    41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60
+);
+
+impl<const CRS: u64> CoordM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    /// Converts the coordinate to the CRS `TO`, the measure is left untouched
+    pub fn reproject<const TO: u64>(&self) -> CoordM<TO>
+    where
+        Epsg<TO>: Crs,
+    {
+        let (lon, lat) = <Epsg<CRS> as Crs>::to_wgs84(self.x, self.y);
+        let (x, y) = <Epsg<TO> as Crs>::from_wgs84(lon, lat);
+        CoordM { x, y, m: self.m }
+    }
+}
+
+impl<const CRS: u64> PointM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    /// See [`CoordM::reproject`]
+    pub fn reproject<const TO: u64>(&self) -> PointM<TO>
+    where
+        Epsg<TO>: Crs,
+    {
+        PointM {
+            coord: self.coord.reproject(),
+        }
+    }
+}
+
+impl<const CRS: u64> LineM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    /// See [`CoordM::reproject`]
+    pub fn reproject<const TO: u64>(&self) -> LineM<TO>
+    where
+        Epsg<TO>: Crs,
+    {
+        LineM {
+            from: self.from.reproject(),
+            to: self.to.reproject(),
+        }
+    }
+}
+
+impl<const CRS: u64> LineStringM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    /// See [`CoordM::reproject`]
+    pub fn reproject<const TO: u64>(&self) -> LineStringM<TO>
+    where
+        Epsg<TO>: Crs,
+    {
+        LineStringM(self.0.iter().map(|c| c.reproject()).collect())
+    }
+}
+
+impl<const CRS: u64> MultiLineStringM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    /// See [`CoordM::reproject`]
+    pub fn reproject<const TO: u64>(&self) -> MultiLineStringM<TO>
+    where
+        Epsg<TO>: Crs,
+    {
+        MultiLineStringM(self.0.iter().map(|ls| ls.reproject()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Distance, Euclidean, Geodesic};
+    use pretty_assertions::assert_eq;
+
+    const UTM_32N: u64 = utm(32, true);
+
+    fn close(a: (f64, f64), b: (f64, f64), tolerance: f64) -> bool {
+        (a.0 - b.0).abs() < tolerance && (a.1 - b.1).abs() < tolerance
+    }
+
+    #[test]
+    fn epsg_codes() {
+        assert_eq!(UTM_32N, 32632);
+        assert_eq!(utm(1, false), 32701);
+        assert_eq!(
+            <Epsg<UTM_32N> as Crs>::AXIS_ORDER,
+            AxisOrder::EastingNorthing
+        );
+    }
+
+    #[test]
+    fn web_mercator() {
+        let c: CoordM<WGS84> = (180., 0., 42.).into();
+        let projected = c.reproject::<WEB_MERCATOR>();
+        assert!(close(
+            (projected.x, projected.y),
+            (WEB_MERCATOR_EXTENT, 0.),
+            1e-6
+        ));
+        assert_eq!(projected.m, 42.);
+
+        let c: CoordM<WGS84> = (10.5, 56.2, 0.).into();
+        let back = c.reproject::<WEB_MERCATOR>().reproject::<WGS84>();
+        assert!(close((back.x, back.y), (c.x, c.y), 1e-9));
+    }
+
+    #[test]
+    fn utm_zone() {
+        // the central meridian of zone 32 on the equator
+        let c: CoordM<WGS84> = (9., 0., 0.).into();
+        let projected = c.reproject::<UTM_32N>();
+        assert!(close((projected.x, projected.y), (500_000., 0.), 1e-6));
+
+        let south = c.reproject::<{ utm(32, false) }>();
+        assert!(close((south.x, south.y), (500_000., 10_000_000.), 1e-6));
+
+        let c: CoordM<WGS84> = (10.5, 56.2, 0.).into();
+        let back = c.reproject::<UTM_32N>().reproject::<WGS84>();
+        assert!(close((back.x, back.y), (c.x, c.y), 1e-9), "{back:?}");
+    }
+
+    #[test]
+    fn utm_preserves_distances() {
+        let ls: LineStringM<WGS84> =
+            LineStringM::new(vec![(10., 56., 0.).into(), (10.01, 56.01, 60.).into()]).unwrap();
+        let projected = ls.reproject::<UTM_32N>();
+
+        let geodesic = Geodesic.distance(PointM::from(ls.0[0]), PointM::from(ls.0[1]));
+        let euclidean =
+            Euclidean.distance(PointM::from(projected.0[0]), PointM::from(projected.0[1]));
+        // the scale factor of UTM is within 0.1% of 1 inside a zone
+        assert!((geodesic - euclidean).abs() / geodesic < 1e-3);
+    }
+}
@@ -8,10 +8,20 @@ pub enum Error {
     NumPoints,
     #[error("Linestring points must temporally ordered")]
     Timestamp,
+    #[error("measure value out of range of DateTime")]
+    MeasureRange,
     #[error("tried to convert to wrong geometry sub-type")]
     IncompatibleType,
     #[error("Geometry unexpectedly empty")]
     Empty,
     #[error("tried to read from a non-existent dimension")]
     Dimension,
+    #[error("expected SRID {expected}, found {found:?}")]
+    Srid { expected: u64, found: Option<u32> },
+    #[error("invalid WKB: {0}")]
+    Wkb(String),
+    #[error("invalid WKT: {0}")]
+    Wkt(String),
+    #[error("invalid encoding: {0}")]
+    Codec(String),
 }
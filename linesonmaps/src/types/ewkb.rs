@@ -0,0 +1,283 @@
+//! Extended WKB (as used by PostGIS) with the SRID embedded, such that geometries can be exchanged with the database without `st_setsrid`/`st_geomfromwkb(.., srid)`.
+//!
+//! The SRID written is the `CRS` of the geometry, and reading fails if the embedded SRID does not match the `CRS` the geometry is read as.
+//! Output is always little endian, input may be either.
+
+use geo::{Coord, LineString, Polygon};
+use geo_traits::{CoordTrait, GeometryTrait, GeometryType, LineStringTrait, PolygonTrait};
+use wkb::reader::{Wkb, read_wkb};
+
+use crate::types::coordm::CoordM;
+use crate::types::error::Error;
+use crate::types::linem::LineM;
+use crate::types::linestringm::LineStringM;
+use crate::types::multilinestringm::MultiLineStringM;
+use crate::types::pointm::PointM;
+
+const POINT: u32 = 1;
+const LINESTRING: u32 = 2;
+const POLYGON: u32 = 3;
+const MULTILINESTRING: u32 = 5;
+
+const FLAG_M: u32 = 0x4000_0000;
+const FLAG_SRID: u32 = 0x2000_0000;
+
+pub trait ToEwkb {
+    /// Encodes the geometry as EWKB, with its `CRS` as SRID
+    fn to_ewkb(&self) -> Vec<u8>;
+}
+
+pub trait FromEwkb: Sized {
+    /// Decodes EWKB, failing with [`Error::Srid`] if the embedded SRID is missing or differs from the `CRS` of `Self`
+    fn from_ewkb(buf: &[u8]) -> Result<Self, Error>;
+}
+
+fn srid<const CRS: u64>() -> u32 {
+    u32::try_from(CRS).expect("EPSG codes fit in 32 bits")
+}
+
+/// Writes the header of a (sub) geometry, only the outermost geometry carries the SRID
+fn header(buf: &mut Vec<u8>, kind: u32, has_m: bool, srid: Option<u32>) {
+    let mut code = kind;
+    if has_m {
+        code |= FLAG_M;
+    }
+    if srid.is_some() {
+        code |= FLAG_SRID;
+    }
+    buf.push(1);
+    buf.extend(code.to_le_bytes());
+    if let Some(srid) = srid {
+        buf.extend(srid.to_le_bytes());
+    }
+}
+
+fn count(buf: &mut Vec<u8>, n: usize) {
+    buf.extend(
+        u32::try_from(n)
+            .expect("too many elements for WKB")
+            .to_le_bytes(),
+    );
+}
+
+fn coords_m<const CRS: u64>(buf: &mut Vec<u8>, coords: &[CoordM<CRS>]) {
+    count(buf, coords.len());
+    for c in coords {
+        buf.extend(c.x.to_le_bytes());
+        buf.extend(c.y.to_le_bytes());
+        buf.extend(c.m.to_le_bytes());
+    }
+}
+
+fn coords_xy(buf: &mut Vec<u8>, ls: &LineString) {
+    count(buf, ls.0.len());
+    for c in ls.coords() {
+        buf.extend(c.x.to_le_bytes());
+        buf.extend(c.y.to_le_bytes());
+    }
+}
+
+/// Reads the SRID embedded in the header of `buf` and checks it against `CRS`
+fn check_srid<const CRS: u64>(buf: &[u8]) -> Result<(), Error> {
+    let (&order, rest) = buf.split_first().ok_or(Error::Empty)?;
+    let word = |bytes: &[u8]| -> Option<u32> {
+        let bytes: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+        Some(match order {
+            0 => u32::from_be_bytes(bytes),
+            _ => u32::from_le_bytes(bytes),
+        })
+    };
+    let code = word(rest).ok_or(Error::Empty)?;
+    let found = match code & FLAG_SRID {
+        0 => None,
+        _ => Some(word(&rest[4..]).ok_or(Error::Empty)?),
+    };
+
+    if found.map(u64::from) == Some(CRS) {
+        Ok(())
+    } else {
+        Err(Error::Srid {
+            expected: CRS,
+            found,
+        })
+    }
+}
+
+fn read<const CRS: u64>(buf: &[u8]) -> Result<Wkb<'_>, Error> {
+    check_srid::<CRS>(buf)?;
+    read_wkb(buf).map_err(|e| Error::Wkb(e.to_string()))
+}
+
+impl<const CRS: u64> ToEwkb for PointM<CRS> {
+    fn to_ewkb(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        header(&mut buf, POINT, true, Some(srid::<CRS>()));
+        buf.extend(self.coord.x.to_le_bytes());
+        buf.extend(self.coord.y.to_le_bytes());
+        buf.extend(self.coord.m.to_le_bytes());
+        buf
+    }
+}
+
+impl<const CRS: u64> FromEwkb for PointM<CRS> {
+    fn from_ewkb(buf: &[u8]) -> Result<Self, Error> {
+        CoordM::try_from(read::<CRS>(buf)?).map(PointM::from)
+    }
+}
+
+/// A line is written as a linestring of two points
+impl<const CRS: u64> ToEwkb for LineM<CRS> {
+    fn to_ewkb(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        header(&mut buf, LINESTRING, true, Some(srid::<CRS>()));
+        coords_m(&mut buf, &[self.from.coord, self.to.coord]);
+        buf
+    }
+}
+
+impl<const CRS: u64> FromEwkb for LineM<CRS> {
+    fn from_ewkb(buf: &[u8]) -> Result<Self, Error> {
+        match LineStringM::<CRS>::from_ewkb(buf)?.0.as_slice() {
+            [from, to] => Ok(LineM::from((*from, *to))),
+            _ => Err(Error::NumPoints),
+        }
+    }
+}
+
+impl<const CRS: u64> ToEwkb for LineStringM<CRS> {
+    fn to_ewkb(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        header(&mut buf, LINESTRING, true, Some(srid::<CRS>()));
+        coords_m(&mut buf, &self.0);
+        buf
+    }
+}
+
+impl<const CRS: u64> FromEwkb for LineStringM<CRS> {
+    fn from_ewkb(buf: &[u8]) -> Result<Self, Error> {
+        LineStringM::try_from(read::<CRS>(buf)?)
+    }
+}
+
+impl<const CRS: u64> ToEwkb for MultiLineStringM<CRS> {
+    fn to_ewkb(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        header(&mut buf, MULTILINESTRING, true, Some(srid::<CRS>()));
+        count(&mut buf, self.0.len());
+        for ls in &self.0 {
+            header(&mut buf, LINESTRING, true, None);
+            coords_m(&mut buf, &ls.0);
+        }
+        buf
+    }
+}
+
+impl<const CRS: u64> FromEwkb for MultiLineStringM<CRS> {
+    fn from_ewkb(buf: &[u8]) -> Result<Self, Error> {
+        MultiLineStringM::try_from(read::<CRS>(buf)?)
+    }
+}
+
+/// Encodes a (stop) polygon as EWKB with SRID `CRS`, the polygon itself carries no measures.
+pub fn polygon_to_ewkb<const CRS: u64>(polygon: &Polygon) -> Vec<u8> {
+    let mut buf = vec![];
+    header(&mut buf, POLYGON, false, Some(srid::<CRS>()));
+    count(&mut buf, 1 + polygon.interiors().len());
+    coords_xy(&mut buf, polygon.exterior());
+    for ring in polygon.interiors() {
+        coords_xy(&mut buf, ring);
+    }
+    buf
+}
+
+/// Decodes a (stop) polygon from EWKB, see [`FromEwkb::from_ewkb`].
+pub fn polygon_from_ewkb<const CRS: u64>(buf: &[u8]) -> Result<Polygon, Error> {
+    let wkb = read::<CRS>(buf)?;
+    let GeometryType::Polygon(polygon) = wkb.as_type() else {
+        return Err(Error::IncompatibleType);
+    };
+    let exterior = polygon.exterior().map(|r| ring(&r)).ok_or(Error::Empty)?;
+    let interiors = polygon.interiors().map(|r| ring(&r)).collect();
+    Ok(Polygon::new(exterior, interiors))
+}
+
+fn ring(r: &impl LineStringTrait<T = f64>) -> LineString {
+    r.coords().map(|c| Coord { x: c.x(), y: c.y() }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::polygon;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn roundtrip() {
+        let p = PointM::<4326>::from((10., 56., 1000.));
+        assert_eq!(PointM::from_ewkb(&p.to_ewkb()), Ok(p));
+
+        let ls = LineStringM::<4326>::new(vec![(10., 56., 0.).into(), (10.1, 56.1, 60.).into()])
+            .unwrap();
+        assert_eq!(LineStringM::from_ewkb(&ls.to_ewkb()), Ok(ls.clone()));
+
+        let line = ls.lines().next().unwrap();
+        assert_eq!(LineM::from_ewkb(&line.to_ewkb()), Ok(line));
+
+        let mls = MultiLineStringM(vec![ls.clone(), ls]);
+        assert_eq!(MultiLineStringM::from_ewkb(&mls.to_ewkb()), Ok(mls));
+
+        let stop =
+            polygon![(x: 10., y: 56.), (x: 10.1, y: 56.), (x: 10., y: 56.1), (x: 10., y: 56.)];
+        assert_eq!(
+            polygon_from_ewkb::<4326>(&polygon_to_ewkb::<4326>(&stop)),
+            Ok(stop)
+        );
+    }
+
+    #[test]
+    fn postgis_ewkb() {
+        // select st_asewkb('SRID=4326;POINT M (10 56 1000)'::geometry, 'NDR')
+        let ewkb =
+            hex::decode("0101000060E610000000000000000024400000000000004C400000000000408F40")
+                .unwrap();
+        let p = PointM::<4326>::from_ewkb(&ewkb).unwrap();
+        assert_eq!(p, PointM::from((10., 56., 1000.)));
+        assert_eq!(p.to_ewkb(), ewkb);
+
+        // same point in big endian
+        let ewkb =
+            hex::decode("0060000001000010E64024000000000000404C000000000000408F400000000000")
+                .unwrap();
+        assert_eq!(PointM::<4326>::from_ewkb(&ewkb), Ok(p));
+    }
+
+    #[test]
+    fn srid_mismatch() {
+        let p = PointM::<4326>::from((10., 56., 1000.));
+        assert_eq!(
+            PointM::<3857>::from_ewkb(&p.to_ewkb()),
+            Err(Error::Srid {
+                expected: 3857,
+                found: Some(4326)
+            })
+        );
+
+        // plain ISO WKB has no SRID
+        let mut wkb = vec![];
+        wkb::writer::write_point(
+            &mut wkb,
+            &p,
+            &wkb::writer::WriteOptions {
+                endianness: wkb::Endianness::LittleEndian,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            PointM::<4326>::from_ewkb(&wkb),
+            Err(Error::Srid {
+                expected: 4326,
+                found: None
+            })
+        );
+    }
+}
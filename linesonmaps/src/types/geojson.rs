@@ -0,0 +1,319 @@
+//! GeoJSON and OGC Moving Features JSON (MF-JSON) output, such that trajectories and stops can be opened directly in QGIS or web viewers.
+//!
+//! Both formats are always in WGS84 longitude/latitude, so geometries are reprojected from their `CRS` when written.
+//! Measures are written as ISO 8601 timestamps: GeoJSON has no place for them in the geometry, so they go into the `datetimes` property
+//! (the MF-JSON trajectory encoding), while MF-JSON features carry them in their `temporalGeometry` (the MF-JSON prism encoding).
+
+use std::fmt::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use geo::{LineString, Polygon};
+
+use crate::types::coordm::CoordM;
+use crate::types::crs::{Crs, Epsg};
+use crate::types::linestringm::LineStringM;
+use crate::types::measure;
+use crate::types::multilinestringm::MultiLineStringM;
+use crate::types::pointm::PointM;
+
+pub trait ToGeoJson {
+    /// Encodes the geometry as a GeoJSON `Feature`
+    fn to_geojson(&self) -> String;
+}
+
+pub trait ToMfJson {
+    /// Encodes the geometry as a MF-JSON `Feature` with a `temporalGeometry`
+    fn to_mf_json(&self) -> String;
+}
+
+/// Wraps already encoded (GeoJSON or MF-JSON) features in a `FeatureCollection`
+pub fn feature_collection(features: impl IntoIterator<Item = String>) -> String {
+    format!(
+        r#"{{"type":"FeatureCollection","features":[{}]}}"#,
+        features.into_iter().collect::<Vec<_>>().join(",")
+    )
+}
+
+/// A quoted ISO 8601 timestamp
+pub(crate) fn datetime(t: DateTime<Utc>) -> String {
+    format!("\"{}\"", t.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+/// A quoted JSON string
+pub(crate) fn string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// A measure as quoted timestamp, or as the raw number if it is outside the range of [`DateTime`] (`null` if not finite)
+fn measure(m: f64) -> String {
+    match measure::to_datetime(m) {
+        Some(t) => datetime(t),
+        None if m.is_finite() => m.to_string(),
+        None => "null".to_string(),
+    }
+}
+
+fn list(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<_>>().join(","))
+}
+
+fn position<const CRS: u64>(x: f64, y: f64) -> String
+where
+    Epsg<CRS>: Crs,
+{
+    let (lon, lat) = <Epsg<CRS> as Crs>::to_wgs84(x, y);
+    format!("[{lon},{lat}]")
+}
+
+fn positions<const CRS: u64>(coords: &[CoordM<CRS>]) -> String
+where
+    Epsg<CRS>: Crs,
+{
+    list(coords.iter().map(|c| position::<CRS>(c.x, c.y)))
+}
+
+fn datetimes<const CRS: u64>(coords: &[CoordM<CRS>]) -> String {
+    list(coords.iter().map(|c| measure(c.m)))
+}
+
+fn ring<const CRS: u64>(ring: &LineString) -> String
+where
+    Epsg<CRS>: Crs,
+{
+    list(ring.coords().map(|c| position::<CRS>(c.x, c.y)))
+}
+
+/// A GeoJSON polygon geometry of a (stop) polygon in `CRS`
+pub(crate) fn polygon_geometry<const CRS: u64>(polygon: &Polygon) -> String
+where
+    Epsg<CRS>: Crs,
+{
+    let rings = std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .map(ring::<CRS>);
+    format!(r#"{{"type":"Polygon","coordinates":{}}}"#, list(rings))
+}
+
+pub(crate) fn feature(geometry: &str, properties: &str) -> String {
+    format!(r#"{{"type":"Feature","geometry":{geometry},"properties":{properties}}}"#)
+}
+
+/// A MF-JSON feature, `time` is the (quoted) start and end of the feature
+pub(crate) fn mf_feature(
+    geometry: &str,
+    temporal_geometry: Option<&str>,
+    time: Option<(String, String)>,
+) -> String {
+    let mut json = format!(r#"{{"type":"Feature","geometry":{geometry}"#);
+    if let Some(temporal_geometry) = temporal_geometry {
+        let _ = write!(json, r#","temporalGeometry":{temporal_geometry}"#);
+    }
+    if let Some((start, end)) = time {
+        let _ = write!(json, r#","time":[{start},{end}]"#);
+    }
+    json.push_str(r#","properties":{}}"#);
+    json
+}
+
+fn moving_point<const CRS: u64>(coords: &[CoordM<CRS>], interpolation: &str) -> String
+where
+    Epsg<CRS>: Crs,
+{
+    format!(
+        r#"{{"type":"MovingPoint","datetimes":{},"coordinates":{},"interpolation":"{interpolation}"}}"#,
+        datetimes(coords),
+        positions(coords)
+    )
+}
+
+fn time_range<const CRS: u64>(coords: &[CoordM<CRS>]) -> Option<(String, String)> {
+    Some((measure(coords.first()?.m), measure(coords.last()?.m)))
+}
+
+impl<const CRS: u64> ToGeoJson for PointM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_geojson(&self) -> String {
+        feature(
+            &format!(
+                r#"{{"type":"Point","coordinates":{}}}"#,
+                position::<CRS>(self.coord.x, self.coord.y)
+            ),
+            &format!(r#"{{"datetime":{}}}"#, measure(self.coord.m)),
+        )
+    }
+}
+
+impl<const CRS: u64> ToMfJson for PointM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_mf_json(&self) -> String {
+        mf_feature(
+            &format!(
+                r#"{{"type":"Point","coordinates":{}}}"#,
+                position::<CRS>(self.coord.x, self.coord.y)
+            ),
+            Some(&moving_point(&[self.coord], "Discrete")),
+            time_range(&[self.coord]),
+        )
+    }
+}
+
+impl<const CRS: u64> ToGeoJson for LineStringM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_geojson(&self) -> String {
+        feature(
+            &format!(
+                r#"{{"type":"LineString","coordinates":{}}}"#,
+                positions(&self.0)
+            ),
+            &format!(r#"{{"datetimes":{}}}"#, datetimes(&self.0)),
+        )
+    }
+}
+
+impl<const CRS: u64> ToMfJson for LineStringM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_mf_json(&self) -> String {
+        mf_feature(
+            &format!(
+                r#"{{"type":"LineString","coordinates":{}}}"#,
+                positions(&self.0)
+            ),
+            Some(&moving_point(&self.0, "Linear")),
+            time_range(&self.0),
+        )
+    }
+}
+
+impl<const CRS: u64> ToGeoJson for MultiLineStringM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_geojson(&self) -> String {
+        feature(
+            &format!(
+                r#"{{"type":"MultiLineString","coordinates":{}}}"#,
+                list(self.0.iter().map(|ls| positions(&ls.0)))
+            ),
+            &format!(
+                r#"{{"datetimes":{}}}"#,
+                list(self.0.iter().map(|ls| datetimes(&ls.0)))
+            ),
+        )
+    }
+}
+
+/// Each linestring is a prism of a `MovingGeometryCollection`
+impl<const CRS: u64> ToMfJson for MultiLineStringM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    fn to_mf_json(&self) -> String {
+        let prisms = list(self.0.iter().map(|ls| moving_point(&ls.0, "Linear")));
+        let coords = self.0.iter().flat_map(|ls| &ls.0);
+        let start = coords.clone().map(|c| c.m).min_by(f64::total_cmp);
+        let end = coords.map(|c| c.m).max_by(f64::total_cmp);
+
+        mf_feature(
+            &format!(
+                r#"{{"type":"MultiLineString","coordinates":{}}}"#,
+                list(self.0.iter().map(|ls| positions(&ls.0)))
+            ),
+            Some(&format!(
+                r#"{{"type":"MovingGeometryCollection","prisms":{prisms}}}"#
+            )),
+            start
+                .zip(end)
+                .map(|(start, end)| (measure(start), measure(end))),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn ls() -> LineStringM<4326> {
+        LineStringM::new(vec![
+            (10., 56., 1700000000.).into(),
+            (10.5, 56.5, 1700000060.5).into(),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn geojson() {
+        assert_eq!(
+            ls().to_geojson(),
+            r#"{"type":"Feature","geometry":{"type":"LineString","coordinates":[[10,56],[10.5,56.5]]},"properties":{"datetimes":["2023-11-14T22:13:20Z","2023-11-14T22:14:20.500Z"]}}"#
+        );
+        assert_eq!(
+            PointM::<4326>::from((10., 56., 1700000000.)).to_geojson(),
+            r#"{"type":"Feature","geometry":{"type":"Point","coordinates":[10,56]},"properties":{"datetime":"2023-11-14T22:13:20Z"}}"#
+        );
+        assert_eq!(
+            feature_collection([MultiLineStringM(vec![ls()]).to_geojson()]),
+            r#"{"type":"FeatureCollection","features":[{"type":"Feature","geometry":{"type":"MultiLineString","coordinates":[[[10,56],[10.5,56.5]]]},"properties":{"datetimes":[["2023-11-14T22:13:20Z","2023-11-14T22:14:20.500Z"]]}}]}"#
+        );
+    }
+
+    #[test]
+    fn mf_json() {
+        assert_eq!(
+            ls().to_mf_json(),
+            r#"{"type":"Feature","geometry":{"type":"LineString","coordinates":[[10,56],[10.5,56.5]]},"temporalGeometry":{"type":"MovingPoint","datetimes":["2023-11-14T22:13:20Z","2023-11-14T22:14:20.500Z"],"coordinates":[[10,56],[10.5,56.5]],"interpolation":"Linear"},"time":["2023-11-14T22:13:20Z","2023-11-14T22:14:20.500Z"],"properties":{}}"#
+        );
+        assert_eq!(
+            MultiLineStringM(vec![ls()]).to_mf_json(),
+            r#"{"type":"Feature","geometry":{"type":"MultiLineString","coordinates":[[[10,56],[10.5,56.5]]]},"temporalGeometry":{"type":"MovingGeometryCollection","prisms":[{"type":"MovingPoint","datetimes":["2023-11-14T22:13:20Z","2023-11-14T22:14:20.500Z"],"coordinates":[[10,56],[10.5,56.5]],"interpolation":"Linear"}]},"time":["2023-11-14T22:13:20Z","2023-11-14T22:14:20.500Z"],"properties":{}}"#
+        );
+    }
+
+    #[test]
+    fn measure_out_of_range() {
+        assert_eq!(measure(1700000000.), r#""2023-11-14T22:13:20Z""#);
+        assert_eq!(measure(1e300), 1e300.to_string());
+        assert_eq!(measure(f64::NAN), "null");
+        assert_eq!(
+            PointM::<4326>::from((10., 56., 1e300)).to_geojson(),
+            format!(
+                r#"{{"type":"Feature","geometry":{{"type":"Point","coordinates":[10,56]}},"properties":{{"datetime":{}}}}}"#,
+                1e300
+            )
+        );
+    }
+
+    #[test]
+    fn reprojected_to_wgs84() {
+        let p = PointM::<4326>::from((10., 56., 1700000000.));
+        let json = p.reproject::<3857>().to_geojson();
+        let coords = json
+            .split_once(r#""coordinates":["#)
+            .and_then(|(_, rest)| rest.split_once(']'))
+            .map(|(coords, _)| coords)
+            .unwrap();
+        let (lon, lat) = coords.split_once(',').unwrap();
+        assert!((lon.parse::<f64>().unwrap() - 10.).abs() < 1e-9);
+        assert!((lat.parse::<f64>().unwrap() - 56.).abs() < 1e-9);
+    }
+}
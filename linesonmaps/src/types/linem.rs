@@ -2,14 +2,87 @@ use crate::types::coordm::CoordM;
 use crate::types::linestringm::LineStringM;
 use crate::types::multilinestringm::MultiLineStringM;
 use crate::types::pointm::PointM;
+use geo_types::Point;
 use geo_traits::{
     GeometryTrait, GeometryType, LineTrait, UnimplementedGeometryCollection, UnimplementedMultiPoint, UnimplementedMultiPolygon, UnimplementedPolygon, UnimplementedRect, UnimplementedTriangle
 };
 
 
 #[derive(Debug, Clone, Copy, PartialEq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LineM<const CRS: u64= 4326> {pub from: PointM<CRS>, pub to: PointM<CRS>}
 
+/// How positions in between two vertices are estimated, see the implementations in [`interpolation`]
+pub trait Interpolation<const CRS: u64>: Copy + Default {
+    /// The point `ratio` (0 to 1) of the way from `from` to `to`
+    fn point_at_ratio(&self, from: Point, to: Point, ratio: f64) -> Point;
+}
+
+pub mod interpolation {
+    use super::Interpolation;
+    use crate::types::crs::{Crs, Degree, Epsg, Unit};
+    use geo::{Euclidean, InterpolatePoint};
+    use geo_types::Point;
+
+    /// Straight line in the coordinate space of the CRS
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Linear;
+
+    /// Along the WGS84 geodesic, only available for degree based CRS's
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Geodesic;
+
+    /// The interpolation matching the unit of measure of the CRS, see [`Unit::point_at_ratio`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Native;
+
+    impl<const CRS: u64> Interpolation<CRS> for Linear {
+        fn point_at_ratio(&self, from: Point, to: Point, ratio: f64) -> Point {
+            Euclidean.point_at_ratio_between(from, to, ratio)
+        }
+    }
+
+    impl<const CRS: u64> Interpolation<CRS> for Geodesic
+    where
+        Epsg<CRS>: Crs<Unit = Degree>,
+    {
+        fn point_at_ratio(&self, from: Point, to: Point, ratio: f64) -> Point {
+            geo::Geodesic.point_at_ratio_between(from, to, ratio)
+        }
+    }
+
+    impl<const CRS: u64> Interpolation<CRS> for Native
+    where
+        Epsg<CRS>: Crs,
+    {
+        fn point_at_ratio(&self, from: Point, to: Point, ratio: f64) -> Point {
+            <Epsg<CRS> as Crs>::Unit::point_at_ratio(from, to, ratio)
+        }
+    }
+}
+
+impl<const CRS: u64> LineM<CRS> {
+    /// Returns the position on the line at measure `m`, assuming constant speed between the endpoints.
+    ///
+    /// Returns [`None`] if `m` is outside the measure range of the line.
+    pub fn locate_along(
+        &self,
+        m: f64,
+        interpolation: impl Interpolation<CRS>,
+    ) -> Option<PointM<CRS>> {
+        let (from, to) = (self.from.coord, self.to.coord);
+        if !(from.m..=to.m).contains(&m) {
+            return None;
+        }
+        let delta_m = to.m - from.m;
+        // identical timestamps, so there is no way of telling where in between the ship was
+        let ratio = if delta_m > 0. { (m - from.m) / delta_m } else { 0. };
+
+        let p = interpolation.point_at_ratio(Point::from(self.from), Point::from(self.to), ratio);
+        Some(PointM::from((p.x(), p.y(), m)))
+    }
+}
+
 impl<const CRS:u64> From<(PointM<CRS>,PointM<CRS>)> for LineM<CRS>{
     fn from(value: (PointM<CRS>,PointM<CRS>)) -> Self {
         LineM { from: value.0, to: value.1 }
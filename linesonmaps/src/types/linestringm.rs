@@ -4,12 +4,21 @@ use geo_traits::{
     UnimplementedMultiPolygon, UnimplementedPolygon, UnimplementedRect, UnimplementedTriangle,
 };
 
+use chrono::{DateTime, Utc};
+
 use crate::types::coordm::CoordM;
 use crate::types::error::Error;
-use crate::types::linem::LineM;
+use crate::types::linem::{Interpolation, LineM};
+use crate::types::measure;
 use crate::types::pointm::PointM;
 
+/// Serialized as an array of coordinates, which are validated like [`LineStringM::try_from`] when deserializing
 #[derive(Debug, Clone, PartialEq,Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Vec<CoordM<CRS>>")
+)]
 pub struct LineStringM<const CRS: u64 = 4326>(pub Vec<CoordM<CRS>>);
 
 impl<const CRS: u64> LineStringM<CRS> {
@@ -29,6 +38,61 @@ impl<const CRS: u64> LineStringM<CRS> {
     pub fn lines(&self) -> impl Iterator<Item=LineM<CRS>> + '_ {
         self.0.windows(2).map(|ps| LineM::from((ps[0],ps[1])))
     }
+
+    /// Returns the (interpolated) position at measure `m`.
+    ///
+    /// Returns [`None`] if `m` lies outside the measure range of the linestring.
+    pub fn locate_along(
+        &self,
+        m: f64,
+        interpolation: impl Interpolation<CRS>,
+    ) -> Option<PointM<CRS>> {
+        // first vertex with a measure >= m
+        let idx = self.0.partition_point(|c| c.m < m);
+        match self.0.get(idx) {
+            Some(c) if c.m == m => Some(c.into()),
+            Some(c) if idx > 0 => LineM::from((self.0[idx - 1], *c)).locate_along(m, interpolation),
+            _ => None,
+        }
+    }
+
+    /// Returns the position of the ship at time `t`, see [`LineStringM::locate_along`].
+    pub fn position_at(
+        &self,
+        t: DateTime<Utc>,
+        interpolation: impl Interpolation<CRS>,
+    ) -> Option<PointM<CRS>> {
+        self.locate_along(measure::from_datetime(t), interpolation)
+    }
+
+    /// Returns the part of the linestring between `t_start` and `t_end` (both inclusive).
+    ///
+    /// The interval is clamped to the time range of the linestring, and the boundary vertices are interpolated if they do not coincide with existing vertices.
+    /// Returns [`None`] if the (clamped) interval is empty.
+    pub fn slice_between(
+        &self,
+        t_start: DateTime<Utc>,
+        t_end: DateTime<Utc>,
+        interpolation: impl Interpolation<CRS>,
+    ) -> Option<LineStringM<CRS>> {
+        let (first, last) = (self.0.first()?.m, self.0.last()?.m);
+        let m_start = measure::from_datetime(t_start).max(first);
+        let m_end = measure::from_datetime(t_end).min(last);
+        if m_start >= m_end {
+            return None;
+        }
+
+        let start = self.locate_along(m_start, interpolation)?;
+        let end = self.locate_along(m_end, interpolation)?;
+        let inner = self.0.iter().filter(|c| c.m > m_start && c.m < m_end);
+
+        LineStringM::new(
+            std::iter::once(start.coord)
+                .chain(inner.copied())
+                .chain(std::iter::once(end.coord))
+                .collect(),
+        )
+    }
 }
 
 impl<const CRS: u64> TryFrom<Vec<CoordM<CRS>>> for LineStringM<CRS> {
@@ -36,7 +100,8 @@ impl<const CRS: u64> TryFrom<Vec<CoordM<CRS>>> for LineStringM<CRS> {
 
     fn try_from(value: Vec<CoordM<CRS>>) -> Result<Self, Self::Error> {
         match value.len() {
-            1 => Err(super::error::Error::NumPoints), //TODO verify that points are temporally ordered
+            1 => Err(super::error::Error::NumPoints),
+            _ if !value.iter().map(|c| c.m).is_sorted() => Err(super::error::Error::Timestamp),
             _ => Ok(LineStringM(value)),
         }
     }
@@ -192,8 +257,9 @@ mod tests {
     use wkb::writer::write_line_string;
 
     use crate::types::coordm::CoordM;
-    use crate::types::linem::LineM;
+    use crate::types::linem::{LineM, interpolation};
     use crate::types::linestringm::LineStringM;
+    use chrono::DateTime;
 
     use pretty_assertions::{assert_eq, assert_ne};
 
@@ -276,4 +342,122 @@ mod tests {
         // dbg!(lsm.unwrap());
     }
 
+    #[test]
+    fn position_at_interpolates() {
+        let coords: Vec<CoordM<4326>> = [(1.0, 2.0, 0.0), (2.0, 3.0, 10.0), (4.0, 3.0, 20.0)]
+            .map(|f| f.into())
+            .to_vec();
+        let ls = LineStringM::new(coords).unwrap();
+
+        let t = DateTime::from_timestamp_secs(5).unwrap();
+        let p = ls.position_at(t, interpolation::Linear).unwrap();
+        assert_eq!(p, (1.5, 2.5, 5.0).into());
+
+        // vertices are returned as-is
+        let p = ls.position_at(DateTime::from_timestamp_secs(10).unwrap(), interpolation::Geodesic);
+        assert_eq!(p, Some((2.0, 3.0, 10.0).into()));
+
+        let p = ls.position_at(DateTime::from_timestamp_secs(15).unwrap(), interpolation::Geodesic).unwrap();
+        assert!((p.coord.x - 3.0).abs() < 1e-3 && (p.coord.y - 3.0).abs() < 1e-2);
+
+        assert!(ls.position_at(DateTime::from_timestamp_secs(21).unwrap(), interpolation::Linear).is_none());
+        assert!(ls.locate_along(-1.0, interpolation::Linear).is_none());
+    }
+
+    #[test]
+    fn slice_between_interpolates_boundaries() {
+        let coords: Vec<CoordM<4326>> = [(1.0, 2.0, 0.0), (2.0, 3.0, 10.0), (4.0, 3.0, 20.0)]
+            .map(|f| f.into())
+            .to_vec();
+        let ls = LineStringM::new(coords).unwrap();
+
+        let slice = ls
+            .slice_between(
+                DateTime::from_timestamp_secs(5).unwrap(),
+                DateTime::from_timestamp_secs(15).unwrap(),
+                interpolation::Linear,
+            )
+            .unwrap();
+        let expected: Vec<CoordM<4326>> = [(1.5, 2.5, 5.0), (2.0, 3.0, 10.0), (3.0, 3.0, 15.0)]
+            .map(|f| f.into())
+            .to_vec();
+        assert_eq!(slice.0, expected);
+
+        // clamped to the time range of the linestring
+        let slice = ls
+            .slice_between(
+                DateTime::from_timestamp_secs(-100).unwrap(),
+                DateTime::from_timestamp_secs(100).unwrap(),
+                interpolation::Linear,
+            )
+            .unwrap();
+        assert_eq!(slice, ls);
+
+        assert!(
+            ls.slice_between(
+                DateTime::from_timestamp_secs(30).unwrap(),
+                DateTime::from_timestamp_secs(40).unwrap(),
+                interpolation::Linear,
+            )
+            .is_none()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize() {
+        use serde::Deserialize;
+        use serde::de::IntoDeserializer;
+        use serde::de::value::Error;
+
+        let coords = |ms: [f64; 2]| {
+            IntoDeserializer::<Error>::into_deserializer(ms.map(|m| vec![1.0, 2.0, m]).to_vec())
+        };
+
+        let ls = LineStringM::<4326>::deserialize(coords([0.0, 1.0]));
+        assert_eq!(
+            ls,
+            Ok(LineStringM(vec![(1.0, 2.0, 0.0).into(), (1.0, 2.0, 1.0).into()]))
+        );
+
+        // measures must be ordered
+        let ls = LineStringM::<4326>::deserialize(coords([1.0, 0.0]));
+        assert!(ls.is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use crate::types::coordm::CoordM;
+        use crate::types::linem::LineM;
+        use crate::types::multilinestringm::MultiLineStringM;
+        use crate::types::pointm::PointM;
+
+        let coord = CoordM::<4326>::from((1., 2., 3.));
+        let json = serde_json::to_string(&coord).unwrap();
+        assert_eq!(json, "[1.0,2.0,3.0]");
+        assert_eq!(serde_json::from_str::<CoordM<4326>>(&json).unwrap(), coord);
+
+        let point = PointM::<4326>::from((1., 2., 3.));
+        let json = serde_json::to_string(&point).unwrap();
+        assert_eq!(json, "[1.0,2.0,3.0]");
+        assert_eq!(serde_json::from_str::<PointM<4326>>(&json).unwrap(), point);
+
+        let line = LineM::<4326>::from((coord, CoordM::from((2., 3., 4.))));
+        let json = serde_json::to_string(&line).unwrap();
+        assert_eq!(json, r#"{"from":[1.0,2.0,3.0],"to":[2.0,3.0,4.0]}"#);
+        assert_eq!(serde_json::from_str::<LineM<4326>>(&json).unwrap(), line);
+
+        let ls = LineStringM::<4326>::new(vec![coord, (2., 3., 4.).into()]).unwrap();
+        let mls = MultiLineStringM(vec![ls.clone(), ls]);
+        let json = serde_json::to_string(&mls).unwrap();
+        assert_eq!(
+            json,
+            "[[[1.0,2.0,3.0],[2.0,3.0,4.0]],[[1.0,2.0,3.0],[2.0,3.0,4.0]]]"
+        );
+        assert_eq!(
+            serde_json::from_str::<MultiLineStringM<4326>>(&json).unwrap(),
+            mls
+        );
+    }
 }
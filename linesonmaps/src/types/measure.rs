@@ -0,0 +1,60 @@
+//! Conversion between measures (`m`, epoch seconds as `f64`) and [`chrono`] types.
+//!
+//! An `f64` resolves about a microsecond for present day epoch seconds, so measures are rounded to whole microseconds instead of truncated to whole seconds,
+//! which would collapse high-rate data onto the same timestamp.
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// The timestamp of measure `m`, [`None`] if it is out of the range of [`DateTime`]
+pub fn to_datetime(m: f64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_micros(micros(m)?)
+}
+
+/// The measure of timestamp `t`
+pub fn from_datetime(t: DateTime<Utc>) -> f64 {
+    t.timestamp() as f64 + f64::from(t.timestamp_subsec_nanos()) / 1e9
+}
+
+/// The duration of a measure difference `dm`, saturating at the range of [`TimeDelta`]
+pub fn to_timedelta(dm: f64) -> TimeDelta {
+    TimeDelta::microseconds((dm * 1e6).round() as i64)
+}
+
+fn micros(m: f64) -> Option<i64> {
+    let micros = (m * 1e6).round();
+    (micros.is_finite() && micros.abs() < i64::MAX as f64).then_some(micros as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn sub_second() {
+        let t = to_datetime(1700000000.123).unwrap();
+        assert_eq!(t.timestamp(), 1700000000);
+        // 1700000000.123 is 1700000000.12299990654 as f64, which must not be truncated to 122 ms
+        assert_eq!(t.timestamp_subsec_millis(), 123);
+        assert_eq!(t.timestamp_subsec_micros(), 123000);
+        assert_eq!(from_datetime(t), 1700000000.123);
+
+        // before the epoch
+        let t = to_datetime(-0.5).unwrap();
+        assert_eq!(t.timestamp_millis(), -500);
+        assert_eq!(from_datetime(t), -0.5);
+
+        assert_eq!(to_datetime(f64::NAN), None);
+        assert_eq!(to_datetime(1e20), None);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(to_timedelta(1.5), TimeDelta::milliseconds(1500));
+        assert_eq!(to_timedelta(-0.000_001), TimeDelta::microseconds(-1));
+        assert_eq!(
+            to_datetime(10.25).unwrap() - to_datetime(10.).unwrap(),
+            to_timedelta(0.25)
+        );
+    }
+}
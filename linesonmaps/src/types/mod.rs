@@ -1,7 +1,13 @@
+pub mod codec;
 pub mod coordm;
+pub mod crs;
 pub mod linem;
 pub mod linestringm;
+pub mod measure;
 pub mod multilinestringm;
 pub mod pointm;
 pub mod error;
-mod consts;
\ No newline at end of file
+pub mod ewkb;
+pub mod geojson;
+pub mod wkt;
+pub(crate) mod consts;
\ No newline at end of file
@@ -10,6 +10,11 @@ use geo_traits::{
 };
 
 #[derive(Debug, Clone, PartialEq,Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct MultiLineStringM<const CRS: u64 = 4326>(pub Vec<LineStringM<CRS>>);
 
 impl<const CRS:u64> From<Vec<LineStringM<CRS>>> for MultiLineStringM<CRS> {
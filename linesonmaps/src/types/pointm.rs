@@ -1,4 +1,6 @@
 use crate::types::coordm::CoordM;
+use chrono::{DateTime, Utc};
+use crate::types::crs::{Crs, Degree, Epsg, Meter, Unit};
 use geo::algorithm::Distance;
 use geo::algorithm::GeodesicMeasure;
 use geo::{Euclidean, HaversineMeasure};
@@ -13,11 +15,36 @@ use geographiclib_rs::Geodesic;
 
 ///largely similar to a [`CoordM`], but distinctions are made in libraries, so i am going to as well :)
 #[derive(Debug, Clone, Copy, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct PointM<const CRS: u64 = 4326> {
     pub coord: CoordM<CRS>,
 }
 
-impl<const CRS: u64> PointM<CRS> {}
+impl<const CRS: u64> PointM<CRS> {
+    /// The measure as a timestamp, see [`CoordM::time`]
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        self.coord.time()
+    }
+}
+
+impl<const CRS: u64> PointM<CRS>
+where
+    Epsg<CRS>: Crs,
+{
+    /// Distance in meters to `other`, along the geodesic for degree based CRS's and euclidean for metric ones, see [`Unit::distance`].
+    pub fn distance_m(&self, other: &PointM<CRS>) -> f64 {
+        <Epsg<CRS> as Crs>::Unit::distance(Point::from(*self), Point::from(*other))
+    }
+
+    /// Bearing in degrees (North: 0°, East: 90°) towards `other`, see [`PointM::distance_m`].
+    pub fn bearing_to(&self, other: &PointM<CRS>) -> f64 {
+        <Epsg<CRS> as Crs>::Unit::bearing(Point::from(*self), Point::from(*other))
+    }
+}
 
 impl<const CRS: u64> From<(f64, f64, f64)> for PointM<CRS> {
     fn from((first, second, third): (f64, f64, f64)) -> Self {
@@ -30,6 +57,14 @@ impl<const CRS: u64> From<(f64, f64, f64)> for PointM<CRS> {
         }
     }
 }
+impl<const CRS: u64> From<(f64, f64, DateTime<Utc>)> for PointM<CRS> {
+    fn from(value: (f64, f64, DateTime<Utc>)) -> Self {
+        PointM {
+            coord: value.into(),
+        }
+    }
+}
+
 impl<const CRS: u64> From<CoordM<CRS>> for PointM<CRS> {
     fn from(value: CoordM<CRS>) -> Self {
         PointM { coord: value }
@@ -142,35 +177,29 @@ impl<const CRS: u64> From<PointM<CRS>> for Point {
     }
 }
 
-impl<const CRS: u64> Distance<f64, PointM<CRS>, PointM<CRS>> for GeodesicMeasure<fn() -> Geodesic> {
+impl<const CRS: u64> Distance<f64, PointM<CRS>, PointM<CRS>> for GeodesicMeasure<fn() -> Geodesic>
+where
+    Epsg<CRS>: Crs<Unit = Degree>,
+{
     fn distance(&self, origin: PointM<CRS>, destination: PointM<CRS>) -> f64 {
-        debug_assert!(
-            super::consts::DEGREE_CRS.contains(&CRS),
-            "Given CRS: {0} uses non-degree Uom",
-            CRS
-        );
         self.distance(Point::from(origin), Point::from(destination))
     }
 }
 
-impl<const CRS: u64> Distance<f64, PointM<CRS>, PointM<CRS>> for HaversineMeasure {
+impl<const CRS: u64> Distance<f64, PointM<CRS>, PointM<CRS>> for HaversineMeasure
+where
+    Epsg<CRS>: Crs<Unit = Degree>,
+{
     fn distance(&self, origin: PointM<CRS>, destination: PointM<CRS>) -> f64 {
-        debug_assert!(
-            super::consts::DEGREE_CRS.contains(&CRS),
-            "Given CRS: {0} uses non-degree Uom",
-            CRS
-        );
         self.distance(Point::from(origin), Point::from(destination))
     }
 }
 
-impl<const CRS: u64> Distance<f64, PointM<CRS>, PointM<CRS>> for Euclidean {
+impl<const CRS: u64> Distance<f64, PointM<CRS>, PointM<CRS>> for Euclidean
+where
+    Epsg<CRS>: Crs<Unit = Meter>,
+{
     fn distance(&self, origin: PointM<CRS>, destination: PointM<CRS>) -> f64 {
-        debug_assert!(
-            super::consts::METRIC_CRS.contains(&CRS),
-            "Given CRS: {0} uses non-meter Uom",
-            CRS
-        );
         self.distance(Point::from(origin), Point::from(destination))
     }
 }
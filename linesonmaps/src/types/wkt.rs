@@ -0,0 +1,400 @@
+//! Well-known text with measures, e.g. `LINESTRING M (10 56 1700000000, 10.1 56.1 1700000060)`, mostly to inspect geometries by eye or in QGIS.
+//!
+//! Coordinates are written in the `CRS` of the geometry, without the SRID.
+//! When reading, the `SRID=..;` prefix of PostGIS EWKT is accepted and checked against the `CRS`, and so is the older `LINESTRINGM` spelling.
+
+use std::fmt::Write;
+
+use geo::{LineString, Polygon};
+
+use crate::types::coordm::CoordM;
+use crate::types::error::Error;
+use crate::types::linem::LineM;
+use crate::types::linestringm::LineStringM;
+use crate::types::multilinestringm::MultiLineStringM;
+use crate::types::pointm::PointM;
+
+pub trait ToWkt {
+    /// Encodes the geometry as WKT
+    fn to_wkt(&self) -> String;
+}
+
+pub trait FromWkt: Sized {
+    /// Decodes WKT (or EWKT), failing with [`Error::IncompatibleType`] before looking at the coordinates if the geometry type does not match
+    fn from_wkt(wkt: &str) -> Result<Self, Error>;
+}
+
+fn coords_m<const CRS: u64>(wkt: &mut String, coords: &[CoordM<CRS>]) {
+    if coords.is_empty() {
+        wkt.push_str("EMPTY");
+        return;
+    }
+    wkt.push('(');
+    for (i, c) in coords.iter().enumerate() {
+        if i > 0 {
+            wkt.push_str(", ");
+        }
+        let _ = write!(wkt, "{} {} {}", c.x, c.y, c.m);
+    }
+    wkt.push(')');
+}
+
+fn coords_xy(wkt: &mut String, ls: &LineString) {
+    wkt.push('(');
+    for (i, c) in ls.coords().enumerate() {
+        if i > 0 {
+            wkt.push_str(", ");
+        }
+        let _ = write!(wkt, "{} {}", c.x, c.y);
+    }
+    wkt.push(')');
+}
+
+impl<const CRS: u64> ToWkt for PointM<CRS> {
+    fn to_wkt(&self) -> String {
+        format!(
+            "POINT M ({} {} {})",
+            self.coord.x, self.coord.y, self.coord.m
+        )
+    }
+}
+
+impl<const CRS: u64> FromWkt for PointM<CRS> {
+    fn from_wkt(wkt: &str) -> Result<Self, Error> {
+        let mut parser = Parser::new::<CRS>(wkt, "POINT")?;
+        let coord = match parser.coords::<CRS>()?.as_slice() {
+            [] => Err(Error::Empty),
+            [coord] => Ok(*coord),
+            _ => Err(Error::NumPoints),
+        }?;
+        parser.end()?;
+        Ok(PointM::from(coord))
+    }
+}
+
+/// A line is written as a linestring of two points
+impl<const CRS: u64> ToWkt for LineM<CRS> {
+    fn to_wkt(&self) -> String {
+        let mut wkt = String::from("LINESTRING M ");
+        coords_m(&mut wkt, &[self.from.coord, self.to.coord]);
+        wkt
+    }
+}
+
+impl<const CRS: u64> FromWkt for LineM<CRS> {
+    fn from_wkt(wkt: &str) -> Result<Self, Error> {
+        match LineStringM::<CRS>::from_wkt(wkt)?.0.as_slice() {
+            [from, to] => Ok(LineM::from((*from, *to))),
+            _ => Err(Error::NumPoints),
+        }
+    }
+}
+
+impl<const CRS: u64> ToWkt for LineStringM<CRS> {
+    fn to_wkt(&self) -> String {
+        let mut wkt = String::from("LINESTRING M ");
+        coords_m(&mut wkt, &self.0);
+        wkt
+    }
+}
+
+impl<const CRS: u64> FromWkt for LineStringM<CRS> {
+    fn from_wkt(wkt: &str) -> Result<Self, Error> {
+        let mut parser = Parser::new::<CRS>(wkt, "LINESTRING")?;
+        let ls = linestring(parser.coords()?)?;
+        parser.end()?;
+        Ok(ls)
+    }
+}
+
+impl<const CRS: u64> ToWkt for MultiLineStringM<CRS> {
+    fn to_wkt(&self) -> String {
+        let mut wkt = String::from("MULTILINESTRING M ");
+        if self.0.is_empty() {
+            wkt.push_str("EMPTY");
+            return wkt;
+        }
+        wkt.push('(');
+        for (i, ls) in self.0.iter().enumerate() {
+            if i > 0 {
+                wkt.push_str(", ");
+            }
+            coords_m(&mut wkt, &ls.0);
+        }
+        wkt.push(')');
+        wkt
+    }
+}
+
+impl<const CRS: u64> FromWkt for MultiLineStringM<CRS> {
+    fn from_wkt(wkt: &str) -> Result<Self, Error> {
+        let mut parser = Parser::new::<CRS>(wkt, "MULTILINESTRING")?;
+        let mut lss = vec![];
+        if !parser.empty() {
+            parser.expect('(')?;
+            loop {
+                lss.push(linestring(parser.coords()?)?);
+                if !parser.eat(',') {
+                    break;
+                }
+            }
+            parser.expect(')')?;
+        }
+        parser.end()?;
+        Ok(MultiLineStringM(lss))
+    }
+}
+
+/// Encodes a (stop) polygon as WKT, the polygon itself carries no measures.
+pub fn polygon_to_wkt(polygon: &Polygon) -> String {
+    let mut wkt = String::from("POLYGON ");
+    if polygon.exterior().0.is_empty() {
+        wkt.push_str("EMPTY");
+        return wkt;
+    }
+    wkt.push('(');
+    coords_xy(&mut wkt, polygon.exterior());
+    for ring in polygon.interiors() {
+        wkt.push_str(", ");
+        coords_xy(&mut wkt, ring);
+    }
+    wkt.push(')');
+    wkt
+}
+
+fn linestring<const CRS: u64>(coords: Vec<CoordM<CRS>>) -> Result<LineStringM<CRS>, Error> {
+    if coords.len() == 1 {
+        return Err(Error::NumPoints);
+    }
+    LineStringM::new(coords).ok_or(Error::Timestamp)
+}
+
+/// A minimal recursive descent parser for the (measured) subset of WKT that is used here
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    /// Consumes the optional SRID and the geometry tag, which must be `kind` with measures
+    fn new<const CRS: u64>(wkt: &'a str, kind: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            rest: wkt.trim_start(),
+        };
+
+        if let Some((prefix, rest)) = parser.rest.split_once(';')
+            && let Some(srid) = prefix.trim().strip_prefix("SRID=")
+        {
+            let found = srid
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| Error::Wkt(e.to_string()))?;
+            if u64::from(found) != CRS {
+                return Err(Error::Srid {
+                    expected: CRS,
+                    found: Some(found),
+                });
+            }
+            parser.rest = rest.trim_start();
+        }
+
+        let tag = parser.word().to_ascii_uppercase();
+        let (tag, has_m) = match tag.strip_suffix('M') {
+            Some(stripped) if stripped == kind => (stripped.to_string(), true),
+            _ => (tag, false),
+        };
+        if tag != kind {
+            return Err(Error::IncompatibleType);
+        }
+
+        let dims = parser.peek_word().to_ascii_uppercase();
+        match (has_m, dims.as_str()) {
+            (false, "M") => {
+                parser.word();
+            }
+            (true, _) => {}
+            _ => return Err(Error::Dimension),
+        }
+        Ok(parser)
+    }
+
+    fn skip_ws(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn peek_word(&mut self) -> &'a str {
+        self.skip_ws();
+        let end = self
+            .rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(self.rest.len());
+        &self.rest[..end]
+    }
+
+    fn word(&mut self) -> &'a str {
+        let word = self.peek_word();
+        self.rest = &self.rest[word.len()..];
+        word
+    }
+
+    fn empty(&mut self) -> bool {
+        if self.peek_word().eq_ignore_ascii_case("EMPTY") {
+            self.word();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(Error::Wkt(format!("expected '{c}' at '{}'", self.rest)))
+        }
+    }
+
+    fn end(&mut self) -> Result<(), Error> {
+        self.skip_ws();
+        match self.rest {
+            "" => Ok(()),
+            rest => Err(Error::Wkt(format!("trailing input '{rest}'"))),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, Error> {
+        self.skip_ws();
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || c == ',' || c == ')')
+            .unwrap_or(self.rest.len());
+        let number = self.rest[..end]
+            .parse()
+            .map_err(|_| Error::Wkt(format!("expected a number at '{}'", self.rest)))?;
+        self.rest = &self.rest[end..];
+        Ok(number)
+    }
+
+    /// A parenthesized list of `x y m` coordinates, or `EMPTY`
+    fn coords<const CRS: u64>(&mut self) -> Result<Vec<CoordM<CRS>>, Error> {
+        let mut coords = vec![];
+        if self.empty() {
+            return Ok(coords);
+        }
+        self.expect('(')?;
+        loop {
+            let (x, y, m) = (self.number()?, self.number()?, self.number()?);
+            coords.push(CoordM { x, y, m });
+            if !self.eat(',') {
+                break;
+            }
+        }
+        match self.expect(')') {
+            // a fourth ordinate
+            Err(_) if self.number().is_ok() => Err(Error::Dimension),
+            res => res.map(|_| coords),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::polygon;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn roundtrip() {
+        let p = PointM::<4326>::from((10.5, 56., 1700000000.25));
+        assert_eq!(p.to_wkt(), "POINT M (10.5 56 1700000000.25)");
+        assert_eq!(PointM::from_wkt(&p.to_wkt()), Ok(p));
+
+        let ls = LineStringM::<4326>::new(vec![(10., 56., 0.).into(), (10.1, 56.1, 60.).into()])
+            .unwrap();
+        assert_eq!(ls.to_wkt(), "LINESTRING M (10 56 0, 10.1 56.1 60)");
+        assert_eq!(LineStringM::from_wkt(&ls.to_wkt()), Ok(ls.clone()));
+
+        let line = ls.lines().next().unwrap();
+        assert_eq!(LineM::from_wkt(&line.to_wkt()), Ok(line));
+
+        let mls = MultiLineStringM(vec![ls.clone(), LineStringM(vec![]), ls]);
+        assert_eq!(
+            mls.to_wkt(),
+            "MULTILINESTRING M ((10 56 0, 10.1 56.1 60), EMPTY, (10 56 0, 10.1 56.1 60))"
+        );
+        assert_eq!(MultiLineStringM::from_wkt(&mls.to_wkt()), Ok(mls));
+
+        let stop = polygon![(x: 10., y: 56.), (x: 10.1, y: 56.), (x: 10., y: 56.1)];
+        assert_eq!(
+            polygon_to_wkt(&stop),
+            "POLYGON ((10 56, 10.1 56, 10 56.1, 10 56))"
+        );
+    }
+
+    #[test]
+    fn postgis_wkt() {
+        let expected =
+            LineStringM::<4326>::new(vec![(10., 56., 0.).into(), (10.1, 56.1, 60.).into()]);
+
+        // select st_asewkt('SRID=4326;LINESTRING M (10 56 0, 10.1 56.1 60)'::geometry)
+        assert_eq!(
+            LineStringM::from_wkt("SRID=4326;LINESTRINGM(10 56 0,10.1 56.1 60)").ok(),
+            expected
+        );
+        // select st_astext(..)
+        assert_eq!(
+            LineStringM::from_wkt("linestring m (10 56 0,10.1 56.1 60)").ok(),
+            expected
+        );
+        assert_eq!(
+            LineStringM::<4326>::from_wkt("SRID=3857;LINESTRINGM(10 56 0,10.1 56.1 60)"),
+            Err(Error::Srid {
+                expected: 4326,
+                found: Some(3857)
+            })
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            PointM::<4326>::from_wkt("LINESTRING M (10 56 0, 10.1 56.1 60)"),
+            Err(Error::IncompatibleType)
+        );
+        // Z or plain 2D geometries have no measure
+        assert_eq!(
+            LineStringM::<4326>::from_wkt("LINESTRING Z (10 56 0, 10.1 56.1 60)"),
+            Err(Error::Dimension)
+        );
+        assert_eq!(
+            LineStringM::<4326>::from_wkt("LINESTRING (10 56, 10.1 56.1)"),
+            Err(Error::Dimension)
+        );
+        assert_eq!(
+            LineStringM::<4326>::from_wkt("LINESTRING M (10 56 0 1, 10.1 56.1 60 1)"),
+            Err(Error::Dimension)
+        );
+        assert_eq!(
+            LineStringM::<4326>::from_wkt("LINESTRING M (10 56 60, 10.1 56.1 0)"),
+            Err(Error::Timestamp)
+        );
+        assert_eq!(
+            LineStringM::<4326>::from_wkt("LINESTRING M (10 56 0)"),
+            Err(Error::NumPoints)
+        );
+        assert!(matches!(
+            LineStringM::<4326>::from_wkt("LINESTRING M (10 56 0, 10.1 56.1 60"),
+            Err(Error::Wkt(_))
+        ));
+    }
+}
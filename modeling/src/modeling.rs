@@ -1,10 +1,11 @@
 use std::ops::Div;
 
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Utc};
 use geo::{Coord, Distance, InterpolatePoint, Vector2DOps, coord, point};
 use geo_traits::{CoordTrait, LineTrait};
 use geo_types::geometry::Triangle;
-use linesonmaps::types::{linem::LineM, pointm::PointM};
+use linesonmaps::types::crs::{Crs, Degree, Epsg};
+use linesonmaps::types::{linem::LineM, measure, pointm::PointM};
 
 pub struct LineTriangle<const CRS: u64> {
     pub triangle: Triangle,
@@ -15,7 +16,10 @@ pub struct LineTriangle<const CRS: u64> {
     pub d: f64,
 }
 
-impl<const CRS: u64> LineTriangle<CRS> {
+impl<const CRS: u64> LineTriangle<CRS>
+where
+    Epsg<CRS>: Crs<Unit = Degree>,
+{
     pub fn point_occupation(&self, ba: f64, bb: f64, bc: f64) -> (DateTime<Utc>, DateTime<Utc>) {
         let probe_vec = probe_vector(&self.line, self.triangle, ba, bb, bc);
 
@@ -112,8 +116,7 @@ pub fn line_to_triangle_pair<const CRS: u64>(
 }
 
 pub fn probe_timestamp(start_m: f64, delta_m: f64, ratio: f64) -> DateTime<Utc> {
-    DateTime::<Utc>::from_timestamp_secs((delta_m * ratio) as i64 + start_m as i64)
-        .expect("ratio er fucked")
+    measure::to_datetime(start_m + delta_m * ratio).expect("ratio er fucked")
 }
 
 pub fn probe_occupation(
@@ -124,11 +127,11 @@ pub fn probe_occupation(
     b: f64,
 ) -> (DateTime<Utc>, DateTime<Utc>) {
     if line_meters == 0. {
-        return (probe_m, probe_m + Duration::seconds(delta_m as i64));
+        return (probe_m, probe_m + measure::to_timedelta(delta_m));
     }
     (
-        probe_m - Duration::seconds((delta_m / line_meters * a) as i64), // formula: timestamp - 'how much earlier the ship arrived due to its length infront of sensor'
-        probe_m + Duration::seconds((delta_m / line_meters * b) as i64), // formula: timestamp + 'how much longer did the ship stay due to its length behind the sensor'
+        probe_m - measure::to_timedelta(delta_m / line_meters * a), // formula: timestamp - 'how much earlier the ship arrived due to its length infront of sensor'
+        probe_m + measure::to_timedelta(delta_m / line_meters * b), // formula: timestamp + 'how much longer did the ship stay due to its length behind the sensor'
     )
 }
 
@@ -140,7 +143,10 @@ pub fn vector_length2(x: f64, y: f64) -> f64 {
     f64::powi(x, 2) + f64::powi(y, 2)
 }
 
-pub fn meters_between_points<const CRS: u64>(origin: PointM<CRS>, destination: PointM<CRS>) -> f64 {
+pub fn meters_between_points<const CRS: u64>(origin: PointM<CRS>, destination: PointM<CRS>) -> f64
+where
+    Epsg<CRS>: Crs<Unit = Degree>,
+{
     geo::algorithm::line_measures::metric_spaces::Geodesic.distance(origin, destination)
 }
 
@@ -192,10 +198,10 @@ mod tests {
         let line = LineM::<4326>::from((coords[0], coords[1]));
 
         let a = line_to_triangle_pair(&line, 1.0, 1.0, 10.0, 10.0);
-        assert_eq!(
-            a.0.point_occupation(1. / 2., 0., 1. / 2.).0.timestamp() as f64 - start_m,
-            (end_m - start_m) / 2.0
-        )
+        // the ship occupies the probe from when its bow, 1 meter ahead, reaches it
+        let ahead = (end_m - start_m) / meters_between_points(line.from, line.to);
+        let occupied = measure::from_datetime(a.0.point_occupation(1. / 2., 0., 1. / 2.).0);
+        assert!((occupied - start_m - ((end_m - start_m) / 2.0 - ahead)).abs() < 1e-3)
     }
 
     #[test]
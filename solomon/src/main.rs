@@ -1,12 +1,11 @@
+use algorithms::lines::SeperateConf;
 use data::loaders::database::DbConn;
 use data::loaders::database::TrajectoryIter;
 use data::loaders::database::insert_sub_traj_inteval;
 use dotenvy::dotenv;
-use geo::Distance;
 use itertools::{self, Itertools};
-use linesonmaps::algo::segmenter::segment_timestamp;
+use linesonmaps::algo::segmenter::TrajectorySplit;
 use linesonmaps::types::linestringm::LineStringM;
-use linesonmaps::types::pointm::PointM;
 
 #[allow(unused)]
 type LineString = LineStringM<4326>;
@@ -28,7 +27,7 @@ fn main() {
 
     // dbg!(cartesian.len());
     let mut conn = DbConn::new().expect("failed to establish database connection");
-    let func = |f, l| dist(f, l, 1000_f64) && time_dist(f, l, 60_f64);
+    let conf = SeperateConf::default();
     let it =
         TrajectoryIter::<500>::new(DbConn::new().expect("failed to establish database connection"))
             .expect("failed to create select iterator");
@@ -37,10 +36,16 @@ fn main() {
         .map(|ts| {
             ts.map(|t| {
                 let z = t
-                    .mmsi
+                    .into_segments(&conf)
                     .into_iter()
-                    .zip_eq(t.trajectory)
-                    .map(|(mmsi, traj)| (mmsi, segment_timestamp(traj, func)))
+                    .map(|(mmsi, splits, _)| {
+                        let intervals = splits
+                            .iter()
+                            .map(TrajectorySplit::interval)
+                            .collect::<Option<Vec<_>>>()
+                            .expect("measure value out of range of DateTime");
+                        (mmsi, intervals)
+                    })
                     .collect_vec();
                 insert_sub_traj_inteval(&mut conn.conn, z)
                     .expect("database error")
@@ -52,12 +57,3 @@ fn main() {
         .expect("failed to process trajectories");
     println!("Hello, world!");
 }
-
-const fn time_dist(first: PointM, second: PointM, thres: f64) -> bool {
-    second.coord.m - first.coord.m < thres
-}
-
-fn dist(first: PointM, second: PointM, thres: f64) -> bool {
-    use geo::algorithm::line_measures::metric_spaces::Geodesic;
-    Geodesic.distance(first, second) < thres
-}
@@ -2,6 +2,7 @@ use chrono::prelude::*;
 use data::tables::Ships;
 use geo_types::Coord;
 use itertools::Itertools;
+use linesonmaps::types::crs::{WEB_MERCATOR, WEB_MERCATOR_EXTENT, WGS84};
 use linesonmaps::types::{coordm::CoordM, linestringm::LineStringM};
 use std::{cmp, sync::Arc};
 
@@ -20,7 +21,9 @@ pub struct PointWTime {
     pub time_stamps: Vec<(DateTime<Utc>, DateTime<Utc>)>,
 }
 
+/// `cell_oc_time` is serialized as chrono does, i.e. `[seconds, nanoseconds]`
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tile {
     pub x: i32,
     pub y: i32,
@@ -108,7 +111,7 @@ pub fn draw_linestring(
         .map(|p| {
             (
                 point_to_grid((p.coord.x, p.coord.y).into(), sampling_zoom_level),
-                DateTime::from_timestamp_secs(p.coord.m as i64).unwrap(),
+                p.time().expect("timestamp should be well within bounds"),
             )
         })
         .tuple_windows()
@@ -195,14 +198,11 @@ pub fn enhance_point(
 }
 
 pub fn point_to_grid(point: Coord<f64>, sampling_zoom_level: i32) -> Point {
-    use std::f64::consts::*;
+    let projected = CoordM::<WGS84>::from((point.x, point.y, 0.)).reproject::<WEB_MERCATOR>();
+    let tiles = 2_f64.powi(sampling_zoom_level);
 
-    let x =
-        (1. / TAU * 2_f64.powi(sampling_zoom_level) * (PI + (point.x * PI / 180.))).floor() as i32;
-    let y = (1. / TAU
-        * 2_f64.powi(sampling_zoom_level)
-        * (PI - ((FRAC_PI_4 + (point.y * PI / 180.) / 2.).tan()).ln()))
-    .floor() as i32;
+    let x = ((WEB_MERCATOR_EXTENT + projected.x) / (2. * WEB_MERCATOR_EXTENT) * tiles).floor() as i32;
+    let y = ((WEB_MERCATOR_EXTENT - projected.y) / (2. * WEB_MERCATOR_EXTENT) * tiles).floor() as i32;
 
     Point { x, y }
 }
@@ -585,4 +585,30 @@ mod tests {
 
         assert_eq!(result.len(), 9);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let tile = Tile {
+            x: 1,
+            y: 2,
+            z: 10,
+            max_draught: Some(6.5),
+            min_sog: None,
+            max_sog: Some(2.0),
+            distinct_ship_count: 3,
+            cell_oc_time: chrono::TimeDelta::milliseconds(5500),
+            min_length: Some(5.0),
+            max_length: Some(5.0),
+            min_width: None,
+            max_width: Some(2.0),
+        };
+        let json = serde_json::to_value(&tile).expect("tile should serialize");
+        assert_eq!(json["cell_oc_time"], serde_json::json!([5, 500_000_000]));
+        assert_eq!(json["min_sog"], serde_json::Value::Null);
+        assert_eq!(
+            serde_json::from_value::<Tile>(json).expect("tile should deserialize"),
+            tile
+        );
+    }
 }